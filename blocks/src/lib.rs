@@ -7,6 +7,7 @@ use crate::shared::{Axis, Direction, Position};
 use collision::Aabb3;
 use cgmath::Point3;
 use lazy_static::lazy_static;
+use std::collections::HashMap;
 
 pub mod material;
 pub use self::material::Material;
@@ -41,9 +42,24 @@ macro_rules! create_ids {
     );
 }
 
+/// The first protocol version of each layout of flattened (1.13+)
+/// block state ids, newest first.
+const FLAT_VERSIONS: [i32; 3] = [477, 401, 393];
+
+fn flat_layout(protocol_version: i32) -> Option<usize> {
+    FLAT_VERSIONS.iter().position(|&version| protocol_version >= version)
+}
+
+#[derive(Default)]
+struct FlatIDMap {
+    blocks: Vec<Option<Block>>,
+    ids: HashMap<Block, usize>,
+}
+
 struct VanillaIDMap {
-    flat: Vec<Option<Block>>,
+    flat: Vec<FlatIDMap>,
     hier: Vec<Option<Block>>,
+    hier_ids: HashMap<Block, usize>,
 }
 
 macro_rules! define_blocks {
@@ -57,6 +73,7 @@ macro_rules! define_blocks {
                 },
                 $(data $datafunc:expr,)*
                 $(offset $offsetfunc:expr,)*
+                $(offsets $offsetsfunc:expr,)*
                 $(material $mat:expr,)*
                 model $model:expr,
                 $(variant $variant:expr,)*
@@ -113,7 +130,7 @@ macro_rules! define_blocks {
             }
 
             #[allow(unused_variables, unreachable_code)]
-            pub fn get_flat_offset(&self) -> Option<usize> {
+            pub fn get_flat_offset(&self, protocol_version: i32) -> Option<usize> {
                 match *self {
                     $(
                         Block::$name {
@@ -123,6 +140,10 @@ macro_rules! define_blocks {
                                 let offset: Option<usize> = ($offsetfunc).map(|v| v);
                                 return offset;
                             )*
+                            $(
+                                let offset: Option<usize> = ($offsetsfunc)(protocol_version);
+                                return offset;
+                            )*
                             $(
                                 let data: Option<usize> = ($datafunc).map(|v| v);
                                 return data;
@@ -134,13 +155,23 @@ macro_rules! define_blocks {
             }
 
             pub fn by_vanilla_id(id: usize, protocol_version: i32) -> Block {
-                if protocol_version >= 404 {
-                    VANILLA_ID_MAP.flat.get(id).and_then(|v| *v).unwrap_or(Block::Missing{})
+                if let Some(layout) = flat_layout(protocol_version) {
+                    VANILLA_ID_MAP.flat[layout].blocks.get(id).and_then(|v| *v).unwrap_or(Block::Missing{})
                 } else {
                     VANILLA_ID_MAP.hier.get(id).and_then(|v| *v).unwrap_or(Block::Missing{})
                 }
             }
 
+            /// The inverse of `by_vanilla_id`, returns `None` if the block
+            /// has no id in the given version.
+            pub fn get_vanilla_id(&self, protocol_version: i32) -> Option<usize> {
+                if let Some(layout) = flat_layout(protocol_version) {
+                    VANILLA_ID_MAP.flat[layout].ids.get(self).cloned()
+                } else {
+                    VANILLA_ID_MAP.hier_ids.get(self).cloned()
+                }
+            }
+
             #[allow(unused_variables, unreachable_code)]
             pub fn get_material(&self) -> Material {
                 match *self {
@@ -255,9 +286,10 @@ macro_rules! define_blocks {
 
         lazy_static! {
             static ref VANILLA_ID_MAP: VanillaIDMap = {
-                let mut blocks_flat = vec![];
+                let mut flat: Vec<FlatIDMap> = FLAT_VERSIONS.iter().map(|_| FlatIDMap::default()).collect();
                 let mut blocks_hier = vec![];
-                let mut flat_id = 0;
+                let mut hier_ids = HashMap::new();
+                let mut flat_id = vec![0; FLAT_VERSIONS.len()];
                 let mut last_internal_id = 0;
                 let mut hier_block_id = 0;
                 $({
@@ -349,7 +381,7 @@ macro_rules! define_blocks {
                             vals.into_iter()
                         }),*
                     );
-                    let mut last_offset: isize = -1;
+                    let mut last_offset = vec![-1isize; FLAT_VERSIONS.len()];
                     for block in iter {
                         let internal_id = block.get_internal_id();
                         let hier_data: Option<usize> = block.get_hierarchical_data();
@@ -364,59 +396,61 @@ macro_rules! define_blocks {
                                 None
                             };
 
-                        let offset = block.get_flat_offset();
-                        if let Some(offset) = offset {
-                            let id = flat_id + offset;
-                            /*
-                            if let Some(vanilla_id) = vanilla_id {
-                                println!("{} block state = {:?} hierarchical {}:{} offset={}", id, block, vanilla_id >> 4, vanilla_id & 0xF, offset);
-                            } else {
-                                println!("{} block state = {:?} hierarchical none, offset={}", id, block, offset);
-                            }
-                            */
-                            if offset as isize > last_offset {
-                                last_offset = offset as isize;
+                        for (layout, &version) in FLAT_VERSIONS.iter().enumerate() {
+                            let offset = match block.get_flat_offset(version) {
+                                Some(offset) => offset,
+                                None => continue,
+                            };
+                            let id = flat_id[layout] + offset;
+                            if offset as isize > last_offset[layout] {
+                                last_offset[layout] = offset as isize;
                             }
 
+                            let blocks_flat = &mut flat[layout].blocks;
                             if blocks_flat.len() <= id {
                                 blocks_flat.resize(id + 1, None);
                             }
                             if blocks_flat[id].is_none() {
                                 blocks_flat[id] = Some(block);
+                                flat[layout].ids.entry(block).or_insert(id);
                             } else {
                                 panic!(
-                                    "Tried to register {:#?} to {} but {:#?} was already registered",
+                                    "Tried to register {:#?} to {} for protocol {} but {:#?} was already registered",
                                     block,
                                     id,
+                                    version,
                                     blocks_flat[id]
                                 );
                             }
+                        }
 
-                            if let Some(vanilla_id) = vanilla_id {
-                                if blocks_hier.len() <= vanilla_id {
-                                    blocks_hier.resize(vanilla_id + 1, None);
-                                }
-                                if blocks_hier[vanilla_id].is_none() {
-                                    blocks_hier[vanilla_id] = Some(block);
-                                } else {
-                                    panic!(
-                                        "Tried to register {:#?} to {} but {:#?} was already registered",
-                                        block,
-                                        id,
-                                        blocks_hier[vanilla_id]
-                                    );
-                                }
+                        if let Some(vanilla_id) = vanilla_id {
+                            if blocks_hier.len() <= vanilla_id {
+                                blocks_hier.resize(vanilla_id + 1, None);
+                            }
+                            if blocks_hier[vanilla_id].is_none() {
+                                blocks_hier[vanilla_id] = Some(block);
+                                hier_ids.entry(block).or_insert(vanilla_id);
+                            } else {
+                                panic!(
+                                    "Tried to register {:#?} to {} but {:#?} was already registered",
+                                    block,
+                                    vanilla_id,
+                                    blocks_hier[vanilla_id]
+                                );
                             }
                         }
                     }
 
                     #[allow(unused_assignments)]
                     {
-                        flat_id += (last_offset + 1) as usize;
+                        for (layout, last_offset) in last_offset.into_iter().enumerate() {
+                            flat_id[layout] += (last_offset + 1) as usize;
+                        }
                     }
                 })+
 
-                VanillaIDMap { flat: blocks_flat, hier: blocks_hier }
+                VanillaIDMap { flat, hier: blocks_hier, hier_ids }
             };
         }
     );
@@ -767,13 +801,24 @@ define_blocks! {
                 NoteBlockInstrument::Bell,
                 NoteBlockInstrument::Guitar,
                 NoteBlockInstrument::Chime,
-                NoteBlockInstrument::Xylophone
+                NoteBlockInstrument::Xylophone,
+                NoteBlockInstrument::IronXylophone,
+                NoteBlockInstrument::CowBell,
+                NoteBlockInstrument::Didgeridoo,
+                NoteBlockInstrument::Bit,
+                NoteBlockInstrument::Banjo,
+                NoteBlockInstrument::Pling
             ],
             note: u8 = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24],
             powered: bool = [true, false],
         },
         data if instrument == NoteBlockInstrument::Harp && note == 0 && powered { Some(0) } else { None },
-        offset Some(instrument.offset() * (25 * 2) + ((note as usize) << 1) + if powered { 0 } else { 1 }),
+        offsets |protocol_version| {
+            if protocol_version < 477 && instrument.offset() >= 10 {
+                return None;
+            }
+            Some(instrument.offset() * (25 * 2) + ((note as usize) << 1) + if powered { 0 } else { 1 })
+        },
         model { ("minecraft", "noteblock") },
     }
     Bed {
@@ -1050,6 +1095,30 @@ define_blocks! {
         model { ("minecraft", variant.as_string()) },
         collision vec![],
     }
+    Cornflower {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "cornflower") },
+        collision vec![],
+    }
+    WitherRose {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "wither_rose") },
+        collision vec![],
+    }
+    LilyOfTheValley {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "lily_of_the_valley") },
+        collision vec![],
+    }
     BrownMushroom {
         props {},
         material Material {
@@ -1413,9 +1482,26 @@ define_blocks! {
                 Rotation::SouthSouthEast
             ],
             waterlogged: bool = [true, false],
+            wood: TreeVariant = [
+                TreeVariant::Oak,
+                TreeVariant::Spruce,
+                TreeVariant::Birch,
+                TreeVariant::Acacia,
+                TreeVariant::Jungle,
+                TreeVariant::DarkOak
+            ],
+        },
+        data if !waterlogged && wood == TreeVariant::Oak { Some(rotation.data()) } else { None },
+        offsets |protocol_version| {
+            let offset = rotation.data() * 2 + if waterlogged { 0 } else { 1 };
+            if protocol_version >= 477 {
+                Some(wood.sign_offset() * (16 * 2) + offset)
+            } else if wood == TreeVariant::Oak {
+                Some(offset)
+            } else {
+                None
+            }
         },
-        data if !waterlogged { Some(rotation.data()) } else { None },
-        offset Some(rotation.data() * 2 + if waterlogged { 0 } else { 1 }),
         material material::INVISIBLE,
         model { ("minecraft", "standing_sign") },
         collision vec![],
@@ -1516,9 +1602,26 @@ define_blocks! {
                 Direction::East
             ],
             waterlogged: bool = [true, false],
+            wood: TreeVariant = [
+                TreeVariant::Oak,
+                TreeVariant::Spruce,
+                TreeVariant::Birch,
+                TreeVariant::Acacia,
+                TreeVariant::Jungle,
+                TreeVariant::DarkOak
+            ],
+        },
+        data if !waterlogged && wood == TreeVariant::Oak { Some(facing.index()) } else { None },
+        offsets |protocol_version| {
+            let offset = if waterlogged { 0 } else { 1 } + facing.horizontal_offset() * 2;
+            if protocol_version >= 477 {
+                Some(wood.sign_offset() * (4 * 2) + offset)
+            } else if wood == TreeVariant::Oak {
+                Some(offset)
+            } else {
+                None
+            }
         },
-        data if !waterlogged { Some(facing.index()) } else { None },
-        offset Some(if waterlogged { 0 } else { 1 } + facing.horizontal_offset() * 2),
         material material::INVISIBLE,
         model { ("minecraft", "wall_sign") },
         variant format!("facing={}", facing.as_string()),
@@ -2938,32 +3041,12 @@ define_blocks! {
             waterlogged: bool = [true, false],
         },
         data if !north && !south && !east && !west && !up && !waterlogged { Some(variant.data()) } else { None },
-        offset Some(if west { 0 } else { 1<<0 } +
-                    if waterlogged { 0 } else { 1<<1 } +
-                    if up { 0 } else { 1<<2 } +
-                    if south { 0 } else { 1<<3 } +
-                    if north { 0 } else { 1<<4 } +
-                    if east { 0 } else { 1<<5 } +
+        offset Some(wall_offset(up, north, south, west, east, waterlogged) +
                     if variant == CobblestoneWallVariant::Normal { 0 } else { 1<<6 }),
         material material::NON_SOLID,
         model { ("minecraft", format!("{}_wall", variant.as_string())) },
         update_state (world, pos) => {
-            let f = |block| match block {
-                Block::CobblestoneWall{..} |
-                Block::FenceGate{..} |
-                Block::SpruceFenceGate{..} |
-                Block::BirchFenceGate{..} |
-                Block::JungleFenceGate{..} |
-                Block::DarkOakFenceGate{..} |
-                Block::AcaciaFenceGate{..} => true,
-                _ => false,
-            };
-
-            let (north, south, west, east) = can_connect_sides(world, pos, &f);
-            let up = !(match world.get_block(pos.shift(Direction::Up)) {
-                Block::Air{..} => true,
-                _ => false,
-            }) || !((north && south && !west && !east) || (!north && !south && west && east));
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
             Block::CobblestoneWall{up, north, south, west, east, variant, waterlogged}
         },
         multipart (key, val) => match key {
@@ -2999,12 +3082,15 @@ define_blocks! {
                 FlowerPotVariant::OrangeTulip,
                 FlowerPotVariant::WhiteTulip,
                 FlowerPotVariant::PinkTulip,
-                FlowerPotVariant::Oxeye
+                FlowerPotVariant::Oxeye,
+                FlowerPotVariant::Cornflower,
+                FlowerPotVariant::LilyOfTheValley,
+                FlowerPotVariant::WitherRose
             ],
             legacy_data: u8 = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
         },
         data if contents == FlowerPotVariant::Empty { Some(legacy_data as usize) } else { None },
-        offset if legacy_data != 0 { None } else { Some(contents.offset()) },
+        offsets |protocol_version| if legacy_data != 0 { None } else { contents.offset(protocol_version) },
         material material::NON_SOLID,
         model { ("minecraft", "flower_pot") },
     }
@@ -4000,7 +4086,9 @@ define_blocks! {
             type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
             variant: StoneSlabVariant = [
                 StoneSlabVariant::Stone,
+                StoneSlabVariant::SmoothStone,
                 StoneSlabVariant::Sandstone,
+                StoneSlabVariant::CutSandstone,
                 StoneSlabVariant::PetrifiedWood,
                 StoneSlabVariant::Cobblestone,
                 StoneSlabVariant::Brick,
@@ -4008,12 +4096,15 @@ define_blocks! {
                 StoneSlabVariant::NetherBrick,
                 StoneSlabVariant::Quartz,
                 StoneSlabVariant::RedSandstone,
+                StoneSlabVariant::CutRedSandstone,
                 StoneSlabVariant::Purpur
             ],
             waterlogged: bool = [true, false],
         },
         data None::<usize>,
-        offset Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2 + variant.offset() * (2 * 3)),
+        offsets |protocol_version| {
+            variant.offset(protocol_version).map(|offset| if waterlogged { 0 } else { 1 } + type_.offset() * 2 + offset * (2 * 3))
+        },
         material material::NON_SOLID,
         model { ("minecraft", format!("{}_slab", variant.as_string()) ) },
         variant format!("type={}", type_.as_string()),
@@ -5357,7 +5448,7 @@ define_blocks! {
             ],
         },
         data None::<usize>,
-        offset Some(if waterlogged { 0 } else { 1 } + variant.offset() * 2),
+        offsets |protocol_version| variant.offset_since(protocol_version).map(|offset| if waterlogged { 0 } else { 1 } + offset * 2),
         model { ("minecraft", variant.as_string()) },
     }
     CoralWallFan {
@@ -5383,9 +5474,9 @@ define_blocks! {
             ],
         },
         data None::<usize>,
-        offset Some(if waterlogged { 0 } else { 1 } +
+        offsets |protocol_version| variant.offset_since(protocol_version).map(|offset| if waterlogged { 0 } else { 1 } +
                     facing.horizontal_offset() * 2 +
-                    variant.offset() * (2 * 4)),
+                    offset * (2 * 4)),
         model { ("minecraft", format!("{}_wall_fan", variant.as_string())) },
     }
     CoralFan {
@@ -5405,8 +5496,8 @@ define_blocks! {
             ],
         },
         data None::<usize>,
-        offset Some(if waterlogged { 0 } else { 1 } +
-                    variant.offset() * 2),
+        offsets |protocol_version| variant.offset_since(protocol_version).map(|offset| if waterlogged { 0 } else { 1 } +
+                    offset * 2),
         model { ("minecraft", format!("{}_fan", variant.as_string())) },
     }
     SeaPickle {
@@ -5435,6 +5526,37 @@ define_blocks! {
         material material::NON_SOLID,
         model { ("minecraft", "conduit") },
     }
+    BambooSapling {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "bamboo_sapling") },
+        collision vec![],
+    }
+    Bamboo {
+        props {
+            age: u8 = [0, 1],
+            leaves: BambooLeaves = [
+                BambooLeaves::None,
+                BambooLeaves::Small,
+                BambooLeaves::Large
+            ],
+            stage: u8 = [0, 1],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some((stage as usize) + leaves.offset() * 2 + (age as usize) * (2 * 3)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "bamboo") },
+        variant format!("age={},leaves={}", age, leaves.as_string()),
+    }
+    PottedBamboo {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "potted_bamboo") },
+    }
     VoidAir {
         props {},
         data None::<usize>,
@@ -5477,88 +5599,1154 @@ define_blocks! {
         offset None,
         model { ("steven", "missing_block") },
     }
-    StructureBlock {
+    PolishedGraniteStairs {
         props {
-            mode: StructureBlockMode = [
-                StructureBlockMode::Save,
-                StructureBlockMode::Load,
-                StructureBlockMode::Corner,
-                StructureBlockMode::Data
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
             ],
+            waterlogged: bool = [true, false],
         },
-        data Some(mode.data()),
-        model { ("minecraft", "structure_block") },
-        variant format!("mode={}", mode.as_string()),
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_granite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::PolishedGraniteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
     }
-
-    Missing {
-        props {},
+    SmoothRedSandstoneStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
         data None::<usize>,
-        model { ("steven", "missing_block") },
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_red_sandstone_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::SmoothRedSandstoneStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
     }
-}
-
-fn can_burn<W: WorldAccess>(world: &W, pos: Position) -> bool {
-    match world.get_block(pos) {
-        Block::Planks{..} |
-        Block::DoubleWoodenSlab{..} |
-        Block::WoodenSlab{..} |
-        Block::FenceGate{..} |
-        Block::SpruceFenceGate{..} |
-        Block::BirchFenceGate{..} |
-        Block::JungleFenceGate{..} |
-        Block::DarkOakFenceGate{..} |
-        Block::AcaciaFenceGate{..} |
-        Block::Fence{..} |
-        Block::SpruceFence{..} |
-        Block::BirchFence{..} |
-        Block::JungleFence{..} |
-        Block::DarkOakFence{..} |
-        Block::AcaciaFence{..} |
-        Block::OakStairs{..} |
-        Block::BirchStairs{..} |
-        Block::SpruceStairs{..} |
-        Block::JungleStairs{..} |
-        Block::AcaciaStairs{..} |
-        Block::DarkOakStairs{..} |
-        Block::Log{..} |
-        Block::Log2{..} |
-        Block::Leaves{..} |
-        Block::Leaves2{..} |
-        Block::BookShelf{..} |
-        Block::TNT{..} |
-        Block::TallGrass{..} |
-        Block::DoublePlant{..} |
-        Block::YellowFlower{..} |
-        Block::RedFlower{..} |
-        Block::DeadBush{..} |
-        Block::Wool{..} |
-        Block::Vine{..} |
-        Block::CoalBlock{..} |
-        Block::HayBlock{..} |
-        Block::Carpet{..} => true,
-        _ => false,
+    MossyStoneBrickStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "mossy_stone_brick_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::MossyStoneBrickStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
     }
-}
-
-fn is_snowy<W: WorldAccess>(world: &W, pos: Position) -> bool {
-    match world.get_block(pos.shift(Direction::Up)) {
-        Block::Snow{..} | Block::SnowLayer{..} => true,
-        _ => false,
+    PolishedDioriteStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_diorite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::PolishedDioriteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
     }
-}
-
-fn can_connect_sides<F: Fn(Block) -> bool, W: WorldAccess>(world: &W, pos: Position, f: &F) -> (bool, bool, bool, bool) {
-    (can_connect(world, pos.shift(Direction::North), f),
-     can_connect(world, pos.shift(Direction::South), f),
-     can_connect(world, pos.shift(Direction::West), f),
-     can_connect(world, pos.shift(Direction::East), f))
-}
-
-fn can_connect<F: Fn(Block) -> bool, W: WorldAccess>(world: &W, pos: Position, f: &F) -> bool {
-    let block = world.get_block(pos);
-    f(block) || (block.get_material().renderable && block.get_material().should_cull_against)
-}
+    MossyCobblestoneStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "mossy_cobblestone_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::MossyCobblestoneStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    EndStoneBrickStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "end_stone_brick_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::EndStoneBrickStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    PlainStoneStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "stone_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::PlainStoneStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    SmoothSandstoneStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_sandstone_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::SmoothSandstoneStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    SmoothQuartzStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_quartz_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::SmoothQuartzStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    GraniteStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "granite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::GraniteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    AndesiteStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "andesite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::AndesiteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    RedNetherBrickStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "red_nether_brick_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::RedNetherBrickStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    PolishedAndesiteStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_andesite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::PolishedAndesiteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    DioriteStairs {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            half: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom],
+            shape: StairShape = [
+                StairShape::Straight,
+                StairShape::InnerLeft,
+                StairShape::InnerRight,
+                StairShape::OuterLeft,
+                StairShape::OuterRight
+            ],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { stair_offset(facing, half, shape, waterlogged) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "diorite_stairs") },
+        variant format!("facing={},half={},shape={}", facing.as_string(), half.as_string(), shape.as_string()),
+        collision stair_collision(facing, shape, half),
+        update_state (world, pos) => Block::DioriteStairs{facing, half, shape: update_stair_shape(world, pos, facing), waterlogged},
+    }
+    PolishedGraniteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_granite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    SmoothRedSandstoneSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_red_sandstone_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    MossyStoneBrickSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "mossy_stone_brick_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    PolishedDioriteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_diorite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    MossyCobblestoneSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "mossy_cobblestone_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    EndStoneBrickSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "end_stone_brick_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    SmoothSandstoneSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_sandstone_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    SmoothQuartzSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "smooth_quartz_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    GraniteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "granite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    AndesiteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "andesite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    RedNetherBrickSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "red_nether_brick_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    PolishedAndesiteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "polished_andesite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    DioriteSlab {
+        props {
+            type_: BlockHalf = [BlockHalf::Top, BlockHalf::Bottom, BlockHalf::Double],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + type_.offset() * 2) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "diorite_slab") },
+        variant format!("type={}", type_.as_string()),
+        collision slab_collision(type_),
+    }
+    BrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::BrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    PrismarineWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "prismarine_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::PrismarineWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    RedSandstoneWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "red_sandstone_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::RedSandstoneWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    MossyStoneBrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "mossy_stone_brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::MossyStoneBrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    GraniteWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "granite_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::GraniteWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    StoneBrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "stone_brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::StoneBrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    NetherBrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "nether_brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::NetherBrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    AndesiteWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "andesite_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::AndesiteWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    RedNetherBrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "red_nether_brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::RedNetherBrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    SandstoneWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "sandstone_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::SandstoneWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    EndStoneBrickWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "end_stone_brick_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::EndStoneBrickWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    DioriteWall {
+        props {
+            up: bool = [false, true],
+            north: bool = [false, true],
+            south: bool = [false, true],
+            west: bool = [false, true],
+            east: bool = [false, true],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(wall_offset(up, north, south, west, east, waterlogged)) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "diorite_wall") },
+        update_state (world, pos) => {
+            let (up, north, south, west, east) = update_wall_connections(world, pos);
+            Block::DioriteWall{up, north, south, west, east, waterlogged}
+        },
+        multipart (key, val) => match key {
+            "up" => up == (val == "true"),
+            "north" => north == (val == "true"),
+            "south" => south == (val == "true"),
+            "east" => east == (val == "true"),
+            "west" => west == (val == "true"),
+            _ => false,
+        },
+    }
+    Scaffolding {
+        props {
+            bottom: bool = [true, false],
+            distance: u8 = [0, 1, 2, 3, 4, 5, 6, 7],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + (distance as usize) * 2 + if bottom { 0 } else { 2 * 8 }) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "scaffolding") },
+        variant format!("bottom={}", bottom),
+    }
+    Loom {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(facing.horizontal_offset()) } else { None },
+        model { ("minecraft", "loom") },
+        variant format!("facing={}", facing.as_string()),
+    }
+    Barrel {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+                Direction::Up,
+                Direction::Down
+            ],
+            open: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if open { 0 } else { 1 } + facing.offset() * 2) } else { None },
+        model { ("minecraft", "barrel") },
+        variant format!("facing={},open={}", facing.as_string(), open),
+    }
+    Smoker {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            lit: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if lit { 0 } else { 1 } + facing.horizontal_offset() * 2) } else { None },
+        model { ("minecraft", "smoker") },
+        variant format!("facing={},lit={}", facing.as_string(), lit),
+    }
+    BlastFurnace {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            lit: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if lit { 0 } else { 1 } + facing.horizontal_offset() * 2) } else { None },
+        model { ("minecraft", "blast_furnace") },
+        variant format!("facing={},lit={}", facing.as_string(), lit),
+    }
+    CartographyTable {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        model { ("minecraft", "cartography_table") },
+    }
+    FletchingTable {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        model { ("minecraft", "fletching_table") },
+    }
+    Grindstone {
+        props {
+            face: AttachedFace = [
+                AttachedFace::Floor,
+                AttachedFace::Wall,
+                AttachedFace::Ceiling
+            ],
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(face.offset() * 4 + facing.horizontal_offset()) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "grindstone") },
+        variant format!("face={},facing={}", face.as_string(), facing.as_string()),
+    }
+    Lectern {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            has_book: bool = [true, false],
+            powered: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(facing.horizontal_offset() * 4 + if has_book { 0 } else { 2 } + if powered { 0 } else { 1 }) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "lectern") },
+        variant format!("facing={}", facing.as_string()),
+    }
+    SmithingTable {
+        props {},
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(0) } else { None },
+        model { ("minecraft", "smithing_table") },
+    }
+    Stonecutter {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(facing.horizontal_offset()) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "stonecutter") },
+        variant format!("facing={}", facing.as_string()),
+    }
+    Bell {
+        props {
+            attachment: BellAttachment = [
+                BellAttachment::Floor,
+                BellAttachment::Ceiling,
+                BellAttachment::SingleWall,
+                BellAttachment::DoubleWall
+            ],
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(attachment.offset() * 4 + facing.horizontal_offset()) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "bell") },
+        variant format!("attachment={},facing={}", attachment.as_string(), facing.as_string()),
+    }
+    Lantern {
+        props {
+            hanging: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if hanging { 0 } else { 1 }) } else { None },
+        material Material {
+            emitted_light: 15,
+            ..material::NON_SOLID
+        },
+        model { ("minecraft", "lantern") },
+        variant format!("hanging={}", hanging),
+    }
+    Campfire {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ],
+            lit: bool = [true, false],
+            signal_fire: bool = [true, false],
+            waterlogged: bool = [true, false],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(if waterlogged { 0 } else { 1 } + if signal_fire { 0 } else { 2 } + if lit { 0 } else { 4 } + facing.horizontal_offset() * 8) } else { None },
+        material Material {
+            emitted_light: if lit { 15 } else { 0 },
+            ..material::NON_SOLID
+        },
+        model { ("minecraft", "campfire") },
+        variant format!("facing={},lit={}", facing.as_string(), lit),
+    }
+    SweetBerryBush {
+        props {
+            age: u8 = [0, 1, 2, 3],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(age as usize) } else { None },
+        material material::NON_SOLID,
+        model { ("minecraft", "sweet_berry_bush") },
+        variant format!("age={}", age),
+        collision vec![],
+    }
+    StructureBlock {
+        props {
+            mode: StructureBlockMode = [
+                StructureBlockMode::Save,
+                StructureBlockMode::Load,
+                StructureBlockMode::Corner,
+                StructureBlockMode::Data
+            ],
+        },
+        data Some(mode.data()),
+        model { ("minecraft", "structure_block") },
+        variant format!("mode={}", mode.as_string()),
+    }
+    Jigsaw {
+        props {
+            facing: Direction = [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+                Direction::Up,
+                Direction::Down
+            ],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(facing.offset()) } else { None },
+        model { ("minecraft", "jigsaw") },
+        variant format!("facing={}", facing.as_string()),
+    }
+    Composter {
+        props {
+            level: u8 = [0, 1, 2, 3, 4, 5, 6, 7, 8],
+        },
+        data None::<usize>,
+        offsets |protocol_version| if protocol_version >= 477 { Some(level as usize) } else { None },
+        model { ("minecraft", "composter") },
+        variant format!("level={}", level),
+    }
+
+    Missing {
+        props {},
+        data None::<usize>,
+        model { ("steven", "missing_block") },
+    }
+}
+
+fn can_burn<W: WorldAccess>(world: &W, pos: Position) -> bool {
+    match world.get_block(pos) {
+        Block::Planks{..} |
+        Block::DoubleWoodenSlab{..} |
+        Block::WoodenSlab{..} |
+        Block::FenceGate{..} |
+        Block::SpruceFenceGate{..} |
+        Block::BirchFenceGate{..} |
+        Block::JungleFenceGate{..} |
+        Block::DarkOakFenceGate{..} |
+        Block::AcaciaFenceGate{..} |
+        Block::Fence{..} |
+        Block::SpruceFence{..} |
+        Block::BirchFence{..} |
+        Block::JungleFence{..} |
+        Block::DarkOakFence{..} |
+        Block::AcaciaFence{..} |
+        Block::OakStairs{..} |
+        Block::BirchStairs{..} |
+        Block::SpruceStairs{..} |
+        Block::JungleStairs{..} |
+        Block::AcaciaStairs{..} |
+        Block::DarkOakStairs{..} |
+        Block::Log{..} |
+        Block::Log2{..} |
+        Block::Leaves{..} |
+        Block::Leaves2{..} |
+        Block::BookShelf{..} |
+        Block::TNT{..} |
+        Block::TallGrass{..} |
+        Block::DoublePlant{..} |
+        Block::YellowFlower{..} |
+        Block::RedFlower{..} |
+        Block::DeadBush{..} |
+        Block::Wool{..} |
+        Block::Vine{..} |
+        Block::CoalBlock{..} |
+        Block::HayBlock{..} |
+        Block::Carpet{..} => true,
+        _ => false,
+    }
+}
+
+fn is_snowy<W: WorldAccess>(world: &W, pos: Position) -> bool {
+    match world.get_block(pos.shift(Direction::Up)) {
+        Block::Snow{..} | Block::SnowLayer{..} => true,
+        _ => false,
+    }
+}
+
+fn can_connect_sides<F: Fn(Block) -> bool, W: WorldAccess>(world: &W, pos: Position, f: &F) -> (bool, bool, bool, bool) {
+    (can_connect(world, pos.shift(Direction::North), f),
+     can_connect(world, pos.shift(Direction::South), f),
+     can_connect(world, pos.shift(Direction::West), f),
+     can_connect(world, pos.shift(Direction::East), f))
+}
+
+fn can_connect<F: Fn(Block) -> bool, W: WorldAccess>(world: &W, pos: Position, f: &F) -> bool {
+    let block = world.get_block(pos);
+    f(block) || (block.get_material().renderable && block.get_material().should_cull_against)
+}
 
 fn can_connect_fence(block: Block) -> bool {
     match block {
@@ -5578,6 +6766,49 @@ fn can_connect_fence(block: Block) -> bool {
     }
 }
 
+fn can_connect_wall(block: Block) -> bool {
+    match block {
+        Block::CobblestoneWall{..} |
+        Block::BrickWall{..} |
+        Block::PrismarineWall{..} |
+        Block::RedSandstoneWall{..} |
+        Block::MossyStoneBrickWall{..} |
+        Block::GraniteWall{..} |
+        Block::StoneBrickWall{..} |
+        Block::NetherBrickWall{..} |
+        Block::AndesiteWall{..} |
+        Block::RedNetherBrickWall{..} |
+        Block::SandstoneWall{..} |
+        Block::EndStoneBrickWall{..} |
+        Block::DioriteWall{..} |
+        Block::FenceGate{..} |
+        Block::SpruceFenceGate{..} |
+        Block::BirchFenceGate{..} |
+        Block::JungleFenceGate{..} |
+        Block::DarkOakFenceGate{..} |
+        Block::AcaciaFenceGate{..} => true,
+        _ => false,
+    }
+}
+
+fn update_wall_connections<W: WorldAccess>(world: &W, pos: Position) -> (bool, bool, bool, bool, bool) {
+    let (north, south, west, east) = can_connect_sides(world, pos, &can_connect_wall);
+    let up = !(match world.get_block(pos.shift(Direction::Up)) {
+        Block::Air{..} => true,
+        _ => false,
+    }) || !((north && south && !west && !east) || (!north && !south && west && east));
+    (up, north, south, west, east)
+}
+
+fn wall_offset(up: bool, north: bool, south: bool, west: bool, east: bool, waterlogged: bool) -> usize {
+    (if west { 0 } else { 1<<0 }) +
+    if waterlogged { 0 } else { 1<<1 } +
+    if up { 0 } else { 1<<2 } +
+    if south { 0 } else { 1<<3 } +
+    if north { 0 } else { 1<<4 } +
+    if east { 0 } else { 1<<5 }
+}
+
 fn can_connect_glasspane(block: Block) -> bool {
     match block {
         Block::Glass{..} |
@@ -5900,7 +7131,21 @@ fn get_stair_info<W: WorldAccess>(world: &W, pos: Position) -> Option<(Direction
         Block::AcaciaStairs{facing, half, ..} |
         Block::DarkOakStairs{facing, half, ..} |
         Block::RedSandstoneStairs{facing, half, ..} |
-        Block::PurpurStairs{facing, half, ..} => Some((facing, half)),
+        Block::PurpurStairs{facing, half, ..} |
+        Block::PolishedGraniteStairs{facing, half, ..} |
+        Block::SmoothRedSandstoneStairs{facing, half, ..} |
+        Block::MossyStoneBrickStairs{facing, half, ..} |
+        Block::PolishedDioriteStairs{facing, half, ..} |
+        Block::MossyCobblestoneStairs{facing, half, ..} |
+        Block::EndStoneBrickStairs{facing, half, ..} |
+        Block::PlainStoneStairs{facing, half, ..} |
+        Block::SmoothSandstoneStairs{facing, half, ..} |
+        Block::SmoothQuartzStairs{facing, half, ..} |
+        Block::GraniteStairs{facing, half, ..} |
+        Block::AndesiteStairs{facing, half, ..} |
+        Block::RedNetherBrickStairs{facing, half, ..} |
+        Block::PolishedAndesiteStairs{facing, half, ..} |
+        Block::DioriteStairs{facing, half, ..} => Some((facing, half)),
         _ => None,
     }
 }
@@ -6142,6 +7387,12 @@ pub enum NoteBlockInstrument {
     Guitar,
     Chime,
     Xylophone,
+    IronXylophone,
+    CowBell,
+    Didgeridoo,
+    Bit,
+    Banjo,
+    Pling,
 }
 
 impl NoteBlockInstrument {
@@ -6157,6 +7408,12 @@ impl NoteBlockInstrument {
             NoteBlockInstrument::Guitar => "guitar",
             NoteBlockInstrument::Chime => "chime",
             NoteBlockInstrument::Xylophone => "xylophone",
+            NoteBlockInstrument::IronXylophone => "iron_xylophone",
+            NoteBlockInstrument::CowBell => "cow_bell",
+            NoteBlockInstrument::Didgeridoo => "didgeridoo",
+            NoteBlockInstrument::Bit => "bit",
+            NoteBlockInstrument::Banjo => "banjo",
+            NoteBlockInstrument::Pling => "pling",
         }
     }
 
@@ -6172,6 +7429,12 @@ impl NoteBlockInstrument {
             NoteBlockInstrument::Guitar => 7,
             NoteBlockInstrument::Chime => 8,
             NoteBlockInstrument::Xylophone => 9,
+            NoteBlockInstrument::IronXylophone => 10,
+            NoteBlockInstrument::CowBell => 11,
+            NoteBlockInstrument::Didgeridoo => 12,
+            NoteBlockInstrument::Bit => 13,
+            NoteBlockInstrument::Banjo => 14,
+            NoteBlockInstrument::Pling => 15,
         }
     }
 }
@@ -6617,7 +7880,9 @@ impl PistonType {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StoneSlabVariant {
     Stone,
+    SmoothStone,
     Sandstone,
+    CutSandstone,
     PetrifiedWood,
     Cobblestone,
     Brick,
@@ -6625,6 +7890,7 @@ pub enum StoneSlabVariant {
     NetherBrick,
     Quartz,
     RedSandstone,
+    CutRedSandstone,
     Purpur,
 }
 
@@ -6632,7 +7898,9 @@ impl StoneSlabVariant {
     pub fn as_string(self) -> &'static str {
         match self {
             StoneSlabVariant::Stone => "stone",
+            StoneSlabVariant::SmoothStone => "smooth_stone",
             StoneSlabVariant::Sandstone => "sandstone",
+            StoneSlabVariant::CutSandstone => "cut_sandstone",
             StoneSlabVariant::PetrifiedWood => "wood_old",
             StoneSlabVariant::Cobblestone => "cobblestone",
             StoneSlabVariant::Brick => "brick",
@@ -6640,6 +7908,7 @@ impl StoneSlabVariant {
             StoneSlabVariant::NetherBrick => "nether_brick",
             StoneSlabVariant::Quartz => "quartz",
             StoneSlabVariant::RedSandstone => "red_sandstone",
+            StoneSlabVariant::CutRedSandstone => "cut_red_sandstone",
             StoneSlabVariant::Purpur => "purpur",
         }
     }
@@ -6656,21 +7925,45 @@ impl StoneSlabVariant {
             StoneSlabVariant::StoneBrick => 5,
             StoneSlabVariant::NetherBrick => 6,
             StoneSlabVariant::Quartz => 7,
+            StoneSlabVariant::SmoothStone |
+            StoneSlabVariant::CutSandstone |
+            StoneSlabVariant::CutRedSandstone => panic!("StoneSlabVariant {:?} has no data (1.14+ only)", self),
         }
     }
 
-    fn offset(self) -> usize {
-        match self {
-            StoneSlabVariant::Stone => 0,
-            StoneSlabVariant::Sandstone => 1,
-            StoneSlabVariant::PetrifiedWood => 2,
-            StoneSlabVariant::Cobblestone => 3,
-            StoneSlabVariant::Brick => 4,
-            StoneSlabVariant::StoneBrick => 5,
-            StoneSlabVariant::NetherBrick => 6,
-            StoneSlabVariant::Quartz => 7,
-            StoneSlabVariant::RedSandstone => 8,
-            StoneSlabVariant::Purpur => 9,
+    fn offset(self, protocol_version: i32) -> Option<usize> {
+        if protocol_version >= 477 {
+            Some(match self {
+                StoneSlabVariant::Stone => 0,
+                StoneSlabVariant::SmoothStone => 1,
+                StoneSlabVariant::Sandstone => 2,
+                StoneSlabVariant::CutSandstone => 3,
+                StoneSlabVariant::PetrifiedWood => 4,
+                StoneSlabVariant::Cobblestone => 5,
+                StoneSlabVariant::Brick => 6,
+                StoneSlabVariant::StoneBrick => 7,
+                StoneSlabVariant::NetherBrick => 8,
+                StoneSlabVariant::Quartz => 9,
+                StoneSlabVariant::RedSandstone => 10,
+                StoneSlabVariant::CutRedSandstone => 11,
+                StoneSlabVariant::Purpur => 12,
+            })
+        } else {
+            match self {
+                StoneSlabVariant::Stone => Some(0),
+                StoneSlabVariant::Sandstone => Some(1),
+                StoneSlabVariant::PetrifiedWood => Some(2),
+                StoneSlabVariant::Cobblestone => Some(3),
+                StoneSlabVariant::Brick => Some(4),
+                StoneSlabVariant::StoneBrick => Some(5),
+                StoneSlabVariant::NetherBrick => Some(6),
+                StoneSlabVariant::Quartz => Some(7),
+                StoneSlabVariant::RedSandstone => Some(8),
+                StoneSlabVariant::Purpur => Some(9),
+                StoneSlabVariant::SmoothStone |
+                StoneSlabVariant::CutSandstone |
+                StoneSlabVariant::CutRedSandstone => None,
+            }
         }
     }
 }
@@ -6941,6 +8234,59 @@ impl ChestType {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BambooLeaves {
+    None,
+    Small,
+    Large,
+}
+
+impl BambooLeaves {
+    pub fn as_string(self) -> &'static str {
+        match self {
+            BambooLeaves::None => "none",
+            BambooLeaves::Small => "small",
+            BambooLeaves::Large => "large",
+        }
+    }
+
+    pub fn offset(self) -> usize {
+        match self {
+            BambooLeaves::None => 0,
+            BambooLeaves::Small => 1,
+            BambooLeaves::Large => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BellAttachment {
+    Floor,
+    Ceiling,
+    SingleWall,
+    DoubleWall,
+}
+
+impl BellAttachment {
+    pub fn as_string(self) -> &'static str {
+        match self {
+            BellAttachment::Floor => "floor",
+            BellAttachment::Ceiling => "ceiling",
+            BellAttachment::SingleWall => "single_wall",
+            BellAttachment::DoubleWall => "double_wall",
+        }
+    }
+
+    pub fn offset(self) -> usize {
+        match self {
+            BellAttachment::Floor => 0,
+            BellAttachment::Ceiling => 1,
+            BellAttachment::SingleWall => 2,
+            BellAttachment::DoubleWall => 3,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StructureBlockMode {
     Save,
@@ -7031,6 +8377,20 @@ impl TreeVariant {
         }
     }
 
+    /// The order signs are listed in, which differs from
+    /// other wooden blocks.
+    pub fn sign_offset(self) -> usize {
+        match self {
+            TreeVariant::Oak => 0,
+            TreeVariant::Spruce => 1,
+            TreeVariant::Birch => 2,
+            TreeVariant::Acacia => 3,
+            TreeVariant::Jungle => 4,
+            TreeVariant::DarkOak => 5,
+            _ => panic!("TreeVariant {:?} has no signs", self),
+        }
+    }
+
     pub fn plank_data(self) -> usize {
         match self {
             TreeVariant::Oak => 0,
@@ -7160,6 +8520,9 @@ pub enum FlowerPotVariant {
     WhiteTulip,
     PinkTulip,
     Oxeye,
+    Cornflower,
+    LilyOfTheValley,
+    WitherRose,
 }
 
 impl FlowerPotVariant {
@@ -7187,11 +8550,16 @@ impl FlowerPotVariant {
             FlowerPotVariant::WhiteTulip => "white_tulip",
             FlowerPotVariant::PinkTulip => "pink_tulip",
             FlowerPotVariant::Oxeye => "oxeye_daisy",
+            FlowerPotVariant::Cornflower => "cornflower",
+            FlowerPotVariant::LilyOfTheValley => "lily_of_the_valley",
+            FlowerPotVariant::WitherRose => "wither_rose",
         }
     }
 
-    pub fn offset(self) -> usize {
-        match self {
+    pub fn offset(self, protocol_version: i32) -> Option<usize> {
+        // 1.14 added its new flowers before the mushrooms
+        let since_1_14 = protocol_version >= 477;
+        Some(match self {
             FlowerPotVariant::Empty => 0,
             FlowerPotVariant::OakSapling => 1,
             FlowerPotVariant::SpruceSapling => 2,
@@ -7210,11 +8578,17 @@ impl FlowerPotVariant {
             FlowerPotVariant::WhiteTulip => 15,
             FlowerPotVariant::PinkTulip => 16,
             FlowerPotVariant::Oxeye => 17,
-            FlowerPotVariant::RedMushroom => 18,
-            FlowerPotVariant::BrownMushroom => 19,
-            FlowerPotVariant::DeadBush => 20,
-            FlowerPotVariant::Cactus => 21,
-        }
+            FlowerPotVariant::Cornflower if since_1_14 => 18,
+            FlowerPotVariant::LilyOfTheValley if since_1_14 => 19,
+            FlowerPotVariant::WitherRose if since_1_14 => 20,
+            FlowerPotVariant::Cornflower |
+            FlowerPotVariant::LilyOfTheValley |
+            FlowerPotVariant::WitherRose => return None,
+            FlowerPotVariant::RedMushroom => if since_1_14 { 21 } else { 18 },
+            FlowerPotVariant::BrownMushroom => if since_1_14 { 22 } else { 19 },
+            FlowerPotVariant::DeadBush => if since_1_14 { 23 } else { 20 },
+            FlowerPotVariant::Cactus => if since_1_14 { 24 } else { 21 },
+        })
     }
 }
 
@@ -7262,5 +8636,52 @@ impl CoralVariant {
             CoralVariant::Horn => 9,
         }
     }
+
+    /// The offset of the coral plants and fans, the dead ones
+    /// were only added in 1.13.1.
+    pub fn offset_since(self, protocol_version: i32) -> Option<usize> {
+        if protocol_version >= 401 {
+            Some(self.offset())
+        } else {
+            self.offset().checked_sub(5)
+        }
+    }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flat_ids() {
+        // Stone keeps its id across the flattened versions
+        assert_eq!(Block::by_vanilla_id(1, 404), Block::Stone{variant: StoneVariant::Normal});
+        assert_eq!(Block::by_vanilla_id(1, 498), Block::Stone{variant: StoneVariant::Normal});
+
+        assert_eq!(Block::by_vanilla_id(8598, 404), Block::StructureBlock{mode: StructureBlockMode::Data});
+        assert_eq!(Block::by_vanilla_id(8599, 404), Block::Missing{});
+        // 1.13 lacked the dead coral plants and fans
+        assert_eq!(Block::by_vanilla_id(8538, 393), Block::StructureBlock{mode: StructureBlockMode::Data});
+        assert_eq!(Block::by_vanilla_id(8539, 393), Block::Missing{});
+        assert_eq!(Block::by_vanilla_id(11262, 498), Block::Composter{level: 0});
+        assert_eq!(Block::by_vanilla_id(11270, 498), Block::Composter{level: 8});
+        assert_eq!(Block::by_vanilla_id(11271, 498), Block::Missing{});
+
+        assert_eq!(Block::Composter{level: 0}.get_vanilla_id(404), None);
+        assert_eq!(Block::Cornflower{}.get_vanilla_id(340), None);
+    }
+
+    #[test]
+    fn flat_ids_round_trip() {
+        for &version in &FLAT_VERSIONS {
+            for id in 0..12000 {
+                let block = Block::by_vanilla_id(id, version);
+                if block == (Block::Missing{}) {
+                    continue;
+                }
+                assert_eq!(block.get_vanilla_id(version), Some(id), "{:?} in {}", block, version);
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The accounts the client has logged in with, kept in `accounts.json`
+// so that switching between them doesn't need the password again.
+//
+// The account in use is still the one set by the `cl_username`,
+// `cl_uuid` and `auth_token` cvars, this only remembers the others.
+
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde_json::{self, json, Value};
+
+use crate::protocol::{self, mojang};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+/// How long, in seconds, an account's token is trusted before it is
+/// validated again.
+pub const REFRESH_INTERVAL: u64 = 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Mojang,
+    Offline,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Mojang => "mojang",
+            Kind::Offline => "offline",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub profile: mojang::Profile,
+    /// When the token was last known to be valid, in seconds since
+    /// the unix epoch.
+    pub last_validated: u64,
+}
+
+impl Account {
+    pub fn kind(&self) -> Kind {
+        if self.profile.offline {
+            Kind::Offline
+        } else {
+            Kind::Mojang
+        }
+    }
+
+    /// Returns whether the token should be validated again, offline
+    /// accounts never have to be.
+    pub fn needs_refresh(&self, now: u64) -> bool {
+        self.kind() == Kind::Mojang && now.saturating_sub(self.last_validated) >= REFRESH_INTERVAL
+    }
+
+    fn from_json(val: &Value) -> Option<Account> {
+        let string = |key: &str| val.get(key).and_then(|v| v.as_str()).map(|v| v.to_owned());
+        let offline = match val.get("kind").and_then(|v| v.as_str())? {
+            "mojang" => false,
+            "offline" => true,
+            _ => return None,
+        };
+        let defaults = mojang::Endpoints::default();
+        Some(Account {
+            profile: mojang::Profile {
+                username: string("username")?,
+                id: string("id")?,
+                access_token: string("access_token").unwrap_or_default(),
+                offline,
+                endpoints: mojang::Endpoints {
+                    auth: string("auth_server").unwrap_or(defaults.auth),
+                    session: string("session_server").unwrap_or(defaults.session),
+                    textures: string("texture_server").unwrap_or(defaults.textures),
+                    texture_keys: string("texture_keys").unwrap_or(defaults.texture_keys),
+                },
+            },
+            last_validated: val.get("last_validated").and_then(|v| v.as_u64()).unwrap_or(0),
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let profile = &self.profile;
+        json!({
+            "kind": self.kind().as_str(),
+            "username": profile.username,
+            "id": profile.id,
+            "access_token": profile.access_token,
+            "last_validated": self.last_validated,
+            "auth_server": profile.endpoints.auth,
+            "session_server": profile.endpoints.session,
+            "texture_server": profile.endpoints.textures,
+            "texture_keys": profile.endpoints.texture_keys,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Accounts {
+    accounts: Vec<Account>,
+}
+
+impl Accounts {
+    /// Loads the saved accounts, if there are any.
+    pub fn load() -> Accounts {
+        match fs::File::open(ACCOUNTS_FILE) {
+            Ok(file) => match serde_json::from_reader(file) {
+                Ok(val) => Accounts::from_json(&val),
+                Err(err) => {
+                    warn!("Failed to read {}: {}", ACCOUNTS_FILE, err);
+                    Accounts::default()
+                },
+            },
+            Err(_) => Accounts::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let res = fs::File::create(ACCOUNTS_FILE)
+            .map_err(protocol::Error::from)
+            .and_then(|mut out| serde_json::to_writer_pretty(&mut out, &self.to_json()).map_err(protocol::Error::from));
+        if let Err(err) = res {
+            warn!("Failed to save {}: {}", ACCOUNTS_FILE, err);
+        }
+    }
+
+    fn from_json(val: &Value) -> Accounts {
+        let accounts = val.get("accounts")
+            .and_then(|v| v.as_array())
+            .map_or_else(Vec::new, |accounts| accounts.iter().filter_map(Account::from_json).collect());
+        Accounts { accounts }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "accounts": self.accounts.iter().map(Account::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Returns the account with the uuid `id`.
+    pub fn find(&self, id: &str) -> Option<&Account> {
+        self.accounts.iter().find(|a| a.profile.id == id)
+    }
+
+    /// Adds the account for a profile that was just logged in or
+    /// refreshed, replacing the previous one for the same player.
+    pub fn insert(&mut self, profile: mojang::Profile) {
+        let account = Account {
+            profile,
+            last_validated: now(),
+        };
+        match self.accounts.iter_mut().find(|a| a.profile.id == account.profile.id) {
+            Some(existing) => *existing = account,
+            None => self.accounts.push(account),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.accounts.retain(|a| a.profile.id != id);
+    }
+
+    /// Returns the profiles whose tokens are due to be validated.
+    pub fn stale_profiles(&self) -> Vec<mojang::Profile> {
+        let now = now();
+        self.accounts.iter()
+            .filter(|a| a.needs_refresh(now))
+            .map(|a| a.profile.clone())
+            .collect()
+    }
+}
+
+/// Refreshes the tokens of `profiles` on another thread, one at a
+/// time. Each profile's id is sent back with the result of its
+/// refresh.
+pub fn refresh_in_background(profiles: Vec<mojang::Profile>, client_token: String) -> mpsc::Receiver<(String, Result<mojang::Profile, protocol::Error>)> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for profile in profiles {
+            let id = profile.id.clone();
+            info!("Refreshing the token for {}", profile.username);
+            if tx.send((id, profile.refresh(&client_token))).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// The current time in seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut accounts = Accounts::default();
+        accounts.insert(mojang::Profile::offline("Steve").unwrap());
+        let mut alex = mojang::Profile::offline("Alex").unwrap();
+        alex.offline = false;
+        alex.access_token = "token".to_owned();
+        alex.endpoints.auth = "https://auth.example.com".to_owned();
+        accounts.insert(alex.clone());
+        // Logging in again replaces the account
+        alex.access_token = "new token".to_owned();
+        accounts.insert(alex.clone());
+        assert_eq!(accounts.accounts().len(), 2);
+
+        let loaded = Accounts::from_json(&accounts.to_json());
+        assert_eq!(loaded.accounts().len(), 2);
+        let steve = &loaded.accounts()[0];
+        assert_eq!(steve.kind(), Kind::Offline);
+        assert_eq!(steve.profile.username, "Steve");
+        let account = loaded.find(&alex.id).unwrap();
+        assert_eq!(account.kind(), Kind::Mojang);
+        assert_eq!(account.profile.access_token, "new token");
+        assert_eq!(account.profile.endpoints, alex.endpoints);
+        assert_eq!(account.last_validated, accounts.find(&alex.id).unwrap().last_validated);
+    }
+
+    #[test]
+    fn stale_accounts() {
+        let mut accounts = Accounts::default();
+        accounts.insert(mojang::Profile::offline("Steve").unwrap());
+        let mut alex = mojang::Profile::offline("Alex").unwrap();
+        alex.offline = false;
+        accounts.insert(alex.clone());
+        assert!(accounts.stale_profiles().is_empty());
+
+        for account in &mut accounts.accounts {
+            account.last_validated -= REFRESH_INTERVAL;
+        }
+        // Offline accounts have nothing to refresh
+        let stale = accounts.stale_profiles();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].id, alex.id);
+
+        accounts.remove(&alex.id);
+        assert!(accounts.find(&alex.id).is_none());
+    }
+}
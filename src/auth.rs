@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::console;
+use crate::protocol::mojang;
 use std::marker::PhantomData;
 
 pub const CL_USERNAME: console::CVar<String> = console::CVar {
@@ -22,6 +23,7 @@ pub const CL_USERNAME: console::CVar<String> = console::CVar {
 to servers."#,
     mutable: false,
     serializable: true,
+    constraint: console::Constraint::NONE,
     default: &|| "".to_owned(),
 };
 
@@ -32,6 +34,7 @@ pub const CL_UUID: console::CVar<String> = console::CVar {
 unlike their username."#,
     mutable: false,
     serializable: true,
+    constraint: console::Constraint::NONE,
     default: &|| "".to_owned(),
 };
 
@@ -42,6 +45,7 @@ pub const AUTH_TOKEN: console::CVar<String> = console::CVar {
 or relogin to this account."#,
     mutable: false,
     serializable: true,
+    constraint: console::Constraint::NONE,
     default: &|| "".to_owned(),
 };
 
@@ -52,12 +56,84 @@ pub const AUTH_CLIENT_TOKEN: console::CVar<String> = console::CVar {
 Used to identify this client vs others."#,
     mutable: false,
     serializable: true,
+    constraint: console::Constraint::NONE,
     default: &|| "".to_owned(),
 };
 
+pub const AUTH_OFFLINE: console::CVar<bool> = console::CVar {
+    ty: PhantomData,
+    name: "auth_offline",
+    description: r#"auth_offline is set when playing with an offline account, which
+skips authentication and can only join offline mode servers."#,
+    mutable: false,
+    serializable: true,
+    constraint: console::Constraint::NONE,
+    default: &|| false,
+};
+
+pub const AUTH_SERVER: console::CVar<String> = console::CVar {
+    ty: PhantomData,
+    name: "auth_server",
+    description: r#"auth_server is the Yggdrasil compatible server accounts log in
+with. Defaults to Mojang's authserver."#,
+    mutable: true,
+    serializable: true,
+    constraint: console::Constraint::NONE,
+    default: &|| mojang::DEFAULT_AUTH_SERVER.to_owned(),
+};
+
+pub const AUTH_SESSION_SERVER: console::CVar<String> = console::CVar {
+    ty: PhantomData,
+    name: "auth_session_server",
+    description: r#"auth_session_server is the session server used to authenticate
+joining servers. Defaults to Mojang's session server."#,
+    mutable: true,
+    serializable: true,
+    constraint: console::Constraint::NONE,
+    default: &|| mojang::DEFAULT_SESSION_SERVER.to_owned(),
+};
+
+pub const AUTH_TEXTURE_SERVER: console::CVar<String> = console::CVar {
+    ty: PhantomData,
+    name: "auth_texture_server",
+    description: r#"auth_texture_server is the url skins are downloaded from, the
+texture's hash is appended to it."#,
+    mutable: true,
+    serializable: true,
+    constraint: console::Constraint::NONE,
+    default: &|| mojang::DEFAULT_TEXTURE_SERVER.to_owned(),
+};
+
+pub const AUTH_TEXTURE_KEYS: console::CVar<String> = console::CVar {
+    ty: PhantomData,
+    name: "auth_texture_keys",
+    description: r#"auth_texture_keys is the key player skins must be signed with, as
+the base64 of the DER encoded key on a single line, or a url serving
+the keys. Defaults to Mojang's."#,
+    mutable: true,
+    serializable: true,
+    constraint: console::Constraint::NONE,
+    default: &|| mojang::DEFAULT_TEXTURE_KEYS.to_owned(),
+};
+
+/// Returns the authentication servers set by the `auth_*` cvars.
+pub fn endpoints(vars: &console::Vars) -> mojang::Endpoints {
+    mojang::Endpoints {
+        auth: vars.get(AUTH_SERVER).clone(),
+        session: vars.get(AUTH_SESSION_SERVER).clone(),
+        textures: vars.get(AUTH_TEXTURE_SERVER).clone(),
+        texture_keys: vars.get(AUTH_TEXTURE_KEYS).clone(),
+    }
+}
+
 pub fn register_vars(vars: &mut console::Vars) {
     vars.register(CL_USERNAME);
     vars.register(CL_UUID);
     vars.register(AUTH_TOKEN);
     vars.register(AUTH_CLIENT_TOKEN);
+    vars.register(AUTH_OFFLINE);
+    vars.register(AUTH_SERVER);
+    vars.register(AUTH_SESSION_SERVER);
+    vars.register(AUTH_TEXTURE_SERVER);
+    vars.register(AUTH_TEXTURE_KEYS);
 }
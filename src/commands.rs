@@ -0,0 +1,277 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The commands that can be typed into the console.
+
+use crate::console;
+use crate::format::{Component, TextComponent};
+use crate::protocol::{capture, packet, proxy};
+use crate::screen;
+use crate::server::resource_pack;
+use crate::settings::{self, Stevenkey};
+
+pub const HELP: console::Command = console::Command {
+    name: "help",
+    usage: "[command]",
+    description: "Lists the commands, or describes one of them",
+    min_args: 0,
+    var_arg: false,
+    run: help,
+};
+
+pub const SET: console::Command = console::Command {
+    name: "set",
+    usage: "<var> <value>",
+    description: "Changes a cvar, typing a cvar's name followed by a value does the same",
+    min_args: 2,
+    var_arg: true,
+    run: set,
+};
+
+pub const GET: console::Command = console::Command {
+    name: "get",
+    usage: "<var>",
+    description: "Shows the value of a cvar, typing a cvar's name on its own does the same",
+    min_args: 1,
+    var_arg: true,
+    run: get,
+};
+
+pub const CONNECT: console::Command = console::Command {
+    name: "connect",
+    usage: "<address>",
+    description: "Connects to a server",
+    min_args: 1,
+    var_arg: false,
+    run: connect,
+};
+
+pub const DISCONNECT: console::Command = console::Command {
+    name: "disconnect",
+    usage: "",
+    description: "Disconnects from the current server",
+    min_args: 0,
+    var_arg: false,
+    run: disconnect,
+};
+
+pub const SAY: console::Command = console::Command {
+    name: "say",
+    usage: "<message>",
+    description: "Sends a chat message, or runs a server command when it starts with /",
+    min_args: 1,
+    var_arg: false,
+    run: say,
+};
+
+pub const RELOAD: console::Command = console::Command {
+    name: "reload",
+    usage: "",
+    description: "Reloads the resource packs and shaders",
+    min_args: 0,
+    var_arg: false,
+    run: reload,
+};
+
+pub const BIND: console::Command = console::Command {
+    name: "bind",
+    usage: "[key] [action]",
+    description: "Binds a key to an action, or shows what keys are bound to",
+    min_args: 0,
+    var_arg: false,
+    run: bind,
+};
+
+pub const CAPTURE: console::Command = console::Command {
+    name: "capture",
+    usage: "<file> [protocol version]",
+    description: "Decodes a packet capture recorded with cl_packet_capture, listing the packets that fail. Uses the version recorded in the capture unless one is given",
+    min_args: 1,
+    var_arg: false,
+    run: capture,
+};
+
+pub fn register_commands(commands: &mut console::Commands) {
+    commands.register(HELP);
+    commands.register(SET);
+    commands.register(GET);
+    commands.register(CONNECT);
+    commands.register(DISCONNECT);
+    commands.register(SAY);
+    commands.register(RELOAD);
+    commands.register(BIND);
+    commands.register(CAPTURE);
+}
+
+/// Runs a line typed into the console, printing the output of each
+/// of its commands.
+pub fn execute(game: &mut crate::Game, line: &str) {
+    let statements = match console::parse(line) {
+        Ok(val) => val,
+        Err(err) => {
+            game.console.lock().unwrap().print_error(&err);
+            return;
+        },
+    };
+    for words in statements {
+        let res = run(game, &words[0], &words[1..]);
+        let mut console = game.console.lock().unwrap();
+        match res {
+            Ok(out) => for line in out.lines() {
+                console.print(Component::Text(TextComponent::new(line)));
+            },
+            Err(err) => console.print_error(&err),
+        }
+    }
+}
+
+fn run(game: &mut crate::Game, name: &str, args: &[String]) -> Result<String, String> {
+    let (run, min_args, usage) = match game.commands.get(name) {
+        Some(command) => (command.run, command.min_args, command.usage),
+        None if game.vars.get_by_name(name).is_some() => {
+            let mut args = args.to_vec();
+            args.insert(0, name.to_owned());
+            return if args.len() == 1 {
+                get(game, &args)
+            } else {
+                set(game, &args)
+            };
+        },
+        None => return Err(format!("Unknown command {}, try help", name)),
+    };
+    if args.len() < min_args {
+        return Err(format!("Usage: {} {}", name, usage));
+    }
+    run(game, args)
+}
+
+fn help(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    let describe = |command: &console::Command| format!("{} {} - {}", command.name, command.usage, command.description);
+    match args.first() {
+        Some(name) => game.commands.get(name)
+            .map(describe)
+            .ok_or_else(|| format!("Unknown command {}", name)),
+        None => Ok(game.commands.iter()
+            .map(describe)
+            .collect::<Vec<_>>()
+            .join("\n")),
+    }
+}
+
+fn set(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    game.vars.set_by_name(&args[0], &args[1..].join(" "))?;
+    get(game, &args[..1])
+}
+
+fn get(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    let name = &args[0];
+    let value = game.vars.get_by_name(name).ok_or_else(|| format!("Unknown var {}", name))?;
+    Ok(format!("{} = {}\n  {}", name, value, game.vars.description_of(name).unwrap_or("")))
+}
+
+fn connect(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    let address = &args[0];
+    if game.server.is_connected() {
+        game.server.disconnect(None);
+    }
+    game.screen_sys.replace_screen(Box::new(screen::connecting::Connecting::new(address)));
+    game.resource_pack_policy = resource_pack::Policy::Prompt;
+    game.connect_to(address, None, proxy::default_proxy(), None);
+    Ok(format!("Connecting to {}", address))
+}
+
+fn disconnect(game: &mut crate::Game, _args: &[String]) -> Result<String, String> {
+    if !game.server.is_connected() {
+        return Err("Not connected to a server".to_owned());
+    }
+    game.server.disconnect(None);
+    game.screen_sys.replace_screen(Box::new(screen::ServerList::new(None)));
+    Ok(String::new())
+}
+
+fn say(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    if !game.server.is_connected() {
+        return Err("Not connected to a server".to_owned());
+    }
+    game.server.send_chat(&args.join(" "));
+    Ok(String::new())
+}
+
+fn reload(game: &mut crate::Game, _args: &[String]) -> Result<String, String> {
+    match game.resource_manager.try_write() {
+        Ok(mut resources) => resources.reload(),
+        Err(_) => return Err("The resources are being loaded, try again shortly".to_owned()),
+    }
+    game.renderer.reload_shaders();
+    Ok("Reloaded the resource packs and shaders".to_owned())
+}
+
+fn bind(game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    let bound_key = |key: &Stevenkey| {
+        let code = *game.vars.get(key.get_cvar());
+        settings::key_name(code).map_or_else(|| code.to_string(), |name| name.to_owned())
+    };
+    let key = match args.first() {
+        Some(key) => settings::key_by_name(key).ok_or_else(|| format!("Unknown key {}", key))?,
+        None => {
+            return Ok(Stevenkey::values().iter()
+                .map(|action| format!("{} = {}", action.name(), bound_key(action)))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        },
+    };
+    let action = match args.get(1) {
+        Some(action) => Stevenkey::get_by_name(action).ok_or_else(|| {
+            let actions: Vec<&str> = Stevenkey::values().iter().map(Stevenkey::name).collect();
+            format!("Unknown action {}, the actions are {}", action, actions.join(", "))
+        })?,
+        None => {
+            return Ok(match Stevenkey::get_by_keycode(key, &game.vars) {
+                Some(action) => format!("{} = {}", action.name(), args[0]),
+                None => format!("{} isn't bound", args[0]),
+            });
+        },
+    };
+    game.vars.set(action.get_cvar(), key as i64);
+    Ok(format!("{} = {}", action.name(), bound_key(&action)))
+}
+
+fn capture(_game: &mut crate::Game, args: &[String]) -> Result<String, String> {
+    let version = match args.get(1) {
+        Some(version) => Some(version.parse::<i32>().map_err(|_| format!("Invalid protocol version {}", version))?),
+        None => None,
+    };
+    let records = capture::load(&args[0]).map_err(|err| format!("Failed to load {}: {}", args[0], err))?;
+    let mut out = vec![];
+    let mut unknown = 0;
+    let mut failed = 0;
+    for (index, record) in records.iter().enumerate() {
+        let version = version.unwrap_or(record.protocol_version);
+        let problem = match record.decode(version) {
+            Ok(Some(packet::Packet::Unknown { .. })) | Ok(None) => {
+                unknown += 1;
+                "unknown packet".to_owned()
+            },
+            Ok(Some(_)) => continue,
+            Err(err) => {
+                failed += 1;
+                format!("{}", err)
+            },
+        };
+        out.push(format!("#{} {:?} {:?} 0x{:02X} (protocol {}): {}",
+            index, record.state, record.direction, record.id, version, problem));
+    }
+    out.push(format!("{} packets, {} failed to decode, {} unknown", records.len(), failed, unknown));
+    Ok(out.join("\n"))
+}
@@ -0,0 +1,212 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The language typed into the console.
+//
+// A line is made up of statements separated by `;`, each a command
+// name followed by its arguments. Arguments are separated by spaces
+// unless quoted with `"`, within which `\"` and `\\` are escapes.
+
+use std::collections::BTreeMap;
+
+use super::Vars;
+
+pub struct Command {
+    pub name: &'static str,
+    /// The arguments the command takes, shown when it is used wrong
+    pub usage: &'static str,
+    pub description: &'static str,
+    /// The minimum number of arguments the command needs
+    pub min_args: usize,
+    /// Whether the first argument is the name of a cvar, for
+    /// completing it
+    pub var_arg: bool,
+    /// Runs the command, returning what to print
+    pub run: fn(&mut crate::Game, &[String]) -> Result<String, String>,
+}
+
+#[derive(Default)]
+pub struct Commands {
+    commands: BTreeMap<&'static str, Command>,
+}
+
+impl Commands {
+    pub fn new() -> Commands { Default::default() }
+
+    pub fn register(&mut self, command: Command) {
+        if self.commands.contains_key(command.name) {
+            panic!("Command registered twice {}", command.name);
+        }
+        self.commands.insert(command.name, command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Returns the commands sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.values()
+    }
+}
+
+/// Splits `line` into its statements, each split into its words.
+pub fn parse(line: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut statements = vec![];
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => word.push(c),
+                            None => return Err("Unterminated escape".to_owned()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated quote".to_owned()),
+                    }
+                }
+            },
+            ';' => {
+                words.extend(word.take());
+                if !words.is_empty() {
+                    statements.push(words);
+                    words = vec![];
+                }
+            },
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word.take());
+    if !words.is_empty() {
+        statements.push(words);
+    }
+    Ok(statements)
+}
+
+/// Completes the last word of `line` with the names of commands or
+/// cvars. Returns the new line and, when the word could be
+/// completed in more than one way, the possible completions.
+pub fn complete(line: &str, commands: &Commands, vars: &Vars) -> (String, Vec<&'static str>) {
+    let statement_start = line.rfind(';').map_or(0, |pos| pos + 1);
+    let word_start = line.rfind(char::is_whitespace)
+        .map_or(0, |pos| pos + 1)
+        .max(statement_start);
+    let (before, word) = line.split_at(word_start);
+    let previous: Vec<&str> = before[statement_start..].split_whitespace().collect();
+
+    let candidates: Vec<&'static str> = match previous.len() {
+        0 => commands.commands.keys().cloned()
+            .chain(vars.names())
+            .collect(),
+        1 if commands.get(previous[0]).map_or(false, |c| c.var_arg) => vars.names(),
+        _ => vec![],
+    };
+    let mut matches: Vec<&'static str> = candidates.into_iter()
+        .filter(|name| name.starts_with(word))
+        .collect();
+    matches.sort();
+    matches.dedup();
+
+    match matches.len() {
+        0 => (line.to_owned(), vec![]),
+        1 => (format!("{}{} ", before, matches[0]), vec![]),
+        _ => {
+            let mut prefix = matches[0];
+            for name in &matches[1..] {
+                let common = prefix.chars()
+                    .zip(name.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum();
+                prefix = &prefix[..common];
+            }
+            (format!("{}{}", before, prefix), matches)
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::marker::PhantomData;
+    use crate::console::{CVar, Constraint};
+
+    fn run(_: &mut crate::Game, _: &[String]) -> Result<String, String> {
+        Ok(String::new())
+    }
+
+    fn command(name: &'static str, var_arg: bool) -> Command {
+        Command {
+            name,
+            usage: "",
+            description: "",
+            min_args: 0,
+            var_arg,
+            run,
+        }
+    }
+
+    fn var(name: &'static str) -> CVar<bool> {
+        CVar {
+            ty: PhantomData,
+            name,
+            description: "",
+            mutable: true,
+            serializable: false,
+            constraint: Constraint::NONE,
+            default: &|| false,
+        }
+    }
+
+    #[test]
+    fn parse_statements() {
+        assert_eq!(parse("  ").unwrap(), Vec::<Vec<String>>::new());
+        assert_eq!(parse("set r_fov 90; say \"hello; \\\"world\\\"\" ;;disconnect").unwrap(), vec![
+            vec!["set".to_owned(), "r_fov".to_owned(), "90".to_owned()],
+            vec!["say".to_owned(), "hello; \"world\"".to_owned()],
+            vec!["disconnect".to_owned()],
+        ]);
+        assert_eq!(parse("set cl_proxy \"\"").unwrap(), vec![
+            vec!["set".to_owned(), "cl_proxy".to_owned(), "".to_owned()],
+        ]);
+        assert!(parse("say \"hello").is_err());
+    }
+
+    #[test]
+    fn completion() {
+        let mut commands = Commands::new();
+        commands.register(command("set", true));
+        commands.register(command("say", false));
+        let mut vars = Vars::new();
+        vars.register(var("r_fov"));
+        vars.register(var("r_max_fps"));
+        vars.register(var("cl_proxy"));
+
+        assert_eq!(complete("se", &commands, &vars), ("set ".to_owned(), vec![]));
+        assert_eq!(complete("s", &commands, &vars), ("s".to_owned(), vec!["say", "set"]));
+        assert_eq!(complete("set r_", &commands, &vars), ("set r_".to_owned(), vec!["r_fov", "r_max_fps"]));
+        assert_eq!(complete("say hi; set cl", &commands, &vars), ("say hi; set cl_proxy ".to_owned(), vec![]));
+        assert_eq!(complete("cl", &commands, &vars), ("cl_proxy ".to_owned(), vec![]));
+        // Only the first argument of set is a cvar
+        assert_eq!(complete("set r_fov r_", &commands, &vars), ("set r_fov r_".to_owned(), vec![]));
+        assert_eq!(complete("say r_", &commands, &vars), ("say r_".to_owned(), vec![]));
+    }
+}
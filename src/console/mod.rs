@@ -18,13 +18,19 @@ use std::any::Any;
 use std::cell::{RefCell, Ref};
 use std::sync::{Arc, Mutex};
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::io::{BufWriter, Write, BufRead, BufReader};
-use log;
+use log::{self, warn};
+use glutin::VirtualKeyCode;
+use clipboard::{ClipboardProvider, ClipboardContext};
 
 use crate::ui;
 use crate::render;
 use crate::format::{Component, TextComponent, Color};
 
+mod command;
+pub use self::command::{Command, Commands, parse, complete};
+
 const FILTERED_CRATES: &[&str] = &[
     //"reqwest", // TODO: needed?
     "mime",
@@ -36,16 +42,65 @@ pub struct CVar<T: Sized + Any + 'static> {
     pub description: &'static str,
     pub mutable: bool,
     pub serializable: bool,
+    pub constraint: Constraint,
     pub default: &'static Fn() -> T,
 }
 
+/// Limits the values a var can be set to. `min` and `max` apply to
+/// numbers whilst `values`, unless empty, lists the allowed text of
+/// numbers and strings.
+#[derive(Clone, Copy, Debug)]
+pub struct Constraint {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub values: &'static [&'static str],
+}
+
+impl Constraint {
+    pub const NONE: Constraint = Constraint {
+        min: None,
+        max: None,
+        values: &[],
+    };
+
+    pub const fn range(min: i64, max: i64) -> Constraint {
+        Constraint {
+            min: Some(min),
+            max: Some(max),
+            values: &[],
+        }
+    }
+
+    fn check_int(&self, val: i64) -> Result<(), String> {
+        match (self.min, self.max) {
+            (Some(min), _) if val < min => Err(format!("{} is below the minimum of {}", val, min)),
+            (_, Some(max)) if val > max => Err(format!("{} is above the maximum of {}", val, max)),
+            _ => self.check_text(&val.to_string()),
+        }
+    }
+
+    fn check_text(&self, val: &str) -> Result<(), String> {
+        if self.values.is_empty() || self.values.contains(&val) {
+            Ok(())
+        } else {
+            Err(format!("{:?} isn't one of {}", val, self.values.join(", ")))
+        }
+    }
+}
+
 impl Var for CVar<i64> {
     fn serialize(&self, val: &Box<Any>) -> String {
         val.downcast_ref::<i64>().unwrap().to_string()
     }
 
-    fn deserialize(&self, input: &str) -> Box<Any> {
-        Box::new(input.parse::<i64>().unwrap())
+    fn deserialize(&self, input: &str) -> Result<Box<Any>, String> {
+        let val = input.parse::<i64>().map_err(|_| format!("{:?} isn't a number", input))?;
+        self.constraint.check_int(val)?;
+        Ok(Box::new(val))
+    }
+
+    fn check(&self, val: &Box<Any>) -> Result<(), String> {
+        self.constraint.check_int(*val.downcast_ref::<i64>().unwrap())
     }
 
     fn description(&self) -> &'static str {
@@ -55,6 +110,10 @@ impl Var for CVar<i64> {
     fn can_serialize(&self) -> bool {
         self.serializable
     }
+
+    fn is_mutable(&self) -> bool {
+        self.mutable
+    }
 }
 
 impl Var for CVar<bool> {
@@ -62,8 +121,14 @@ impl Var for CVar<bool> {
         val.downcast_ref::<bool>().unwrap().to_string()
     }
 
-    fn deserialize(&self, input: &str) -> Box<Any> {
-        Box::new(input.parse::<bool>().unwrap())
+    fn deserialize(&self, input: &str) -> Result<Box<Any>, String> {
+        input.parse::<bool>()
+            .map(|v| Box::new(v) as Box<Any>)
+            .map_err(|_| format!("{:?} isn't true or false", input))
+    }
+
+    fn check(&self, _val: &Box<Any>) -> Result<(), String> {
+        Ok(())
     }
 
     fn description(&self) -> &'static str {
@@ -73,6 +138,10 @@ impl Var for CVar<bool> {
     fn can_serialize(&self) -> bool {
         self.serializable
     }
+
+    fn is_mutable(&self) -> bool {
+        self.mutable
+    }
 }
 
 impl Var for CVar<String> {
@@ -80,8 +149,20 @@ impl Var for CVar<String> {
         format!("\"{}\"", val.downcast_ref::<String>().unwrap())
     }
 
-    fn deserialize(&self, input: &str) -> Box<Any> {
-        Box::new((&input[1..input.len() - 1]).to_owned())
+    // Quotes are optional so values typed into the console don't
+    // need them
+    fn deserialize(&self, input: &str) -> Result<Box<Any>, String> {
+        let val = if input.len() >= 2 && input.starts_with('"') && input.ends_with('"') {
+            &input[1..input.len() - 1]
+        } else {
+            input
+        };
+        self.constraint.check_text(val)?;
+        Ok(Box::new(val.to_owned()))
+    }
+
+    fn check(&self, val: &Box<Any>) -> Result<(), String> {
+        self.constraint.check_text(val.downcast_ref::<String>().unwrap())
     }
 
     fn description(&self) -> &'static str {
@@ -90,25 +171,54 @@ impl Var for CVar<String> {
     fn can_serialize(&self) -> bool {
         self.serializable
     }
+
+    fn is_mutable(&self) -> bool {
+        self.mutable
+    }
 }
 
 pub trait Var {
     fn serialize(&self, val: &Box<Any>) -> String;
-    fn deserialize(&self, input: &str) -> Box<Any>;
+    /// Parses a value, as saved or typed into the console, checking
+    /// it is allowed.
+    fn deserialize(&self, input: &str) -> Result<Box<Any>, String>;
+    /// Checks the var can be set to `val`.
+    fn check(&self, val: &Box<Any>) -> Result<(), String>;
     fn description(&self) -> &'static str;
     fn can_serialize(&self) -> bool;
+    fn is_mutable(&self) -> bool;
 }
 
+type Listener = Box<dyn Fn(&dyn Any)>;
+
+const CONFIG_FILE: &str = "conf.cfg";
+
 #[derive(Default)]
 pub struct Vars {
     names: HashMap<String, &'static str>,
     vars: HashMap<&'static str, Box<Var>>,
     var_values: HashMap<&'static str, RefCell<Box<Any>>>,
+    listeners: RefCell<HashMap<&'static str, Vec<Listener>>>,
+    /// Where the vars are saved, `conf.cfg` when unset
+    config_file: Option<PathBuf>,
 }
 
 impl Vars {
     pub fn new() -> Vars { Default::default() }
 
+    /// Creates vars saved to and loaded from `file` instead of
+    /// `conf.cfg`.
+    pub fn with_config_file<P: Into<PathBuf>>(file: P) -> Vars {
+        Vars {
+            config_file: Some(file.into()),
+            ..Default::default()
+        }
+    }
+
+    fn config_file(&self) -> &Path {
+        self.config_file.as_ref().map_or(Path::new(CONFIG_FILE), PathBuf::as_path)
+    }
+
     pub fn register<T: Sized + Any>(&mut self, var: CVar<T>)
         where CVar<T>: Var
     {
@@ -128,36 +238,118 @@ impl Vars {
         Ref::map(var, |v| v.downcast_ref::<T>().unwrap())
     }
 
+    /// Sets `var` unless `val` isn't allowed by its constraint, which
+    /// is logged.
     pub fn set<T: Sized + Any>(&self, var: CVar<T>, val: T)
         where CVar<T>: Var
     {
-        *self.var_values.get(var.name).unwrap().borrow_mut() = Box::new(val);
+        let val: Box<Any> = Box::new(val);
+        if let Err(err) = var.check(&val) {
+            warn!("Not setting {}: {}", var.name, err);
+            return;
+        }
+        self.store(var.name, val);
+    }
+
+    /// Calls `listener` with the new value whenever `var` is set.
+    /// Listeners must not set the var they listen to.
+    pub fn subscribe<T: Sized + Any, F>(&self, var: CVar<T>, listener: F)
+        where CVar<T>: Var,
+              F: Fn(&T) + 'static
+    {
+        self.listeners.borrow_mut()
+            .entry(var.name)
+            .or_default()
+            .push(Box::new(move |val| listener(val.downcast_ref::<T>().unwrap())));
+    }
+
+    fn store(&self, name: &'static str, val: Box<Any>) {
+        *self.var_values.get(name).unwrap().borrow_mut() = val;
         self.save_config();
+        if let Some(listeners) = self.listeners.borrow().get(name) {
+            let val = self.var_values.get(name).unwrap().borrow();
+            for listener in listeners {
+                listener(&**val);
+            }
+        }
+    }
+
+    /// Returns the names of every registered var, sorted.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self.vars.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn description_of(&self, name: &str) -> Option<&'static str> {
+        self.vars.get(name).map(|var| var.description())
+    }
+
+    /// Returns the value of the var `name` as it would be saved.
+    pub fn get_by_name(&self, name: &str) -> Option<String> {
+        let var = self.vars.get(name)?;
+        Some(var.serialize(&self.var_values.get(name).unwrap().borrow()))
+    }
+
+    /// Sets the var `name` from its textual form, as typed into the
+    /// console.
+    pub fn set_by_name(&self, name: &str, input: &str) -> Result<(), String> {
+        let name = *self.names.get(name).ok_or_else(|| format!("Unknown var {}", name))?;
+        let var = self.vars.get(name).unwrap();
+        if !var.is_mutable() {
+            return Err(format!("{} can't be changed", name));
+        }
+        let val = var.deserialize(input).map_err(|err| format!("Can't set {}: {}", name, err))?;
+        self.store(name, val);
+        Ok(())
     }
 
-    pub fn load_config(&mut self) {
-        if let Ok(file) = fs::File::open("conf.cfg") {
+    /// Loads the values saved in the config file, `conf.cfg` by
+    /// default, returning why any lines were skipped. Skipped vars
+    /// keep their defaults.
+    pub fn load_config(&mut self) -> Vec<String> {
+        let mut errors = vec![];
+        if let Ok(file) = fs::File::open(self.config_file()) {
             let reader = BufReader::new(file);
             for line in reader.lines() {
                 let line = line.unwrap();
                 if line.starts_with('#') || line.is_empty() {
                     continue;
                 }
-                let parts = line.splitn(2, ' ').map(|v| v.to_owned()).collect::<Vec<String>>();
-                let (name, arg) = (&parts[0], &parts[1]);
-                if let Some(var_name) = self.names.get(name) {
-                    let var = self.vars.get(var_name).unwrap();
-                    let val = var.deserialize(arg);
-                    if var.can_serialize() {
-                        self.var_values.insert(var_name, RefCell::new(val));
-                    }
+                let mut parts = line.splitn(2, ' ');
+                let name = parts.next().unwrap();
+                let arg = match parts.next() {
+                    Some(val) => val,
+                    None => {
+                        errors.push(format!("{} is missing a value", name));
+                        continue;
+                    },
+                };
+                let var_name = match self.names.get(name) {
+                    Some(val) => *val,
+                    None => {
+                        errors.push(format!("Unknown var {}", name));
+                        continue;
+                    },
+                };
+                let var = self.vars.get(var_name).unwrap();
+                let val = match var.deserialize(arg) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        errors.push(format!("Ignoring {}: {}", name, err));
+                        continue;
+                    },
+                };
+                if var.can_serialize() {
+                    self.var_values.insert(var_name, RefCell::new(val));
                 }
             }
         }
+        errors
     }
 
     pub fn save_config(&self) {
-        let mut file = BufWriter::new(fs::File::create("conf.cfg").unwrap());
+        let mut file = BufWriter::new(fs::File::create(self.config_file()).unwrap());
         for (name, var) in &self.vars {
             if !var.can_serialize() {
                 continue;
@@ -178,6 +370,16 @@ pub struct Console {
     history: Vec<Component>,
     dirty: bool,
 
+    /// The line being typed
+    input: String,
+    /// Lines entered previously, oldest first
+    input_history: Vec<String>,
+    /// The entry of `input_history` being shown, when going back
+    /// through it
+    input_history_pos: Option<usize>,
+    /// Lines entered but not yet run
+    pending: Vec<String>,
+
     elements: Option<ConsoleElements>,
     active: bool,
     position: f64,
@@ -185,6 +387,7 @@ pub struct Console {
 
 struct ConsoleElements {
     background: ui::ImageRef,
+    input: ui::TextRef,
     lines: Vec<ui::FormattedRef>,
 }
 
@@ -194,6 +397,11 @@ impl Console {
             history: vec![Component::Text(TextComponent::new("")); 200],
             dirty: false,
 
+            input: String::new(),
+            input_history: vec![],
+            input_history_pos: None,
+            pending: vec![],
+
             elements: None,
             active: false,
             position: -220.0,
@@ -208,6 +416,88 @@ impl Console {
         self.active = !self.active;
     }
 
+    pub fn key_type(&mut self, c: char) {
+        // The key that opens the console also types this
+        if c.is_control() || c == '`' {
+            return;
+        }
+        self.input.push(c);
+    }
+
+    pub fn key_press(&mut self, key: VirtualKeyCode, ctrl_pressed: bool, commands: &Commands, vars: &Vars) {
+        match key {
+            VirtualKeyCode::Back => {
+                self.input.pop();
+            },
+            VirtualKeyCode::Return => {
+                let line = self.input.trim().to_owned();
+                self.input.clear();
+                self.input_history_pos = None;
+                if line.is_empty() {
+                    return;
+                }
+                self.print(Component::Text(TextComponent::new(&format!("> {}", line))));
+                if self.input_history.last() != Some(&line) {
+                    self.input_history.push(line.clone());
+                }
+                self.pending.push(line);
+            },
+            VirtualKeyCode::Up => {
+                let pos = match self.input_history_pos {
+                    Some(pos) => pos.saturating_sub(1),
+                    None if !self.input_history.is_empty() => self.input_history.len() - 1,
+                    None => return,
+                };
+                self.input_history_pos = Some(pos);
+                self.input = self.input_history[pos].clone();
+            },
+            VirtualKeyCode::Down => {
+                if let Some(pos) = self.input_history_pos {
+                    if pos + 1 < self.input_history.len() {
+                        self.input_history_pos = Some(pos + 1);
+                        self.input = self.input_history[pos + 1].clone();
+                    } else {
+                        self.input_history_pos = None;
+                        self.input.clear();
+                    }
+                }
+            },
+            VirtualKeyCode::Tab => {
+                let (line, options) = complete(&self.input, commands, vars);
+                self.input = line;
+                if !options.is_empty() {
+                    self.print(Component::Text(TextComponent::new(&options.join("  "))));
+                }
+            },
+            VirtualKeyCode::V if ctrl_pressed => {
+                let mut clipboard: ClipboardContext = ClipboardProvider::new().unwrap();
+                if let Ok(text) = clipboard.get_contents() {
+                    self.input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Returns the lines entered since this was last called, for
+    /// running.
+    pub fn take_commands(&mut self) -> Vec<String> {
+        ::std::mem::take(&mut self.pending)
+    }
+
+    /// Adds a line to the console, without logging it.
+    pub fn print(&mut self, line: Component) {
+        self.history.remove(0);
+        self.history.push(line);
+        self.dirty = true;
+    }
+
+    pub fn print_error(&mut self, msg: &str) {
+        let mut msg = TextComponent::new(msg);
+        msg.modifier.color = Some(Color::Red);
+        self.print(Component::Text(msg));
+    }
+
     pub fn tick(&mut self,
                 ui_container: &mut ui::Container,
                 renderer: &render::Renderer,
@@ -241,8 +531,14 @@ impl Console {
                 .colour((0, 0, 0, 180))
                 .draw_index(500)
                 .create(ui_container);
+            let input = ui::TextBuilder::new()
+                .text("")
+                .position(5.0, 5.0)
+                .alignment(ui::VAttach::Bottom, ui::HAttach::Left)
+                .attach(&mut *background.borrow_mut());
             self.elements = Some(ConsoleElements {
                 background,
+                input,
                 lines: vec![],
             });
             self.dirty = true;
@@ -251,12 +547,14 @@ impl Console {
         let mut background = elements.background.borrow_mut();
         background.y = self.position;
         background.width = w;
+        elements.input.borrow_mut().text = format!("> {}_", self.input);
 
         if self.dirty {
             self.dirty = false;
             elements.lines.clear();
 
-            let mut offset = 0.0;
+            // Below the input line
+            let mut offset = 20.0;
             for line in self.history.iter().rev() {
                 if offset >= 210.0 {
                     break;
@@ -290,7 +588,6 @@ impl Console {
                  record.line().unwrap_or(0),
                  record.level(),
                  record.args());
-        self.history.remove(0);
         let mut msg = TextComponent::new("");
         msg.modifier.extra = Some(vec![
             Component::Text(TextComponent::new("[")),
@@ -321,8 +618,7 @@ impl Console {
             Component::Text(TextComponent::new("] ")),
             Component::Text(TextComponent::new(&format!("{}", record.args())))
         ]);
-        self.history.push(Component::Text(msg));
-        self.dirty = true;
+        self.print(Component::Text(msg));
     }
 }
 
@@ -353,3 +649,96 @@ impl log::Log for ConsoleProxy {
 
 unsafe impl Send for ConsoleProxy {}
 unsafe impl Sync for ConsoleProxy {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    const FOV: CVar<i64> = CVar {
+        ty: PhantomData,
+        name: "fov",
+        description: "",
+        mutable: true,
+        serializable: true,
+        constraint: Constraint::range(30, 110),
+        default: &|| 90,
+    };
+
+    const MODE: CVar<String> = CVar {
+        ty: PhantomData,
+        name: "mode",
+        description: "",
+        mutable: true,
+        serializable: true,
+        constraint: Constraint {
+            values: &["fast", "fancy"],
+            ..Constraint::NONE
+        },
+        default: &|| "fast".to_owned(),
+    };
+
+    #[test]
+    fn constraints() {
+        assert_eq!(*FOV.deserialize("110").unwrap().downcast_ref::<i64>().unwrap(), 110);
+        assert!(FOV.deserialize("111").is_err());
+        assert!(FOV.deserialize("29").is_err());
+        assert!(FOV.deserialize("wide").is_err());
+        assert!(FOV.check(&(Box::new(120i64) as Box<Any>)).is_err());
+
+        assert_eq!(MODE.deserialize("\"fancy\"").unwrap().downcast_ref::<String>().unwrap(), "fancy");
+        assert_eq!(MODE.deserialize("fast").unwrap().downcast_ref::<String>().unwrap(), "fast");
+        assert!(MODE.deserialize("\"slow\"").is_err());
+        assert!(MODE.check(&(Box::new("slow".to_owned()) as Box<Any>)).is_err());
+    }
+
+    fn config_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("steven-console-{}-{}.cfg", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn listeners() {
+        let file = config_file("listeners");
+        let mut vars = Vars::with_config_file(&file);
+        vars.register(FOV);
+        let seen = Rc::new(RefCell::new(vec![]));
+        let s = seen.clone();
+        vars.subscribe(FOV, move |&val| s.borrow_mut().push(val));
+
+        vars.set(FOV, 100);
+        vars.set_by_name("fov", "70").unwrap();
+        // Rejected values don't reach listeners
+        vars.set(FOV, 200);
+        assert!(vars.set_by_name("fov", "20").is_err());
+        assert_eq!(*seen.borrow(), vec![100, 70]);
+        assert_eq!(*vars.get(FOV), 70);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn load_config_errors() {
+        let file = config_file("load");
+        fs::write(&file, "# A comment\nfov 100\nmode\nspeed 3\nmode \"slow\"\n").unwrap();
+        let mut vars = Vars::with_config_file(&file);
+        vars.register(FOV);
+        vars.register(MODE);
+        let errors = vars.load_config();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("mode is missing a value"));
+        assert!(errors[1].contains("Unknown var speed"));
+        assert!(errors[2].starts_with("Ignoring mode"));
+        assert_eq!(*vars.get(FOV), 100);
+        assert_eq!(*vars.get(MODE), "fast");
+
+        // Saving goes to the same file
+        vars.set(MODE, "fancy".to_owned());
+        let mut vars = Vars::with_config_file(&file);
+        vars.register(FOV);
+        vars.register(MODE);
+        assert!(vars.load_config().is_empty());
+        assert_eq!(*vars.get(MODE), "fancy");
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,347 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Downloads of files whose SHA-1 hash is known up front, such as the
+// vanilla client and assets.
+//
+// Files are written to a `.part` file next to where they belong and
+// only moved into place once their hash matches, so an interrupted
+// download never leaves a broken file behind. The next attempt, even
+// after a restart, continues from the end of the `.part` file.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use sha1::{self, Digest};
+use log::warn;
+use reqwest;
+
+use crate::protocol::Error;
+
+/// How many times a download is tried before giving up on it
+const ATTEMPTS: u32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct Download {
+    /// Shown whilst downloading
+    pub name: String,
+    pub url: String,
+    pub location: PathBuf,
+    /// The expected SHA-1 hash in lowercase hex
+    pub hash: String,
+    /// The expected size, 0 if unknown
+    pub size: u64,
+}
+
+/// Progress reported whilst downloading.
+#[derive(Clone, Copy)]
+pub enum Event<'a> {
+    /// The download is being fetched, starting at the given offset
+    /// when resumed
+    Started(&'a Download, u64),
+    /// Bytes were read
+    Read(&'a Download, u64),
+    /// The download either succeeded, was already there or failed
+    Finished(&'a Download),
+}
+
+pub type Listener = Arc<dyn Fn(Event) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Downloader {
+    client: reqwest::Client,
+    /// The delay before the first retry, doubled for each retry after
+    retry_delay: Duration,
+}
+
+impl Downloader {
+    pub fn new(client: reqwest::Client) -> Downloader {
+        Downloader {
+            client,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Fetches `download` unless it is already there with the right
+    /// hash, retrying failed attempts.
+    pub fn fetch(&self, download: &Download, listener: &Listener) -> Result<(), Error> {
+        let res = self.fetch_with_retry(download, listener);
+        listener(Event::Finished(download));
+        res
+    }
+
+    fn fetch_with_retry(&self, download: &Download, listener: &Listener) -> Result<(), Error> {
+        if verify(&download.location, &download.hash) {
+            return Ok(());
+        }
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        loop {
+            match self.try_fetch(download, listener) {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    if attempt >= ATTEMPTS {
+                        return Err(err);
+                    }
+                    warn!("Failed to download {} (attempt {} of {}): {}", download.url, attempt, ATTEMPTS, err);
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                },
+            }
+        }
+    }
+
+    fn try_fetch(&self, download: &Download, listener: &Listener) -> Result<(), Error> {
+        if let Some(parent) = download.location.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let part = part_location(&download.location);
+        let offset = fs::metadata(&part).map(|v| v.len()).unwrap_or(0);
+        let mut req = self.client.get(&download.url);
+        if offset > 0 {
+            req = req.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let res = req.send()?;
+        if offset > 0 && res.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // Nothing left to fetch, unless the part is broken in
+            // which case finishing removes it to start again.
+            return finish(&part, download);
+        }
+        let mut res = res.error_for_status()?;
+
+        let (mut file, offset) = if offset > 0 && res.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            (fs::OpenOptions::new().append(true).open(&part)?, offset)
+        } else {
+            (fs::File::create(&part)?, 0)
+        };
+        listener(Event::Started(download, offset));
+        let mut buf = vec![0; 16 * 1024];
+        loop {
+            let size = res.read(&mut buf)?;
+            if size == 0 {
+                break;
+            }
+            file.write_all(&buf[..size])?;
+            listener(Event::Read(download, size as u64));
+        }
+        drop(file);
+        finish(&part, download)
+    }
+
+    /// Fetches all of `downloads` with `threads` downloads at a time.
+    /// Failed downloads are logged and returned.
+    pub fn fetch_all(&self, downloads: Vec<Download>, threads: usize, listener: Listener) -> Vec<(Download, Error)> {
+        let queue = Arc::new(Mutex::new(downloads.into_iter()));
+        let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+            let downloader = self.clone();
+            let queue = queue.clone();
+            let listener = listener.clone();
+            thread::spawn(move || {
+                let mut failed = vec![];
+                loop {
+                    // Only lock whilst taking the next download
+                    let download = queue.lock().unwrap().next();
+                    let download = match download {
+                        Some(val) => val,
+                        None => return failed,
+                    };
+                    if let Err(err) = downloader.fetch(&download, &listener) {
+                        warn!("Failed to download {}: {}", download.name, err);
+                        failed.push((download, err));
+                    }
+                }
+            })
+        }).collect();
+        workers.into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Returns whether the file at `location` exists and has the SHA-1
+/// hash `hash`.
+pub fn verify(location: &Path, hash: &str) -> bool {
+    match file_hash(location) {
+        Ok(actual) => actual == hash,
+        Err(_) => false,
+    }
+}
+
+fn file_hash(location: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(location)?;
+    let mut hasher = sha1::Sha1::new();
+    let mut buf = vec![0; 16 * 1024];
+    loop {
+        let size = file.read(&mut buf)?;
+        if size == 0 {
+            break;
+        }
+        hasher.input(&buf[..size]);
+    }
+    Ok(hex::encode(hasher.result()))
+}
+
+fn part_location(location: &Path) -> PathBuf {
+    let mut name = location.file_name().map_or_else(Default::default, |v| v.to_owned());
+    name.push(".part");
+    location.with_file_name(name)
+}
+
+/// Moves the completed `part` into place if its hash is right,
+/// otherwise removes it so the next attempt starts over.
+fn finish(part: &Path, download: &Download) -> Result<(), Error> {
+    let actual = file_hash(part)?;
+    if actual != download.hash {
+        fs::remove_file(part)?;
+        return Err(Error::Err(format!("Downloaded {} has the hash {}, expected {}", download.name, actual, download.hash)));
+    }
+    fs::rename(part, &download.location)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_http::serve;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn sha1_hex(data: &[u8]) -> String {
+        let mut hasher = sha1::Sha1::new();
+        hasher.input(data);
+        hex::encode(hasher.result())
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("steven-downloader-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn download(url: &str, dir: &Path, data: &[u8]) -> Download {
+        Download {
+            name: "test".to_owned(),
+            url: url.to_owned(),
+            location: dir.join("file"),
+            hash: sha1_hex(data),
+            size: data.len() as u64,
+        }
+    }
+
+    fn no_listener() -> Listener {
+        Arc::new(|_| {})
+    }
+
+    fn downloader() -> Downloader {
+        Downloader {
+            client: reqwest::Client::new(),
+            retry_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn retries_bad_hashes() {
+        const DATA: &[u8] = b"the real contents";
+        let dir = temp_dir("retry");
+        let requests = Arc::new(AtomicUsize::new(0));
+        let (url, handle) = {
+            let requests = requests.clone();
+            serve(3, move |_| match requests.fetch_add(1, Ordering::SeqCst) {
+                0 => ("500 Internal Server Error", vec![]),
+                1 => ("200 OK", b"corrupted".to_vec()),
+                _ => ("200 OK", DATA.to_vec()),
+            })
+        };
+        let download = download(&format!("{}/file", url), &dir, DATA);
+        downloader().fetch(&download, &no_listener()).unwrap();
+        handle.join().unwrap();
+        assert_eq!(fs::read(&download.location).unwrap(), DATA);
+        assert!(!part_location(&download.location).exists());
+
+        // Already there so nothing is requested
+        downloader().fetch(&download, &no_listener()).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resumes_parts() {
+        const DATA: &[u8] = b"0123456789abcdefghij";
+        let dir = temp_dir("resume");
+        fs::create_dir_all(&dir).unwrap();
+        let (url, handle) = serve(1, |request| {
+            assert_eq!(request.header("range"), Some("bytes=8-"));
+            ("206 Partial Content", DATA[8..].to_vec())
+        });
+        let download = download(&format!("{}/file", url), &dir, DATA);
+        fs::write(part_location(&download.location), &DATA[..8]).unwrap();
+
+        let read = Arc::new(AtomicUsize::new(0));
+        let listener: Listener = {
+            let read = read.clone();
+            Arc::new(move |event| match event {
+                Event::Started(_, offset) => assert_eq!(offset, 8),
+                Event::Read(_, size) => { read.fetch_add(size as usize, Ordering::SeqCst); },
+                Event::Finished(_) => {},
+            })
+        };
+        downloader().fetch(&download, &listener).unwrap();
+        handle.join().unwrap();
+        assert_eq!(read.load(Ordering::SeqCst), DATA.len() - 8);
+        assert_eq!(fs::read(&download.location).unwrap(), DATA);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fetches_in_parallel() {
+        let dir = temp_dir("parallel");
+        let files: Vec<Vec<u8>> = (0..6u8).map(|i| vec![i; 100 + i as usize]).collect();
+        let served = files.clone();
+        let (url, handle) = serve(5, move |request| {
+            let index: usize = request.path[1..].parse().unwrap();
+            ("200 OK", served[index].clone())
+        });
+        let downloads: Vec<Download> = files.iter().enumerate().map(|(i, data)| Download {
+            name: i.to_string(),
+            url: format!("{}/{}", url, i),
+            location: dir.join(i.to_string()),
+            hash: sha1_hex(data),
+            size: data.len() as u64,
+        }).collect();
+        // One is already there and one has been corrupted
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&downloads[0].location, &files[0]).unwrap();
+        fs::write(&downloads[1].location, b"corrupt").unwrap();
+
+        let finished = Arc::new(AtomicUsize::new(0));
+        let listener: Listener = {
+            let finished = finished.clone();
+            Arc::new(move |event| if let Event::Finished(_) = event {
+                finished.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let failed = downloader().fetch_all(downloads.clone(), 3, listener);
+        handle.join().unwrap();
+        assert!(failed.is_empty());
+        assert_eq!(finished.load(Ordering::SeqCst), files.len());
+        for (download, data) in downloads.iter().zip(&files) {
+            assert_eq!(&fs::read(&download.location).unwrap(), data);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
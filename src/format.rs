@@ -58,7 +58,9 @@ impl Component {
     }
 
     pub fn to_value(&self) -> serde_json::Value {
-        unimplemented!()
+        match *self {
+            Component::Text(ref txt) => txt.to_value(),
+        }
     }
 }
 
@@ -118,7 +120,25 @@ impl Modifier {
     }
 
     pub fn to_value(&self) -> serde_json::Value {
-        unimplemented!()
+        let mut v = serde_json::Map::new();
+        for &(name, val) in &[
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated),
+        ] {
+            if let Some(val) = val {
+                v.insert(name.to_owned(), serde_json::Value::Bool(val));
+            }
+        }
+        if let Some(color) = self.color {
+            v.insert("color".to_owned(), serde_json::Value::String(color.to_string()));
+        }
+        if let Some(ref extra) = self.extra {
+            v.insert("extra".to_owned(), serde_json::Value::Array(extra.iter().map(Component::to_value).collect()));
+        }
+        serde_json::Value::Object(v)
     }
 }
 
@@ -144,7 +164,9 @@ impl TextComponent {
     }
 
     pub fn to_value(&self) -> serde_json::Value {
-        unimplemented!()
+        let mut v = self.modifier.to_value();
+        v["text"] = serde_json::Value::String(self.text.clone());
+        v
     }
 }
 
@@ -293,6 +315,20 @@ fn test_color_from() {
     }
 }
 
+#[test]
+fn test_component_round_trip() {
+    let c = Component::from_string(r#"{"text":"hello","bold":true,"color":"red","extra":[{"text":" world"}]}"#);
+    let c = Component::from_value(&c.to_value());
+    match c {
+        Component::Text(ref txt) => {
+            assert_eq!(txt.text, "hello");
+            assert_eq!(txt.modifier.bold, Some(true));
+            assert!(match txt.modifier.color { Some(Color::Red) => true, _ => false });
+        },
+    }
+    assert_eq!(format!("{}", c), "hello world");
+}
+
 const LEGACY_CHAR: char = '§';
 
 pub fn convert_legacy(c: &mut Component) {
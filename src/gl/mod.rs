@@ -468,6 +468,7 @@ pub type ShaderParameter = u32;
 
 pub const COMPILE_STATUS: ShaderParameter = gl::COMPILE_STATUS;
 pub const INFO_LOG_LENGTH: ShaderParameter = gl::INFO_LOG_LENGTH;
+pub const LINK_STATUS: ShaderParameter = gl::LINK_STATUS;
 
 pub struct Program(u32);
 
@@ -494,6 +495,34 @@ impl Program {
         }
     }
 
+    /// Binds the attribute `name` to `location`, which takes effect
+    /// when the program is next linked.
+    pub fn bind_attribute_location(&self, location: Attribute, name: &str) {
+        unsafe {
+            let name_c = ffi::CString::new(name).unwrap();
+            gl::BindAttribLocation(self.0, location.0 as u32, name_c.as_ptr());
+        }
+    }
+
+    pub fn get_parameter(&self, param: ShaderParameter) -> i32 {
+        let mut ret: i32 = 0;
+        unsafe {
+            gl::GetProgramiv(self.0, param, &mut ret);
+        }
+        ret
+    }
+
+    pub fn get_info_log(&self) -> String {
+        let len = self.get_parameter(INFO_LOG_LENGTH);
+
+        let mut data = Vec::<u8>::with_capacity(len as usize);
+        unsafe {
+            data.set_len(len as usize);
+            gl::GetProgramInfoLog(self.0, len, ptr::null_mut(), data.as_mut_ptr() as *mut i8);
+        }
+        String::from_utf8(data).unwrap()
+    }
+
     pub fn uniform_location(&self, name: &str) -> Option<Uniform> {
         let u = unsafe {
             gl::GetUniformLocation(self.0, ffi::CString::new(name).unwrap().as_ptr())
@@ -39,7 +39,7 @@ impl Default for Stack {
 
 impl Serializable for Option<Stack> {
     fn read_from<R: io::Read>(buf: &mut R) -> Result<Option<Stack>, protocol::Error> {
-        let protocol_version = unsafe { protocol::CURRENT_PROTOCOL_VERSION };
+        let protocol_version = protocol::current_protocol_version();
 
         if protocol_version >= 404 {
             let present = buf.read_u8()? != 0;
@@ -58,8 +58,8 @@ impl Serializable for Option<Stack> {
             return Ok(None);
         }
         let count = buf.read_u8()? as isize;
-        let damage = if protocol_version >= 404 {
-            // 1.13.2+ stores damage in the NBT
+        let damage = if protocol_version >= 393 {
+            // 1.13+ stores damage in the NBT
             None
         } else {
             Some(buf.read_i16::<BigEndian>()? as isize)
@@ -89,17 +89,81 @@ impl Serializable for Option<Stack> {
         }))
     }
     fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), protocol::Error> {
+        let protocol_version = protocol::current_protocol_version();
+
         match *self {
             Some(ref val) => {
-                // TODO: if protocol_version >= 404, send present and id varint, no damage, for 1.13.2
-                buf.write_i16::<BigEndian>(val.id as i16)?;
+                if protocol_version >= 404 {
+                    buf.write_u8(1)?;
+                    protocol::VarInt(val.id as i32).write_to(buf)?;
+                } else {
+                    buf.write_i16::<BigEndian>(val.id as i16)?;
+                }
                 buf.write_u8(val.count as u8)?;
-                buf.write_i16::<BigEndian>(val.damage.unwrap_or(0) as i16)?;
+                if protocol_version < 393 {
+                    buf.write_i16::<BigEndian>(val.damage.unwrap_or(0) as i16)?;
+                }
                 // TODO: compress zlib NBT if 1.7
                 val.tag.write_to(buf)?;
             }
-            None => buf.write_i16::<BigEndian>(-1)?,
+            None => if protocol_version >= 404 {
+                buf.write_u8(0)?;
+            } else {
+                buf.write_i16::<BigEndian>(-1)?;
+            },
         }
         Result::Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    // The same item (stone, count 1, no tag) in the 1.13.2, 1.13 and 1.12.2 slot formats
+    const SLOT_404: &[u8] = &[1, 1, 1, 0];
+    const SLOT_393: &[u8] = &[0, 1, 1, 0];
+    const SLOT_340: &[u8] = &[0, 1, 1, 0, 0, 0];
+
+    fn read_slot(version: i32, data: &[u8]) -> Option<Stack> {
+        protocol::with_protocol_version(version, || {
+            Option::<Stack>::read_from(&mut io::Cursor::new(data)).unwrap()
+        })
+    }
+
+    #[test]
+    fn versions_per_thread() {
+        let threads: Vec<_> = vec![(404, SLOT_404), (340, SLOT_340)].into_iter().map(|(version, data)| {
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    let stack = read_slot(version, data).unwrap();
+                    assert_eq!(stack.id, 1);
+                    assert_eq!(stack.count, 1);
+                }
+            })
+        }).collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn slot_formats() {
+        for &(version, data) in &[(404, SLOT_404), (393, SLOT_393), (340, SLOT_340)] {
+            let stack = read_slot(version, data);
+            assert_eq!(stack.as_ref().map(|s| (s.id, s.count)), Some((1, 1)));
+            let mut out = vec![];
+            protocol::with_protocol_version(version, || stack.write_to(&mut out)).unwrap();
+            assert_eq!(out, data);
+        }
+    }
+
+    #[test]
+    fn nested_version_restored() {
+        protocol::with_protocol_version(404, || {
+            assert!(read_slot(340, SLOT_340).is_some());
+            assert_eq!(protocol::current_protocol_version(), 404);
+        });
+    }
+}
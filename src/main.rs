@@ -13,9 +13,13 @@
 // limitations under the License.
 
 #![recursion_limit="300"]
+#![cfg_attr(feature = "bench", feature(test))]
+
+#[cfg(feature = "bench")]
+extern crate test;
 
 use std::time::{Instant, Duration};
-use log::{info, warn};
+use log::{info, warn, error};
 extern crate steven_shared as shared;
 
 #[macro_use]
@@ -29,27 +33,38 @@ pub mod item;
 pub mod gl;
 pub mod types;
 pub mod resources;
+pub mod downloader;
+#[cfg(test)]
+mod mock_http;
+pub mod watcher;
 pub mod render;
 pub mod ui;
 pub mod screen;
 pub mod settings;
 pub mod console;
+pub mod commands;
 pub mod server;
 pub mod world;
 pub mod chunk_builder;
 pub mod auth;
+pub mod accounts;
 pub mod model;
 pub mod entity;
 
 use std::sync::{Arc, RwLock, Mutex};
 use std::rc::Rc;
+use std::cell::Cell;
 use std::marker::PhantomData;
 use std::thread;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use crate::protocol::mojang;
 use glutin;
 use glutin::GlContext;
 
+/// How often the saved accounts are checked for stale tokens
+const ACCOUNT_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
 const CL_BRAND: console::CVar<String> = console::CVar {
     ty: PhantomData,
     name: "cl_brand",
@@ -57,6 +72,7 @@ const CL_BRAND: console::CVar<String> = console::CVar {
                   \"Vanilla\"",
     mutable: false,
     serializable: false,
+    constraint: console::Constraint::NONE,
     default: &|| "Steven".to_owned(),
 };
 
@@ -66,6 +82,7 @@ pub struct Game {
     resource_manager: Arc<RwLock<resources::Manager>>,
     console: Arc<Mutex<console::Console>>,
     vars: Rc<console::Vars>,
+    commands: console::Commands,
     should_close: bool,
 
     server: server::Server,
@@ -73,7 +90,25 @@ pub struct Game {
     chunk_builder: chunk_builder::ChunkBuilder,
 
     connect_reply: Option<mpsc::Receiver<Result<server::Server, protocol::Error>>>,
-    protocol_version: i32,
+    integrated_server: Option<server::integrated::IntegratedServer>,
+    /// Whether the server being played on, or connected to, is the
+    /// integrated server
+    on_integrated: bool,
+    /// Set when cl_resource_packs changes, until the packs are loaded
+    resource_packs_changed: Rc<Cell<bool>>,
+    /// The server's resource pack currently applied
+    server_pack: Option<PathBuf>,
+    /// What to do with resource packs sent by the server being
+    /// connected to
+    resource_pack_policy: server::resource_pack::Policy,
+    /// Set when cl_hot_reload or r_shader_dir changes, until applied
+    hot_reload_changed: Rc<Cell<bool>>,
+    /// Results of refreshing the saved accounts' tokens
+    account_refresh: Option<mpsc::Receiver<(String, Result<mojang::Profile, protocol::Error>)>>,
+    /// When the saved accounts are next checked for stale tokens. The
+    /// first check waits so it doesn't race the login screen's
+    /// refresh of the current account's token.
+    next_account_refresh: Instant,
 
     dpi_factor: f64,
     last_mouse_x: f64,
@@ -84,39 +119,198 @@ pub struct Game {
 }
 
 impl Game {
-    pub fn connect_to(&mut self, address: &str) {
-        // Read saved server protocol version from ping response TODO: get from memory?
-        use std::fs;
-        let file = match fs::File::open("server_versions.json") {
-            Ok(val) => val,
-            Err(_) => return,
-        };
-        let server_versions_info: serde_json::Value = serde_json::from_reader(file).unwrap();
-        let protocol_version = {
-            if let Some(v) = server_versions_info.get(address) {
-                v.as_i64().unwrap() as i32
-            } else {
-                warn!("Server protocol version not known for {} (no ping response?), defaulting to {}", address, protocol::SUPPORTED_PROTOCOLS[0]);
-                protocol::SUPPORTED_PROTOCOLS[0]
-            }
-        };
+    /// Starts the integrated server, if it isn't already running,
+    /// and connects to it.
+    pub fn connect_to_integrated(&mut self) -> Result<(), protocol::Error> {
+        if self.integrated_server.is_none() {
+            self.integrated_server = Some(server::integrated::IntegratedServer::start(Path::new(server::integrated::SAVE_FILE))?);
+        }
+        let address = self.integrated_server.as_ref().unwrap().address();
+        // The integrated server is always local so never proxied
+        self.resource_pack_policy = server::resource_pack::Policy::Prompt;
+        self.connect_to(&address, Some(server::integrated::PROTOCOL_VERSION), None, None);
+        self.on_integrated = true;
+        Ok(())
+    }
 
-        self.protocol_version = protocol_version;
+    /// Connects to the server at `address`, through `proxy` if given.
+    /// Unless a protocol version is given the server is pinged first
+    /// to pick one. `account` is joined as instead of the current
+    /// account when set.
+    pub fn connect_to(&mut self, address: &str, protocol_version: Option<i32>, proxy: Option<protocol::proxy::Proxy>, account: Option<mojang::Profile>) {
         let (tx, rx) = mpsc::channel();
         self.connect_reply = Some(rx);
+        self.on_integrated = false;
         let address = address.to_owned();
         let resources = self.resource_manager.clone();
-        let profile = mojang::Profile {
-            username: self.vars.get(auth::CL_USERNAME).clone(),
-            id: self.vars.get(auth::CL_UUID).clone(),
-            access_token: self.vars.get(auth::AUTH_TOKEN).clone(),
+        let profile = account.unwrap_or_else(|| {
+            let id = self.vars.get(auth::CL_UUID).clone();
+            // Saved accounts remember the servers they logged in with
+            let endpoints = accounts::Accounts::load().find(&id)
+                .map_or_else(|| auth::endpoints(&self.vars), |a| a.profile.endpoints.clone());
+            mojang::Profile {
+                username: self.vars.get(auth::CL_USERNAME).clone(),
+                id,
+                access_token: self.vars.get(auth::AUTH_TOKEN).clone(),
+                offline: *self.vars.get(auth::AUTH_OFFLINE),
+                endpoints,
+            }
+        });
+        let connect_timeout = *self.vars.get(settings::CL_CONNECT_TIMEOUT);
+        let read_timeout = *self.vars.get(settings::CL_READ_TIMEOUT);
+        let capture = self.vars.get(settings::CL_PACKET_CAPTURE).clone();
+        let nameserver = self.vars.get(settings::CL_NAMESERVER).clone();
+        let resolver = if nameserver.is_empty() {
+            protocol::resolve::Resolver::new()
+        } else {
+            match protocol::resolve::parse_nameserver(&nameserver) {
+                Ok(nameserver) => protocol::resolve::Resolver::with_nameserver(nameserver),
+                Err(err) => {
+                    error!("Invalid cl_nameserver: {}", err);
+                    protocol::resolve::Resolver::new()
+                },
+            }
+        };
+        let options = protocol::ConnectOptions {
+            resolver,
+            connect_timeout: Duration::from_secs(connect_timeout.max(1) as u64),
+            read_timeout: if read_timeout > 0 {
+                Some(Duration::from_secs(read_timeout as u64))
+            } else {
+                None
+            },
+            capture: if capture.is_empty() {
+                None
+            } else {
+                Some(capture.into())
+            },
+            proxy,
         };
         thread::spawn(move || {
-            tx.send(server::Server::connect(resources, profile, &address, protocol_version)).unwrap();
+            // Pinned versions are mapped onto a supported one the
+            // same way a negotiated one is
+            let protocol_version = match protocol_version {
+                Some(version) => protocol::versions::best_protocol_version(version)
+                    .ok_or(protocol::Error::UnsupportedVersion(version)),
+                None => server::negotiate::negotiate(&address, &options),
+            };
+            let res = protocol_version
+                .and_then(|protocol_version| server::Server::connect(resources, profile, &address, protocol_version, &options));
+            tx.send(res).unwrap();
         });
     }
 
+    /// Loads the resource packs listed in cl_resource_packs once
+    /// it has changed.
+    fn update_resource_packs(&mut self) {
+        if !self.resource_packs_changed.get() {
+            return;
+        }
+        // Tried again next tick if the manager is busy
+        if let Ok(mut resources) = self.resource_manager.try_write() {
+            let packs: Vec<String> = self.vars.get(settings::CL_RESOURCE_PACKS).split(',')
+                .map(|v| v.trim().to_owned())
+                .filter(|v| !v.is_empty())
+                .collect();
+            resources.set_resource_packs(&packs);
+            self.resource_packs_changed.set(false);
+        }
+    }
+
+    /// Applies the resource pack the server sent, or removes it once
+    /// the server no longer wants it.
+    fn update_server_pack(&mut self) {
+        let mut res = Ok(());
+        let wanted = self.server.resource_pack().map(Path::to_path_buf);
+        if wanted != self.server_pack {
+            // Tried again next tick if the manager is busy
+            let mut resources = match self.resource_manager.try_write() {
+                Ok(resources) => resources,
+                Err(_) => return,
+            };
+            res = resources.set_server_pack(wanted.as_ref().map(PathBuf::as_path))
+                .map_err(|err| protocol::Error::Err(format!("{}", err)));
+            self.server_pack = if res.is_ok() { wanted } else { None };
+        }
+        self.server.resource_pack_applied(res);
+    }
+
+    /// Applies r_shader_dir and starts or stops watching the
+    /// directory packs and shaders once cl_hot_reload or
+    /// r_shader_dir has changed.
+    fn update_hot_reload(&mut self) {
+        if !self.hot_reload_changed.get() {
+            return;
+        }
+        let hot_reload = *self.vars.get(settings::CL_HOT_RELOAD);
+        // Tried again next tick if the manager is busy
+        if let Ok(mut resources) = self.resource_manager.try_write() {
+            resources.set_hot_reload(hot_reload);
+        } else {
+            return;
+        }
+        let shader_dir = self.vars.get(settings::R_SHADER_DIR).clone();
+        let shader_dir = if shader_dir.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(shader_dir))
+        };
+        self.renderer.set_shader_dir(shader_dir, hot_reload);
+        self.hot_reload_changed.set(false);
+    }
+
+    /// Runs the lines entered into the console.
+    fn run_commands(&mut self) {
+        let lines = self.console.lock().unwrap().take_commands();
+        for line in lines {
+            commands::execute(self, &line);
+        }
+    }
+
+    /// Keeps the saved accounts' tokens valid by refreshing those
+    /// that haven't been validated recently.
+    fn refresh_accounts(&mut self) {
+        if let Some(ref recv) = self.account_refresh {
+            loop {
+                match recv.try_recv() {
+                    Ok((_, Ok(profile))) => {
+                        // Switching accounts may have saved in the
+                        // meantime so reload before updating.
+                        let mut saved = accounts::Accounts::load();
+                        if saved.find(&profile.id).is_none() {
+                            // Removed whilst being refreshed
+                            continue;
+                        }
+                        if *self.vars.get(auth::CL_UUID) == profile.id {
+                            self.vars.set(auth::AUTH_TOKEN, profile.access_token.clone());
+                        }
+                        saved.insert(profile);
+                        saved.save();
+                    },
+                    Ok((id, Err(err))) => warn!("Failed to refresh the token for {}: {}", id, err),
+                    Err(mpsc::TryRecvError::Empty) => return,
+                    Err(mpsc::TryRecvError::Disconnected) => break,
+                }
+            }
+            self.account_refresh = None;
+        }
+        if Instant::now() < self.next_account_refresh {
+            return;
+        }
+        self.next_account_refresh = Instant::now() + ACCOUNT_REFRESH_INTERVAL;
+        let client_token = self.vars.get(auth::AUTH_CLIENT_TOKEN).clone();
+        let stale = accounts::Accounts::load().stale_profiles();
+        if !client_token.is_empty() && !stale.is_empty() {
+            self.account_refresh = Some(accounts::refresh_in_background(stale, client_token));
+        }
+    }
+
     pub fn tick(&mut self, delta: f64) {
+        self.update_resource_packs();
+        self.update_server_pack();
+        self.update_hot_reload();
+        self.refresh_accounts();
+        self.run_commands();
         if !self.server.is_connected() {
             self.renderer.camera.yaw += 0.005 * delta;
             if self.renderer.camera.yaw > ::std::f64::consts::PI * 2.0 {
@@ -124,6 +318,18 @@ impl Game {
             }
         }
 
+        if let Some(request) = self.server.resource_pack_request.take() {
+            match self.resource_pack_policy {
+                server::resource_pack::Policy::Enabled => self.server.respond_to_resource_pack(request, true),
+                server::resource_pack::Policy::Disabled => self.server.respond_to_resource_pack(request, false),
+                server::resource_pack::Policy::Prompt => {
+                    self.focused = false;
+                    let answerer = self.server.resource_pack_answerer();
+                    self.screen_sys.add_screen(Box::new(screen::resource_pack_prompt::ResourcePackPrompt::new(request, answerer)));
+                },
+            }
+        }
+
         if let Some(disconnect_reason) = self.server.disconnect_reason.take() {
             self.screen_sys.replace_screen(Box::new(screen::ServerList::new(
                 Some(disconnect_reason)
@@ -163,30 +369,62 @@ impl Game {
         if clear_reply {
             self.connect_reply = None;
         }
+
+        // The integrated server is shut down once it is left
+        let left_integrated = !self.on_integrated || !self.server.is_connected();
+        if self.integrated_server.is_some() && self.connect_reply.is_none() && left_integrated {
+            self.integrated_server = None;
+        }
+    }
+}
+
+/// Sets the default proxy from the value of cl_proxy.
+fn set_proxy(setting: &str) {
+    match protocol::proxy::Proxy::parse(setting) {
+        Ok(proxy) => protocol::proxy::set_default_proxy(proxy),
+        Err(err) => {
+            error!("{}", err);
+            protocol::proxy::set_default_proxy(None);
+        },
     }
 }
 
 fn main() {
     let con = Arc::new(Mutex::new(console::Console::new()));
-    let (vars, vsync) = {
+    let (vars, vsync, config_errors) = {
         let mut vars = console::Vars::new();
         vars.register(CL_BRAND);
         auth::register_vars(&mut vars);
         settings::register_vars(&mut vars);
-        vars.load_config();
+        let config_errors = vars.load_config();
         vars.save_config();
         let vsync = *vars.get(settings::R_VSYNC);
-        (Rc::new(vars), vsync)
+        (Rc::new(vars), vsync, config_errors)
     };
 
+    let mut commands = console::Commands::new();
+    commands::register_commands(&mut commands);
+
     let proxy = console::ConsoleProxy::new(con.clone());
 
     log::set_boxed_logger(Box::new(proxy)).unwrap();
     log::set_max_level(log::LevelFilter::Trace);
 
     info!("Starting steven");
+    for err in config_errors {
+        warn!("conf.cfg: {}", err);
+    }
 
-    let (res, mut resui) = resources::Manager::new();
+    let local_path = |val: &str| if val.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(val))
+    };
+    let local_assets = resources::LocalAssets {
+        client_jar: local_path(&vars.get(settings::CL_ASSETS_JAR)),
+        assets_dir: local_path(&vars.get(settings::CL_ASSETS_DIR)),
+    };
+    let (res, mut resui) = resources::Manager::new(&local_assets);
     let resource_manager = Arc::new(RwLock::new(res));
 
     let mut events_loop = glutin::EventsLoop::new();
@@ -227,10 +465,18 @@ fn main() {
         resource_manager: resource_manager.clone(),
         console: con,
         vars,
+        commands,
         should_close: false,
         chunk_builder: chunk_builder::ChunkBuilder::new(resource_manager, textures),
         connect_reply: None,
-        protocol_version: protocol::SUPPORTED_PROTOCOLS[0],
+        integrated_server: None,
+        on_integrated: false,
+        resource_packs_changed: Rc::new(Cell::new(true)),
+        server_pack: None,
+        resource_pack_policy: server::resource_pack::Policy::Prompt,
+        hot_reload_changed: Rc::new(Cell::new(true)),
+        account_refresh: None,
+        next_account_refresh: Instant::now() + ACCOUNT_REFRESH_INTERVAL,
         dpi_factor,
         last_mouse_x: 0.0,
         last_mouse_y: 0.0,
@@ -240,6 +486,23 @@ fn main() {
     };
     game.renderer.camera.pos = cgmath::Point3::new(0.5, 13.2, 0.5);
 
+    let fov = game.renderer.fov();
+    fov.set(*game.vars.get(settings::R_FOV) as f64);
+    game.vars.subscribe(settings::R_FOV, move |&val| fov.set(val as f64));
+    set_proxy(&game.vars.get(settings::CL_PROXY));
+    game.vars.subscribe(settings::CL_PROXY, |val| set_proxy(val));
+    let textures = game.renderer.get_textures();
+    textures.write().unwrap().set_texture_server(&game.vars.get(auth::AUTH_TEXTURE_SERVER));
+    game.vars.subscribe(auth::AUTH_TEXTURE_SERVER, move |val| textures.write().unwrap().set_texture_server(val));
+    let changed = game.resource_packs_changed.clone();
+    game.vars.subscribe(settings::CL_RESOURCE_PACKS, move |_| changed.set(true));
+    let changed = game.hot_reload_changed.clone();
+    game.vars.subscribe(settings::CL_HOT_RELOAD, move |_| changed.set(true));
+    let changed = game.hot_reload_changed.clone();
+    game.vars.subscribe(settings::R_SHADER_DIR, move |_| changed.set(true));
+    // TODO: after https://github.com/tomaka/glutin/issues/693 Allow changing vsync on a Window
+    game.vars.subscribe(settings::R_VSYNC, |_| warn!("Changing vsync takes effect after restarting"));
+
     let mut last_resource_version = 0;
     while !game.should_close {
 
@@ -264,13 +527,6 @@ fn main() {
         };
         last_resource_version = version;
 
-        let vsync_changed = *game.vars.get(settings::R_VSYNC);
-        if vsync != vsync_changed {
-            println!("Changing vsync currently requires restarting");
-            break;
-            // TODO: after https://github.com/tomaka/glutin/issues/693 Allow changing vsync on a Window
-            //vsync = vsync_changed;
-        }
         let fps_cap = *game.vars.get(settings::R_MAX_FPS);
 
         game.tick(delta);
@@ -361,7 +617,10 @@ fn handle_window_event(window: &mut glutin::GlWindow,
             },
 
             WindowEvent::ReceivedCharacter(codepoint) => {
-                if !game.focused {
+                let console_active = game.console.lock().unwrap().is_active();
+                if console_active {
+                    game.console.lock().unwrap().key_type(codepoint);
+                } else if !game.focused {
                     ui_container.key_type(game, codepoint);
                 }
             },
@@ -414,6 +673,28 @@ fn handle_window_event(window: &mut glutin::GlWindow,
                 }
             },
             WindowEvent::KeyboardInput{device_id: _, input} => {
+                // The open console takes the keyboard, apart from
+                // releasing keys held down in game
+                let console_active = game.console.lock().unwrap().is_active();
+                match (console_active, input.state, input.virtual_keycode) {
+                    (true, _, Some(VirtualKeyCode::Grave)) | (false, _, _) | (_, _, None) => {},
+                    (true, ElementState::Pressed, Some(key)) => {
+                        game.console.lock().unwrap().key_press(key, input.modifiers.ctrl, &game.commands, &game.vars);
+                        return;
+                    },
+                    (true, ElementState::Released, Some(VirtualKeyCode::Escape)) => {
+                        game.console.lock().unwrap().toggle();
+                        return;
+                    },
+                    (true, ElementState::Released, Some(key)) => {
+                        if game.focused {
+                            if let Some(steven_key) = settings::Stevenkey::get_by_keycode(key, &game.vars) {
+                                game.server.key_press(false, steven_key);
+                            }
+                        }
+                        return;
+                    },
+                }
                 match (input.state, input.virtual_keycode) {
                     (ElementState::Released, Some(VirtualKeyCode::Escape)) => {
                         if game.focused {
@@ -0,0 +1,86 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// A minimal http server for tests of code that downloads things.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// A request received by `serve`.
+#[derive(Debug)]
+pub struct Request {
+    /// The request line, e.g. `GET /path HTTP/1.1`
+    pub line: String,
+    pub path: String,
+    /// The headers, with lowercase names
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| &value[..])
+    }
+}
+
+/// Answers `count` requests with `handler`, which returns the status
+/// (e.g. `200 OK`) and body of the response. Returns the url of the
+/// server and a handle returning the requests once all were answered.
+pub fn serve<F, S>(count: usize, mut handler: F) -> (String, thread::JoinHandle<Vec<Request>>)
+    where F: FnMut(&Request) -> (S, Vec<u8>) + Send + 'static,
+          S: AsRef<str>
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut requests = vec![];
+        for _ in 0..count {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = vec![];
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header == "\r\n" {
+                    break;
+                }
+                if let Some(pos) = header.find(':') {
+                    headers.push((header[..pos].trim().to_lowercase(), header[pos + 1..].trim().to_owned()));
+                }
+            }
+            let mut request = Request {
+                path: line.split(' ').nth(1).unwrap_or("").to_owned(),
+                line: line.trim().to_owned(),
+                headers,
+                body: String::new(),
+            };
+            let length = request.header("content-length").map_or(0, |v| v.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.body = String::from_utf8(body).unwrap();
+
+            let (status, body) = handler(&request);
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status.as_ref(), body.len()).unwrap();
+            stream.write_all(&body).unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (url, handle)
+}
@@ -0,0 +1,210 @@
+// Records the packets sent and received by a connection so that
+// misbehaving servers can be debugged offline. Captures are plain
+// text, one packet per line:
+//
+//     <unix millis>\t<state>\t<direction>\t<id>\t<hex payload>\t<debug>
+//
+// The payload is the decompressed packet body following the id, so a
+// capture can be decoded again with `packet_by_id` for any version.
+// Each connection appends its own header, naming the protocol version
+// it used, followed by its packets.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use hex;
+use super::{packet, Direction, Error, State};
+
+const HEADER: &str = "# steven packet capture, protocol ";
+
+/// A shared handle to a capture file. Clones of a connection share
+/// the same capture so both halves end up in one file.
+#[derive(Clone)]
+pub struct Capture {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Capture {
+    /// Starts a capture at the end of the file at `path`, creating
+    /// it if needed, so reconnecting keeps the earlier captures.
+    pub fn create<P: AsRef<Path>>(path: P, protocol_version: i32) -> Result<Capture, Error> {
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Capture::from_writer(io::BufWriter::new(file), protocol_version)
+    }
+
+    pub fn from_writer<W: Write + Send + 'static>(mut out: W, protocol_version: i32) -> Result<Capture, Error> {
+        writeln!(out, "{}{}", HEADER, protocol_version)?;
+        out.flush()?;
+        Ok(Capture {
+            out: Arc::new(Mutex::new(Box::new(out))),
+        })
+    }
+
+    /// Appends a packet to the capture. Failures are ignored as a
+    /// broken capture shouldn't take the connection down with it.
+    pub fn record(&self, state: State, direction: Direction, id: i32, data: &[u8], debug: &str) {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1000 + u64::from(d.subsec_millis()))
+            .unwrap_or(0);
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}\t{:?}\t{:?}\t{}\t{}\t{}",
+            timestamp, state, direction, id, hex::encode(data), debug.replace('\n', " "));
+        let _ = out.flush();
+    }
+}
+
+/// A single packet read back from a capture.
+#[derive(Debug)]
+pub struct Record {
+    /// The version the connection used, from the capture's header
+    pub protocol_version: i32,
+    pub timestamp: u64,
+    pub state: State,
+    pub direction: Direction,
+    pub id: i32,
+    pub data: Vec<u8>,
+    /// The `Debug` form of the packet at the time it was captured.
+    pub debug: String,
+}
+
+impl Record {
+    /// Decodes the captured payload as the given protocol version
+    /// would, without needing the original server.
+    pub fn decode(&self, protocol_version: i32) -> Result<Option<packet::Packet>, Error> {
+        let mut buf = io::Cursor::new(&self.data[..]);
+        let packet = packet::packet_by_id(protocol_version, self.state, self.direction, self.id, &mut buf)?;
+        let left = self.data.len() - buf.position() as usize;
+        if packet.is_some() && left != 0 {
+            return Err(Error::Err(format!("Failed to read all of packet 0x{:X}, had {} bytes left", self.id, left)));
+        }
+        Ok(packet)
+    }
+}
+
+/// Loads every packet in a capture file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Record>, Error> {
+    read_records(io::BufReader::new(fs::File::open(path)?))
+}
+
+pub fn read_records<R: BufRead>(input: R) -> Result<Vec<Record>, Error> {
+    let mut records = vec![];
+    let mut protocol_version = None;
+    for (num, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.starts_with(HEADER) {
+            protocol_version = Some(line[HEADER.len()..].trim().parse()
+                .map_err(|_| Error::Err(format!("Malformed capture header on line {}", num + 1)))?);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let protocol_version = protocol_version
+            .ok_or_else(|| Error::Err(format!("Capture record on line {} comes before any header", num + 1)))?;
+        let record = parse_record(protocol_version, &line)
+            .ok_or_else(|| Error::Err(format!("Malformed capture record on line {}", num + 1)))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn parse_record(protocol_version: i32, line: &str) -> Option<Record> {
+    let mut parts = line.splitn(6, '\t');
+    let timestamp = parts.next()?.parse().ok()?;
+    let state = match parts.next()? {
+        "Handshaking" => State::Handshaking,
+        "Play" => State::Play,
+        "Status" => State::Status,
+        "Login" => State::Login,
+        _ => return None,
+    };
+    let direction = match parts.next()? {
+        "Serverbound" => Direction::Serverbound,
+        "Clientbound" => Direction::Clientbound,
+        _ => return None,
+    };
+    let id = parts.next()?.parse().ok()?;
+    let data = hex::decode(parts.next()?).ok()?;
+    let debug = parts.next().unwrap_or("").to_owned();
+    Some(Record {
+        protocol_version,
+        timestamp,
+        state,
+        direction,
+        id,
+        data,
+        debug,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::{PacketType, VarInt};
+
+    #[derive(Clone)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let out = Shared(Arc::new(Mutex::new(vec![])));
+        let capture = Capture::from_writer(out.clone(), 404).unwrap();
+
+        let handshake = packet::handshake::serverbound::Handshake {
+            protocol_version: VarInt(404),
+            host: "localhost".to_owned(),
+            port: 25565,
+            next: VarInt(1),
+        };
+        let id = handshake.packet_id(404).unwrap();
+        let debug = format!("{:?}", handshake);
+        let mut data = vec![];
+        handshake.write(&mut data).unwrap();
+        capture.record(State::Handshaking, Direction::Serverbound, id, &data, &debug);
+
+        let text = out.0.lock().unwrap().clone();
+        let records = read_records(io::Cursor::new(text)).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].protocol_version, 404);
+        assert_eq!(records[0].id, id);
+        assert_eq!(records[0].data, data);
+        assert_eq!(records[0].debug, debug);
+
+        match records[0].decode(404).unwrap() {
+            Some(packet::Packet::Handshake(val)) => {
+                assert_eq!(val.host, "localhost");
+                assert_eq!(val.port, 25565);
+            },
+            other => panic!("unexpected packet {:?}", other),
+        }
+    }
+
+    #[test]
+    fn appends_sessions() {
+        let path = std::env::temp_dir().join(format!("steven-capture-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        for &version in &[404, 340] {
+            let capture = Capture::create(&path, version).unwrap();
+            capture.record(State::Status, Direction::Serverbound, 0, &[], "StatusRequest");
+        }
+        let records = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].protocol_version, 404);
+        assert_eq!(records[1].protocol_version, 340);
+
+        assert!(read_records(io::Cursor::new("0\tStatus\tServerbound\t0\t\t")).is_err());
+    }
+}
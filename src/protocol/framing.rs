@@ -0,0 +1,283 @@
+// Packet framing: the length prefix each packet is sent with and,
+// once the server enables it, compression.
+//
+// The buffers and zlib streams are kept between packets so that
+// framing a packet doesn't allocate once they have grown to fit the
+// largest packet seen. Uncompressed packets aren't copied at all,
+// only a header is generated for them.
+
+use std::io::{self, Write};
+use std::mem;
+use flate2::write::{ZlibDecoder, ZlibEncoder};
+use flate2::Compression;
+use super::{Error, Serializable, VarInt};
+
+pub struct Framing {
+    threshold: i32,
+    encoder: ZlibEncoder<Vec<u8>>,
+    decoder: ZlibDecoder<Vec<u8>>,
+    header: Vec<u8>,
+    /// The last packet compressed or decompressed
+    scratch: Vec<u8>,
+}
+
+impl Default for Framing {
+    fn default() -> Framing {
+        Framing::new(-1)
+    }
+}
+
+impl Framing {
+    /// Creates framing which compresses packets larger than
+    /// `threshold` bytes. A negative threshold disables compression.
+    pub fn new(threshold: i32) -> Framing {
+        Framing {
+            threshold,
+            encoder: ZlibEncoder::new(Vec::new(), Compression::default()),
+            decoder: ZlibDecoder::new(Vec::new()),
+            header: Vec::with_capacity(10),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn threshold(&self) -> i32 {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: i32) {
+        self.threshold = threshold;
+    }
+
+    /// Frames `packet`, its id followed by its body, for sending.
+    ///
+    /// The frame is returned as a header and a body which are sent
+    /// one after the other. The body is either `packet` itself or
+    /// the compressed packet, so both can be encrypted in place.
+    pub fn encode<'a>(&'a mut self, packet: &'a mut [u8]) -> Result<(&'a mut [u8], &'a mut [u8]), Error> {
+        self.header.clear();
+        if self.threshold < 0 {
+            VarInt(packet.len() as i32).write_to(&mut self.header)?;
+            return Ok((&mut self.header[..], packet));
+        }
+        if packet.len() as i32 <= self.threshold {
+            // A data length of 0 marks the packet as uncompressed
+            VarInt(packet.len() as i32 + 1).write_to(&mut self.header)?;
+            self.header.push(0);
+            return Ok((&mut self.header[..], packet));
+        }
+
+        // Compress straight into the scratch buffer, the encoder's
+        // own writer is only a placeholder between packets.
+        let mut out = mem::replace(&mut self.scratch, Vec::new());
+        out.clear();
+        mem::swap(self.encoder.get_mut(), &mut out);
+        self.encoder.write_all(packet)?;
+        self.scratch = self.encoder.reset(Vec::new())?;
+
+        let data_length = packet.len() as i32;
+        VarInt(varint_size(data_length) as i32 + self.scratch.len() as i32).write_to(&mut self.header)?;
+        VarInt(data_length).write_to(&mut self.header)?;
+        Ok((&mut self.header[..], &mut self.scratch[..]))
+    }
+
+    /// Unpacks a received frame, without its length prefix, into the
+    /// packet's id followed by its body.
+    pub fn decode<'a>(&'a mut self, frame: &'a [u8]) -> Result<&'a [u8], Error> {
+        if self.threshold < 0 {
+            return Ok(frame);
+        }
+        let mut cursor = io::Cursor::new(frame);
+        let data_length = VarInt::read_from(&mut cursor)?.0;
+        let data = &frame[cursor.position() as usize..];
+        if data_length == 0 {
+            return Ok(data);
+        }
+        if data_length < 0 {
+            return Err(Error::Err(format!("Invalid uncompressed packet length {}", data_length)));
+        }
+
+        let mut out = mem::replace(&mut self.scratch, Vec::new());
+        out.clear();
+        out.reserve(data_length as usize);
+        mem::swap(self.decoder.get_mut(), &mut out);
+        self.decoder.write_all(data)?;
+        self.scratch = self.decoder.reset(Vec::new())?;
+        if self.scratch.len() != data_length as usize {
+            return Err(Error::Err(format!("Packet decompressed to {} bytes, expected {}", self.scratch.len(), data_length)));
+        }
+        Ok(&self.scratch[..])
+    }
+}
+
+/// Returns the number of bytes `val` takes up as a VarInt.
+fn varint_size(val: i32) -> usize {
+    let mut val = val as u32;
+    let mut size = 1;
+    while val >= 0x80 {
+        val >>= 7;
+        size += 1;
+    }
+    size
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use flate2::read;
+
+    // The framing `Conn` used before `Framing`, kept to check the
+    // two stay compatible.
+    fn legacy_encode(mut buf: Vec<u8>, threshold: i32) -> Vec<u8> {
+        let mut extra = if threshold >= 0 { 1 } else { 0 };
+        if threshold >= 0 && buf.len() as i32 > threshold {
+            extra = 0;
+            let mut new = Vec::new();
+            VarInt(buf.len() as i32).write_to(&mut new).unwrap();
+            let mut write = read::ZlibEncoder::new(io::Cursor::new(buf), Compression::default());
+            write.read_to_end(&mut new).unwrap();
+            buf = new;
+        }
+        let mut out = vec![];
+        VarInt(buf.len() as i32 + extra).write_to(&mut out).unwrap();
+        if threshold >= 0 && extra == 1 {
+            VarInt(0).write_to(&mut out).unwrap();
+        }
+        out.extend_from_slice(&buf);
+        out
+    }
+
+    fn legacy_decode(frame: &[u8], threshold: i32) -> Vec<u8> {
+        let mut buf = io::Cursor::new(frame);
+        let len = VarInt::read_from(&mut buf).unwrap().0 as usize;
+        let mut ibuf = vec![0; len];
+        buf.read_exact(&mut ibuf).unwrap();
+        let mut buf = io::Cursor::new(ibuf);
+        if threshold >= 0 {
+            let uncompressed_size = VarInt::read_from(&mut buf).unwrap().0;
+            if uncompressed_size != 0 {
+                let mut new = Vec::with_capacity(uncompressed_size as usize);
+                read::ZlibDecoder::new(buf).read_to_end(&mut new).unwrap();
+                return new;
+            }
+        }
+        let pos = buf.position() as usize;
+        buf.into_inner()[pos..].to_vec()
+    }
+
+    fn encode(framing: &mut Framing, packet: &[u8]) -> Vec<u8> {
+        let mut packet = packet.to_vec();
+        let (header, body) = framing.encode(&mut packet).unwrap();
+        let mut frame = header.to_vec();
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    fn decode(framing: &mut Framing, frame: &[u8]) -> Vec<u8> {
+        let mut buf = io::Cursor::new(frame);
+        let len = VarInt::read_from(&mut buf).unwrap().0 as usize;
+        let start = buf.position() as usize;
+        assert_eq!(start + len, frame.len());
+        framing.decode(&frame[start..]).unwrap().to_vec()
+    }
+
+    fn test_packets() -> Vec<Vec<u8>> {
+        vec![
+            vec![0x00],
+            vec![0x21, 1, 2, 3, 4, 5, 6, 7, 8],
+            (0..255u8).collect(),
+            (0..4096).map(|i| (i * 7 % 13) as u8).collect(),
+            (0..200_000).map(|i| (i % 251) as u8).collect(),
+        ]
+    }
+
+    #[test]
+    fn compatible_with_legacy() {
+        for &threshold in &[-1, 0, 256] {
+            let mut framing = Framing::new(threshold);
+            for packet in test_packets() {
+                let frame = encode(&mut framing, &packet);
+                assert_eq!(frame, legacy_encode(packet.clone(), threshold));
+                assert_eq!(legacy_decode(&frame, threshold), packet);
+                assert_eq!(decode(&mut framing, &legacy_encode(packet.clone(), threshold)), packet);
+            }
+        }
+    }
+
+    #[test]
+    fn decompressed_length_checked() {
+        let mut framing = Framing::new(0);
+        let mut frame = encode(&mut framing, &[1, 2, 3, 4]);
+        // Claim the packet is one byte longer than it is
+        frame[1] = 5;
+        assert!(framing.decode(&frame[1..]).is_err());
+    }
+
+    #[test]
+    fn varint_sizes() {
+        for &val in &[0, 1, 127, 128, 16383, 16384, 2097151, 2097152, ::std::i32::MAX, -1] {
+            let mut buf = vec![];
+            VarInt(val).write_to(&mut buf).unwrap();
+            assert_eq!(varint_size(val), buf.len());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+    use crate::test::{black_box, Bencher};
+    use cfb8::stream_cipher::{NewStreamCipher, StreamCipher};
+    use crate::protocol::Aes128Cfb;
+
+    const KEY: [u8; 16] = [7; 16];
+
+    // Roughly the size of a chunk data packet, mostly runs of the
+    // same block with some noise so it compresses like one.
+    fn chunk_packet() -> Vec<u8> {
+        (0..100_000u32)
+            .map(|i| if i % 64 < 48 { 1 } else { (i.wrapping_mul(2_654_435_761) >> 24) as u8 })
+            .collect()
+    }
+
+    #[bench]
+    fn encode_chunk(b: &mut Bencher) {
+        let mut framing = Framing::new(256);
+        let mut cipher = Aes128Cfb::new_var(&KEY, &KEY).unwrap();
+        let mut packet = chunk_packet();
+        b.bytes = packet.len() as u64;
+        b.iter(|| {
+            let (header, body) = framing.encode(&mut packet).unwrap();
+            cipher.encrypt(header);
+            cipher.encrypt(body);
+            black_box(body.len());
+        });
+    }
+
+    #[bench]
+    fn decode_chunk(b: &mut Bencher) {
+        let packet = chunk_packet();
+        let mut frame = {
+            let mut framing = Framing::new(256);
+            let mut packet = packet.clone();
+            let (header, body) = framing.encode(&mut packet).unwrap();
+            let mut frame = header.to_vec();
+            frame.extend_from_slice(body);
+            frame
+        };
+        Aes128Cfb::new_var(&KEY, &KEY).unwrap().encrypt(&mut frame);
+
+        let mut framing = Framing::new(256);
+        let mut buf = frame.clone();
+        b.bytes = packet.len() as u64;
+        b.iter(|| {
+            buf.copy_from_slice(&frame);
+            Aes128Cfb::new_var(&KEY, &KEY).unwrap().decrypt(&mut buf);
+            let mut cursor = io::Cursor::new(&buf[..]);
+            let len = VarInt::read_from(&mut cursor).unwrap().0 as usize;
+            let start = cursor.position() as usize;
+            let decoded = framing.decode(&buf[start..start + len]).unwrap();
+            assert_eq!(decoded.len(), packet.len());
+        });
+    }
+}
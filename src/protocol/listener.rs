@@ -0,0 +1,330 @@
+// Accepts connections from clients so that a server can be implemented
+// with the same packet definitions the client uses. Only the parts
+// needed to get a client into the play state are handled here:
+// the handshake, status pings and offline mode logins with optional
+// compression. Everything after that is up to the caller.
+
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::time::Duration;
+use crate::format;
+use super::{packet, Conn, Direction, Error, State, Status, VarInt, UUID, SUPPORTED_PROTOCOLS};
+use super::packet::Packet;
+
+/// How long a client gets to send each packet before it is logged
+/// in, so that one which never does doesn't tie up its thread.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Listener {
+    listener: TcpListener,
+    handshake_timeout: Duration,
+}
+
+/// A client that has connected but not sent its handshake yet.
+pub struct PendingClient {
+    conn: Conn,
+}
+
+/// The handshake sent by a client when it first connects.
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    pub protocol_version: i32,
+    pub host: String,
+    pub port: u16,
+}
+
+/// A client that has completed the handshake, split by
+/// what it wants to do next.
+pub enum Incoming {
+    Status(StatusRequest),
+    Login(LoginRequest),
+}
+
+impl Listener {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Listener, Error> {
+        Ok(Listener {
+            listener: TcpListener::bind(addr)?,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Waits for the next client. Its handshake should be read on
+    /// another thread, so that a slow client doesn't hold up the
+    /// ones connecting after it.
+    pub fn accept(&self) -> Result<PendingClient, Error> {
+        let (stream, addr) = self.listener.accept()?;
+        stream.set_read_timeout(Some(self.handshake_timeout))?;
+        // The handshake is the same in every version, the real
+        // version is only known once it has been read.
+        let conn = Conn::from_stream(
+            stream,
+            addr.ip().to_string(),
+            addr.port(),
+            Direction::Clientbound,
+            SUPPORTED_PROTOCOLS[0],
+            None,
+        )?;
+        Ok(PendingClient { conn })
+    }
+}
+
+impl PendingClient {
+    /// Reads the client's handshake, and its login start when it
+    /// wants to log in.
+    pub fn handshake(self) -> Result<Incoming, Error> {
+        let mut conn = self.conn;
+        let handshake = match conn.read_packet()? {
+            Packet::Handshake(val) => val,
+            val => return Err(Error::Err(format!("Wrong packet: {:?}", val))),
+        };
+        let info = Handshake {
+            protocol_version: handshake.protocol_version.0,
+            host: handshake.host,
+            port: handshake.port,
+        };
+        if SUPPORTED_PROTOCOLS.contains(&info.protocol_version) {
+            conn.protocol_version = info.protocol_version;
+        }
+
+        match handshake.next.0 {
+            1 => {
+                conn.state = State::Status;
+                Ok(Incoming::Status(StatusRequest {
+                    handshake: info,
+                    conn,
+                }))
+            },
+            2 => {
+                conn.state = State::Login;
+                let username = match conn.read_packet()? {
+                    Packet::LoginStart(val) => val.username,
+                    val => return Err(Error::Err(format!("Wrong packet: {:?}", val))),
+                };
+                Ok(Incoming::Login(LoginRequest {
+                    handshake: info,
+                    username,
+                    conn,
+                }))
+            },
+            next => Err(Error::Err(format!("Invalid next state {}", next))),
+        }
+    }
+}
+
+/// A client pinging the server for its status.
+pub struct StatusRequest {
+    pub handshake: Handshake,
+    conn: Conn,
+}
+
+impl StatusRequest {
+    /// Sends the status to the client and answers its ping, if
+    /// it sends one.
+    pub fn respond(mut self, status: &Status) -> Result<(), Error> {
+        match self.conn.read_packet()? {
+            Packet::StatusRequest(_) => {},
+            val => return Err(Error::Err(format!("Wrong packet: {:?}", val))),
+        }
+        self.conn.write_packet(packet::status::clientbound::StatusResponse {
+            status: serde_json::to_string(&status.to_value())?,
+        })?;
+        match self.conn.read_packet() {
+            Ok(Packet::StatusPing(val)) => {
+                self.conn.write_packet(packet::status::clientbound::StatusPong {
+                    ping: val.ping,
+                })
+            },
+            Ok(val) => Err(Error::Err(format!("Wrong packet: {:?}", val))),
+            // Clients are free to hang up without pinging
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// A client trying to log in.
+pub struct LoginRequest {
+    pub handshake: Handshake,
+    pub username: String,
+    conn: Conn,
+}
+
+impl LoginRequest {
+    /// Whether the server can talk to the client at all, clients
+    /// using unsupported versions can only be disconnected.
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_PROTOCOLS.contains(&self.handshake.protocol_version)
+    }
+
+    /// Logs the client in without authentication, as offline mode
+    /// servers do. Packets larger than `compression_threshold` will
+    /// be compressed, a negative threshold disables compression.
+    ///
+    /// Returns the connection, now in the play state and without the
+    /// handshake's read timeout, along with the UUID given to the
+    /// player.
+    pub fn accept_offline(mut self, compression_threshold: i32) -> Result<(Conn, UUID), Error> {
+        if !self.is_supported() {
+            let version = self.handshake.protocol_version;
+            self.disconnect(format::Component::Text(format::TextComponent::new(
+                &format!("Unsupported protocol version {}", version)
+            )))?;
+            return Err(Error::UnsupportedVersion(version));
+        }
+        if compression_threshold >= 0 && self.conn.protocol_version >= 47 {
+            self.conn.write_packet(packet::login::clientbound::SetInitialCompression {
+                threshold: VarInt(compression_threshold),
+            })?;
+            self.conn.set_compresssion(compression_threshold);
+        }
+        let uuid = UUID::offline(&self.username);
+        self.conn.write_packet(packet::login::clientbound::LoginSuccess {
+            uuid: uuid.to_string(),
+            username: self.username,
+        })?;
+        self.conn.state = State::Play;
+        self.conn.set_read_timeout(None)?;
+        Ok((self.conn, uuid))
+    }
+
+    /// Refuses the login with the given reason.
+    pub fn disconnect(mut self, reason: format::Component) -> Result<(), Error> {
+        self.conn.write_packet(packet::login::clientbound::LoginDisconnect {
+            reason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+    use std::time::Instant;
+    use crate::protocol::{StatusPlayers, StatusVersion};
+
+    #[test]
+    fn status() {
+        let listener = Listener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            match listener.accept().unwrap().handshake().unwrap() {
+                Incoming::Status(req) => req.respond(&Status {
+                    version: StatusVersion {
+                        name: "test".to_owned(),
+                        protocol: 404,
+                    },
+                    players: StatusPlayers {
+                        max: 20,
+                        online: 1,
+                        sample: vec![],
+                    },
+                    description: format::Component::Text(format::TextComponent::new("A test server")),
+                    favicon: None,
+                    forge: None,
+                }).unwrap(),
+                Incoming::Login(_) => panic!("expected a status request"),
+            }
+        });
+
+        let (status, _) = Conn::new(&addr.to_string(), 404).unwrap().do_status().unwrap();
+        server.join().unwrap();
+        assert_eq!(status.version.name, "test");
+        assert_eq!(status.players.max, 20);
+        assert_eq!(status.description.to_string(), "A test server");
+    }
+
+    #[test]
+    fn offline_login() {
+        let listener = Listener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let req = match listener.accept().unwrap().handshake().unwrap() {
+                Incoming::Login(req) => req,
+                Incoming::Status(_) => panic!("expected a login"),
+            };
+            assert_eq!(req.username, "Steve");
+            let (mut conn, _) = req.accept_offline(16).unwrap();
+            conn.write_packet(packet::play::clientbound::KeepAliveClientbound_i64 {
+                id: 1234,
+            }).unwrap();
+        });
+
+        let mut conn = Conn::new(&addr.to_string(), 404).unwrap();
+        conn.write_packet(packet::handshake::serverbound::Handshake {
+            protocol_version: VarInt(404),
+            host: "localhost".to_owned(),
+            port: addr.port(),
+            next: VarInt(2),
+        }).unwrap();
+        conn.state = State::Login;
+        conn.write_packet(packet::login::serverbound::LoginStart {
+            username: "Steve".to_owned(),
+        }).unwrap();
+        match conn.read_packet().unwrap() {
+            Packet::SetInitialCompression(val) => conn.set_compresssion(val.threshold.0),
+            val => panic!("unexpected packet {:?}", val),
+        }
+        match conn.read_packet().unwrap() {
+            Packet::LoginSuccess(val) => {
+                // Matches what vanilla offline mode servers hand out
+                assert_eq!(val.uuid, "5627dd98-e6be-3c21-b8a8-e92344183641");
+                assert_eq!(UUID::from_str(&val.uuid), UUID::offline("Steve"));
+            },
+            val => panic!("unexpected packet {:?}", val),
+        }
+        conn.state = State::Play;
+        match conn.read_packet().unwrap() {
+            Packet::KeepAliveClientbound_i64(val) => assert_eq!(val.id, 1234),
+            val => panic!("unexpected packet {:?}", val),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn silent_clients_time_out() {
+        let mut listener = Listener::bind("127.0.0.1:0").unwrap();
+        listener.handshake_timeout = Duration::from_millis(50);
+        let addr = listener.local_addr().unwrap();
+        let _client = std::net::TcpStream::connect(addr).unwrap();
+        assert!(listener.accept().unwrap().handshake().is_err());
+    }
+
+    #[test]
+    fn silent_clients_dont_block_others() {
+        let listener = Listener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for _ in 0 .. 2 {
+                let client = listener.accept().unwrap();
+                thread::spawn(move || {
+                    if let Ok(Incoming::Status(req)) = client.handshake() {
+                        req.respond(&Status {
+                            version: StatusVersion {
+                                name: "test".to_owned(),
+                                protocol: 404,
+                            },
+                            players: StatusPlayers {
+                                max: 20,
+                                online: 0,
+                                sample: vec![],
+                            },
+                            description: format::Component::Text(format::TextComponent::new("")),
+                            favicon: None,
+                            forge: None,
+                        }).unwrap();
+                    }
+                });
+            }
+        });
+
+        // Never sends its handshake
+        let _silent = std::net::TcpStream::connect(addr).unwrap();
+        let start = Instant::now();
+        let (status, _) = Conn::new(&addr.to_string(), 404).unwrap().do_status().unwrap();
+        assert_eq!(status.version.name, "test");
+        assert!(start.elapsed() < HANDSHAKE_TIMEOUT);
+        server.join().unwrap();
+    }
+}
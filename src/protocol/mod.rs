@@ -22,6 +22,12 @@ use serde_json;
 use reqwest;
 
 pub mod mojang;
+pub mod resolve;
+pub mod capture;
+pub mod listener;
+pub mod proxy;
+pub mod framing;
+pub mod signature;
 
 use crate::nbt;
 use crate::format;
@@ -29,18 +35,43 @@ use std::fmt;
 use std::default;
 use std::net::TcpStream;
 use std::io;
-use std::io::{Write, Read};
+use std::io::{Write, Read, BufReader, BufWriter};
+use std::mem;
 use std::convert;
 use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
-use flate2::read::{ZlibDecoder, ZlibEncoder};
-use flate2::Compression;
 use std::time::{Instant, Duration};
+use std::cell::Cell;
+use std::path::PathBuf;
 use crate::shared::Position;
+use log::debug;
 
-pub const SUPPORTED_PROTOCOLS: [i32; 12] = [404, 451, 452, 340, 316, 315, 210, 109, 107, 74, 47, 5];
+pub const SUPPORTED_PROTOCOLS: [i32; 24] = [498, 490, 485, 480, 477, 452, 451, 404, 401, 393, 340, 338, 335, 316, 315, 210, 110, 109, 108, 107, 74, 47, 5, 4];
 
-// TODO: switch to using thread_local storage?, see https://doc.rust-lang.org/std/macro.thread_local.html
-pub static mut CURRENT_PROTOCOL_VERSION: i32 = SUPPORTED_PROTOCOLS[0];
+thread_local! {
+    static CURRENT_PROTOCOL_VERSION: Cell<i32> = Cell::new(SUPPORTED_PROTOCOLS[0]);
+}
+
+/// Returns the protocol version of the packet currently being read
+/// or written on this thread. Only meaningful from within
+/// `with_protocol_version`, which every `Conn` uses around its
+/// (de)serialization so that connections using different versions
+/// don't interfere with each other.
+pub fn current_protocol_version() -> i32 {
+    CURRENT_PROTOCOL_VERSION.with(|v| v.get())
+}
+
+/// Runs `f` with `current_protocol_version` returning `version`,
+/// restoring the previous value afterwards.
+pub fn with_protocol_version<T, F: FnOnce() -> T>(version: i32, f: F) -> T {
+    struct Restore(i32);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT_PROTOCOL_VERSION.with(|v| v.set(self.0));
+        }
+    }
+    let _restore = Restore(CURRENT_PROTOCOL_VERSION.with(|v| v.replace(version)));
+    f()
+}
 
 /// Helper macro for defining packets
 #[macro_export]
@@ -67,6 +98,9 @@ macro_rules! state_packets {
                 )*
             )+
         )+
+            /// A packet whose id isn't known for the connection's protocol
+            /// version, kept undecoded so it can be skipped.
+            Unknown { id: i32, data: Vec<u8> },
         }
 
         $(
@@ -97,8 +131,12 @@ macro_rules! state_packets {
 
                     impl PacketType for $name {
 
-                        fn packet_id(&self, version: i32) -> i32 {
+                        fn packet_id(&self, version: i32) -> Result<i32, Error> {
                             packet::versions::translate_internal_packet_id_for_version(version, State::$stateName, Direction::$dirName, internal_ids::$name, false)
+                                .map_err(|err| match err {
+                                    Error::UnknownPacketId { .. } => Error::Err(format!("{} is not supported by protocol version {}", stringify!($name), version)),
+                                    err => err,
+                                })
                         }
 
                         fn write<W: io::Write>(self, buf: &mut W) -> Result<(), Error> {
@@ -120,13 +158,21 @@ macro_rules! state_packets {
         /// Returns the packet for the given state, direction and id after parsing the fields
         /// from the buffer.
         pub fn packet_by_id<R: io::Read>(version: i32, state: State, dir: Direction, id: i32, mut buf: &mut R) -> Result<Option<Packet>, Error> {
-            match state {
+            with_protocol_version(version, || match state {
                 $(
                     State::$stateName => {
                         match dir {
                             $(
                                 Direction::$dirName => {
-                                    let internal_id = packet::versions::translate_internal_packet_id_for_version(version, state, dir, id, true);
+                                    let internal_id = match packet::versions::translate_internal_packet_id_for_version(version, state, dir, id, true) {
+                                        Ok(internal_id) => internal_id,
+                                        Err(Error::UnknownPacketId { .. }) => {
+                                            let mut data = Vec::new();
+                                            io::Read::read_to_end(&mut buf, &mut data)?;
+                                            return Result::Ok(Option::Some(Packet::Unknown { id, data }));
+                                        },
+                                        Err(err) => return Result::Err(err),
+                                    };
                                     match internal_id {
                                     $(
                                         self::$state::$dir::internal_ids::$name => {
@@ -147,7 +193,7 @@ macro_rules! state_packets {
                         }
                     }
                 )+
-            }
+            })
         }
     }
 }
@@ -164,7 +210,7 @@ macro_rules! protocol_packet_ids {
     })+) => {
         use crate::protocol::*;
 
-        pub fn translate_internal_packet_id(state: State, dir: Direction, id: i32, to_internal: bool) -> i32 {
+        pub fn translate_internal_packet_id(state: State, dir: Direction, id: i32, to_internal: bool) -> Option<i32> {
             match state {
                 $(
                     State::$stateName => {
@@ -174,16 +220,16 @@ macro_rules! protocol_packet_ids {
                                     if to_internal {
                                         match id {
                                         $(
-                                            $id => crate::protocol::packet::$state::$dir::internal_ids::$name,
+                                            $id => Some(crate::protocol::packet::$state::$dir::internal_ids::$name),
                                         )*
-                                            _ => panic!("bad packet id 0x{:x} in {:?} {:?}", id, dir, state),
+                                            _ => None,
                                         }
                                     } else {
                                         match id {
                                         $(
-                                            crate::protocol::packet::$state::$dir::internal_ids::$name => $id,
+                                            crate::protocol::packet::$state::$dir::internal_ids::$name => Some($id),
                                         )*
-                                            _ => panic!("bad packet internal id 0x{:x} in {:?} {:?}", id, dir, state),
+                                            _ => None,
                                         }
                                     }
                                 }
@@ -405,14 +451,19 @@ impl UUID {
     pub fn from_str(s: &str) -> UUID {
         use hex;
         // TODO: Panics aren't the best idea here
-        if s.len() != 36 {
-            panic!("Invalid UUID format");
-        }
-        let mut parts = hex::decode(&s[..8]).unwrap();
-        parts.extend_from_slice(&hex::decode(&s[9..13]).unwrap());
-        parts.extend_from_slice(&hex::decode(&s[14..18]).unwrap());
-        parts.extend_from_slice(&hex::decode(&s[19..23]).unwrap());
-        parts.extend_from_slice(&hex::decode(&s[24..36]).unwrap());
+        // 1.7.5 and older send UUIDs without hyphens
+        let parts = match s.len() {
+            32 => hex::decode(s).unwrap(),
+            36 => {
+                let mut parts = hex::decode(&s[..8]).unwrap();
+                parts.extend_from_slice(&hex::decode(&s[9..13]).unwrap());
+                parts.extend_from_slice(&hex::decode(&s[14..18]).unwrap());
+                parts.extend_from_slice(&hex::decode(&s[19..23]).unwrap());
+                parts.extend_from_slice(&hex::decode(&s[24..36]).unwrap());
+                parts
+            },
+            _ => panic!("Invalid UUID format"),
+        };
         let mut high = 0u64;
         let mut low = 0u64;
         for i in 0 .. 8 {
@@ -421,6 +472,30 @@ impl UUID {
         }
         UUID(high, low)
     }
+
+    /// Returns the UUID offline mode servers give to a player,
+    /// a name based (version 3) UUID of "OfflinePlayer:<name>".
+    pub fn offline(name: &str) -> UUID {
+        use md5::{Md5, Digest};
+        let mut hash = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes());
+        hash[6] = (hash[6] & 0x0f) | 0x30;
+        hash[8] = (hash[8] & 0x3f) | 0x80;
+        let mut high = 0u64;
+        let mut low = 0u64;
+        for i in 0 .. 8 {
+            high |= (hash[i] as u64) << (56 - i*8);
+            low |= (hash[i + 8] as u64) << (56 - i*8);
+        }
+        UUID(high, low)
+    }
+}
+
+impl fmt::Display for UUID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            self.0 >> 32, (self.0 >> 16) & 0xffff, self.0 & 0xffff,
+            self.1 >> 48, self.1 & 0xffff_ffff_ffff)
+    }
 }
 
 impl Default for UUID {
@@ -722,17 +797,32 @@ impl fmt::Debug for VarLong {
 
 impl Serializable for Position {
     fn read_from<R: io::Read>(buf: &mut R) -> Result<Position, Error> {
-        let pos = buf.read_u64::<BigEndian>()?;
-        Ok(Position::new(
-            ((pos as i64) >> 38) as i32,
-            (((pos as i64) >> 26) & 0xFFF) as i32,
-            ((pos as i64) << 38 >> 38) as i32
-        ))
+        let pos = buf.read_u64::<BigEndian>()? as i64;
+        // 18w43a (1.14) moved y to the lowest bits
+        if current_protocol_version() >= 441 {
+            Ok(Position::new(
+                (pos >> 38) as i32,
+                (pos << 52 >> 52) as i32,
+                (pos << 26 >> 38) as i32
+            ))
+        } else {
+            Ok(Position::new(
+                (pos >> 38) as i32,
+                ((pos >> 26) & 0xFFF) as i32,
+                (pos << 38 >> 38) as i32
+            ))
+        }
     }
     fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), Error> {
-        let pos = (((self.x as u64) & 0x3FFFFFF) << 38)
-            | (((self.y as u64) & 0xFFF) << 26)
-            | ((self.z as u64) & 0x3FFFFFF);
+        let pos = if current_protocol_version() >= 441 {
+            (((self.x as u64) & 0x3FFFFFF) << 38)
+                | (((self.z as u64) & 0x3FFFFFF) << 12)
+                | ((self.y as u64) & 0xFFF)
+        } else {
+            (((self.x as u64) & 0x3FFFFFF) << 38)
+                | (((self.y as u64) & 0xFFF) << 26)
+                | ((self.z as u64) & 0x3FFFFFF)
+        };
         buf.write_u64::<BigEndian>(pos)?;
         Result::Ok(())
     }
@@ -765,6 +855,13 @@ pub enum Error {
     IOError(io::Error),
     Json(serde_json::Error),
     Reqwest(reqwest::Error),
+    Auth(mojang::AuthError),
+    UnsupportedVersion(i32),
+    UnknownPacketId {
+        state: State,
+        direction: Direction,
+        id: i32,
+    },
 }
 
 impl convert::From<io::Error> for Error {
@@ -785,6 +882,12 @@ impl convert::From<reqwest::Error> for Error {
     }
 }
 
+impl convert::From<mojang::AuthError> for Error {
+    fn from(e: mojang::AuthError) -> Error {
+        Error::Auth(e)
+    }
+}
+
 impl ::std::error::Error for Error {
     fn description(&self) -> &str {
         match *self {
@@ -793,6 +896,9 @@ impl ::std::error::Error for Error {
             Error::IOError(ref e) => e.description(),
             Error::Json(ref e) => e.description(),
             Error::Reqwest(ref e) => e.description(),
+            Error::Auth(_) => "Authentication failed",
+            Error::UnsupportedVersion(_) => "Unsupported protocol version",
+            Error::UnknownPacketId { .. } => "Unknown packet id",
         }
     }
 }
@@ -805,6 +911,9 @@ impl ::std::fmt::Display for Error {
             Error::IOError(ref e) => e.fmt(f),
             Error::Json(ref e) => e.fmt(f),
             Error::Reqwest(ref e) => e.fmt(f),
+            Error::Auth(ref e) => e.fmt(f),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported protocol version {}", version),
+            Error::UnknownPacketId { state, direction, id } => write!(f, "unknown packet id 0x{:x} in {:?} {:?}", id, direction, state),
         }
     }
 }
@@ -813,6 +922,10 @@ type Aes128Cfb = Cfb8<Aes128>;
 
 pub struct Conn {
     stream: TcpStream,
+    /// Reads from the stream, after any data left over from the
+    /// `Conn` this was cloned from.
+    reader: BufReader<io::Chain<io::Cursor<Vec<u8>>, TcpStream>>,
+    writer: BufWriter<TcpStream>,
     pub host: String,
     pub port: u16,
     direction: Direction,
@@ -821,95 +934,157 @@ pub struct Conn {
 
     cipher: Option<Aes128Cfb>,
 
-    compression_threshold: i32,
-    compression_read: Option<ZlibDecoder<io::Cursor<Vec<u8>>>>,
-    compression_write: Option<ZlibEncoder<io::Cursor<Vec<u8>>>>,
+    framing: framing::Framing,
+    /// Reused for the packet being written
+    write_buf: Vec<u8>,
+    /// Reused for the packet being read
+    read_buf: Vec<u8>,
+    pub capture: Option<capture::Capture>,
+}
+
+/// Options controlling how a `Conn` is established.
+#[derive(Clone, Debug)]
+pub struct ConnectOptions {
+    pub resolver: resolve::Resolver,
+    /// How long to wait for each resolved address to accept
+    /// the connection before trying the next one.
+    pub connect_timeout: Duration,
+    /// How long a read may block before the connection is
+    /// considered dead. `None` waits forever.
+    pub read_timeout: Option<Duration>,
+    /// File to record every packet sent and received to, for
+    /// debugging. See the `capture` module.
+    pub capture: Option<PathBuf>,
+    /// Proxy to tunnel the connection through. The server's
+    /// host is resolved by the proxy, so SRV records aren't
+    /// followed.
+    pub proxy: Option<proxy::Proxy>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions {
+            resolver: resolve::Resolver::new(),
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Some(Duration::from_secs(30)),
+            capture: None,
+            proxy: proxy::default_proxy(),
+        }
+    }
 }
 
 impl Conn {
     pub fn new(target: &str, protocol_version: i32) -> Result<Conn, Error> {
-        unsafe {
-            CURRENT_PROTOCOL_VERSION = protocol_version;
-        }
+        Conn::connect(target, protocol_version, &ConnectOptions::default())
+    }
 
-        // TODO SRV record support
-        let mut parts = target.split(':').collect::<Vec<&str>>();
-        let address = if parts.len() == 1 {
-            parts.push("25565");
-            format!("{}:25565", parts[0])
-        } else {
-            format!("{}:{}", parts[0], parts[1])
+    pub fn connect(target: &str, protocol_version: i32, options: &ConnectOptions) -> Result<Conn, Error> {
+        let (stream, host, port) = match options.proxy {
+            Some(ref proxy) => {
+                let address = resolve::ServerAddress::parse(target)?;
+                let port = address.port.unwrap_or(resolve::DEFAULT_PORT);
+                let stream = proxy.connect(&address.host, port, options.connect_timeout)?;
+                (stream, address.host, port)
+            },
+            None => {
+                let resolved = options.resolver.resolve(target)?;
+                (Conn::connect_direct(target, &resolved, options)?, resolved.host, resolved.port)
+            },
+        };
+        stream.set_read_timeout(options.read_timeout)?;
+        let capture = match options.capture {
+            Some(ref path) => Some(capture::Capture::create(path, protocol_version)?),
+            None => None,
         };
-        let stream = TcpStream::connect(&*address)?;
-        Result::Ok(Conn {
+        Conn::from_stream(stream, host, port, Direction::Serverbound, protocol_version, capture)
+    }
+
+    fn from_stream(stream: TcpStream, host: String, port: u16, direction: Direction, protocol_version: i32, capture: Option<capture::Capture>) -> Result<Conn, Error> {
+        Conn::with_unread(stream, host, port, direction, protocol_version, capture, Vec::new())
+    }
+
+    fn with_unread(stream: TcpStream, host: String, port: u16, direction: Direction, protocol_version: i32, capture: Option<capture::Capture>, unread: Vec<u8>) -> Result<Conn, Error> {
+        Ok(Conn {
+            reader: BufReader::new(io::Cursor::new(unread).chain(stream.try_clone()?)),
+            writer: BufWriter::new(stream.try_clone()?),
             stream,
-            host: parts[0].to_owned(),
-            port: parts[1].parse().unwrap(),
-            direction: Direction::Serverbound,
+            host,
+            port,
+            direction,
             state: State::Handshaking,
             protocol_version,
             cipher: Option::None,
-            compression_threshold: -1,
-            compression_read: Option::None,
-            compression_write: Option::None,
+            framing: framing::Framing::default(),
+            write_buf: Vec::new(),
+            read_buf: Vec::new(),
+            capture,
         })
     }
 
-    pub fn write_packet<T: PacketType>(&mut self, packet: T) -> Result<(), Error> {
-        let mut buf = Vec::new();
-        VarInt(packet.packet_id(self.protocol_version)).write_to(&mut buf)?;
-        packet.write(&mut buf)?;
-
-        let mut extra = if self.compression_threshold >= 0 {
-            1
-        } else {
-            0
-        };
-        if self.compression_threshold >= 0 && buf.len() as i32 > self.compression_threshold {
-            if self.compression_write.is_none() {
-                self.compression_write = Some(ZlibEncoder::new(io::Cursor::new(Vec::new()), Compression::default()));
+    fn connect_direct(target: &str, resolved: &resolve::Resolved, options: &ConnectOptions) -> Result<TcpStream, Error> {
+        let mut last_err = None;
+        for address in &resolved.addresses {
+            match TcpStream::connect_timeout(address, options.connect_timeout) {
+                Ok(val) => return Ok(val),
+                Err(err) => {
+                    debug!("Failed to connect to {}: {}", address, err);
+                    last_err = Some(err);
+                },
             }
-            extra = 0;
-            let uncompressed_size = buf.len();
-            let mut new = Vec::new();
-            VarInt(uncompressed_size as i32).write_to(&mut new)?;
-            let write = self.compression_write.as_mut().unwrap();
-            write.reset(io::Cursor::new(buf));
-            write.read_to_end(&mut new)?;
-            buf = new;
         }
+        Err(match last_err {
+            Some(err) => Error::IOError(err),
+            None => Error::Err(format!("No addresses to connect to for {}", target)),
+        })
+    }
 
-        VarInt(buf.len() as i32 + extra).write_to(self)?;
-        if self.compression_threshold >= 0 && extra == 1 {
-            VarInt(0).write_to(self)?;
+    pub fn write_packet<T: PacketType + fmt::Debug>(&mut self, packet: T) -> Result<(), Error> {
+        // Anything written directly goes first
+        if !self.write_buf.is_empty() {
+            self.flush()?;
         }
-        self.write_all(&buf)?;
+        let res = self.write_frame(packet);
+        // Not left for the next direct write to send, even on errors
+        self.write_buf.clear();
+        res
+    }
+
+    fn write_frame<T: PacketType + fmt::Debug>(&mut self, packet: T) -> Result<(), Error> {
+        let buf = &mut self.write_buf;
+        let id = packet.packet_id(self.protocol_version)?;
+        VarInt(id).write_to(buf)?;
+        let debug = self.capture.as_ref().map(|_| format!("{:?}", packet));
+        let body_start = buf.len();
+        with_protocol_version(self.protocol_version, || packet.write(buf))?;
+        if let (Some(capture), Some(debug)) = (self.capture.as_ref(), debug) {
+            capture.record(self.state, self.direction, id, &buf[body_start..], &debug);
+        }
+
+        let (header, body) = self.framing.encode(buf)?;
+        send(&mut self.writer, self.cipher.as_mut(), &mut [header, body])?;
 
         Result::Ok(())
     }
 
     pub fn read_packet(&mut self) -> Result<packet::Packet, Error> {
-        let len = VarInt::read_from(self)?.0 as usize;
-        let mut ibuf = vec![0; len];
-        self.read_exact(&mut ibuf)?;
-
-        let mut buf = io::Cursor::new(ibuf);
-
-        if self.compression_threshold >= 0 {
-            if self.compression_read.is_none() {
-                self.compression_read = Some(ZlibDecoder::new(io::Cursor::new(Vec::new())));
-            }
-            let uncompressed_size = VarInt::read_from(&mut buf)?.0;
-            if uncompressed_size != 0 {
-                let mut new = Vec::with_capacity(uncompressed_size as usize);
-                {
-                    let reader = self.compression_read.as_mut().unwrap();
-                    reader.reset(buf);
-                    reader.read_to_end(&mut new)?;
-                }
-                buf = io::Cursor::new(new);
-            }
+        let len = VarInt::read_from(self)?.0;
+        if len < 0 {
+            return Err(Error::Err(format!("Invalid packet length {}", len)));
         }
+        // Taken so the frame can be read into whilst borrowing self
+        let mut frame = mem::replace(&mut self.read_buf, Vec::new());
+        frame.clear();
+        frame.resize(len as usize, 0);
+        let res = self.read_exact(&mut frame)
+            .map_err(Error::from)
+            .and_then(|_| self.decode_packet(&frame));
+        self.read_buf = frame;
+        res
+    }
+
+    fn decode_packet(&mut self, frame: &[u8]) -> Result<packet::Packet, Error> {
+        let data = self.framing.decode(frame)?;
+        let mut buf = io::Cursor::new(data);
         let id = VarInt::read_from(&mut buf)?.0;
 
         let dir = match self.direction {
@@ -917,17 +1092,26 @@ impl Conn {
             Direction::Serverbound => Direction::Clientbound,
         };
 
-        let packet = packet::packet_by_id(self.protocol_version, self.state, dir, id, &mut buf)?;
+        let body_start = buf.position() as usize;
+        let packet = packet::packet_by_id(self.protocol_version, self.state, dir, id, &mut buf);
+        if let Some(ref capture) = self.capture {
+            let debug = match packet {
+                Ok(Some(ref val)) => format!("{:?}", val),
+                Ok(None) => "missing packet".to_owned(),
+                Err(ref err) => format!("error: {}", err),
+            };
+            capture.record(self.state, dir, id, &data[body_start..], &debug);
+        }
+        let packet = packet?;
 
         match packet {
             Some(val) => {
                 let pos = buf.position() as usize;
-                let ibuf = buf.into_inner();
-                if ibuf.len() != pos {
+                if data.len() != pos {
                     return Result::Err(Error::Err(format!("Failed to read all of packet 0x{:X}, \
                                                            had {} bytes left",
                                                           id,
-                                                          ibuf.len() - pos)))
+                                                          data.len() - pos)))
                 }
                 Result::Ok(val)
             }
@@ -940,12 +1124,17 @@ impl Conn {
         self.cipher = Option::Some(cipher);
     }
 
+    /// Sets how long reads may block for before failing, `None`
+    /// blocks forever.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        Ok(self.stream.set_read_timeout(timeout)?)
+    }
+
     pub fn set_compresssion(&mut self, threshold: i32) {
-        self.compression_threshold = threshold;
+        self.framing.set_threshold(threshold);
     }
 
     pub fn do_status(mut self) -> Result<(Status, Duration), Error> {
-        use serde_json::Value;
         use self::packet::status::serverbound::*;
         use self::packet::handshake::serverbound::Handshake;
         use self::packet::Packet;
@@ -977,47 +1166,243 @@ impl Conn {
 
         let ping = start.elapsed();
 
-        let val: Value = match serde_json::from_str(&status) {
+        let val: serde_json::Value = match serde_json::from_str(&status) {
             Ok(val) => val,
             Err(_) => return Err(Error::Err("Json parse error".to_owned())),
         };
+        Ok((Status::from_value(&val)?, ping))
+    }
+
+    /// Pings the server using the server list ping from before 1.7,
+    /// which later servers still answer too.
+    pub fn do_legacy_status(mut self) -> Result<(Status, Duration), Error> {
+        let mut extra = vec![];
+        // The protocol version of 1.6.4, the last to use this ping
+        extra.write_u8(78)?;
+        write_legacy_string(&mut extra, &self.host)?;
+        extra.write_i32::<BigEndian>(self.port as i32)?;
+
+        let mut req = vec![0xFE, 0x01, 0xFA];
+        write_legacy_string(&mut req, "MC|PingHost")?;
+        req.write_u16::<BigEndian>(extra.len() as u16)?;
+        req.extend_from_slice(&extra);
+
+        let start = Instant::now();
+        self.write_all(&req)?;
+        self.flush()?;
+        let id = self.read_u8()?;
+        if id != 0xFF {
+            return Err(Error::Err(format!("Unexpected legacy ping response 0x{:X}", id)));
+        }
+        let len = self.read_u16::<BigEndian>()?;
+        let mut chars = Vec::with_capacity(len as usize);
+        for _ in 0 .. len {
+            chars.push(self.read_u16::<BigEndian>()?);
+        }
+        let ping = start.elapsed();
+        let response = String::from_utf16(&chars)
+            .map_err(|_| Error::Err("Invalid legacy ping response".to_owned()))?;
+        Ok((Status::from_legacy(&response)?, ping))
+    }
+
+    /// Pings the server for its status, falling back to the legacy
+    /// ping for servers that don't understand the modern one.
+    pub fn ping(target: &str, protocol_version: i32, options: &ConnectOptions) -> Result<(Status, Duration), Error> {
+        let conn = Conn::connect(target, protocol_version, options)?;
+        match conn.do_status() {
+            Ok(val) => Ok(val),
+            Err(err) => {
+                debug!("Status ping to {} failed ({}), trying the legacy ping", target, err);
+                Conn::connect(target, protocol_version, options)?
+                    .do_legacy_status()
+                    .map_err(|_| err)
+            },
+        }
+    }
+}
+
+fn write_legacy_string<W: io::Write>(buf: &mut W, val: &str) -> Result<(), Error> {
+    let chars: Vec<u16> = val.encode_utf16().collect();
+    buf.write_u16::<BigEndian>(chars.len() as u16)?;
+    for c in chars {
+        buf.write_u16::<BigEndian>(c)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Status {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    pub description: format::Component,
+    pub favicon: Option<String>,
+    /// The mods required to join, only set for Forge servers.
+    pub forge: Option<ForgeInfo>,
+}
 
+impl Status {
+    pub fn from_value(val: &serde_json::Value) -> Result<Status, Error> {
+        use serde_json::Value;
         let invalid_status = || Error::Err("Invalid status".to_owned());
 
-        let version = val.get("version").ok_or(invalid_status())?;
-        let players = val.get("players").ok_or(invalid_status())?;
+        let version = val.get("version").ok_or_else(invalid_status)?;
+        let players = val.get("players").ok_or_else(invalid_status)?;
+
+        let sample = players.get("sample")
+            .and_then(Value::as_array)
+            .map_or_else(Vec::new, |sample| sample.iter()
+                .filter_map(|p| Some(StatusPlayer {
+                    name: p.get("name").and_then(Value::as_str)?.to_owned(),
+                    id: p.get("id").and_then(Value::as_str)?.to_owned(),
+                }))
+                .collect());
 
-        Ok((Status {
+        Ok(Status {
             version: StatusVersion {
-                name: version.get("name").and_then(Value::as_str).ok_or(invalid_status())?
+                name: version.get("name").and_then(Value::as_str).ok_or_else(invalid_status)?
                           .to_owned(),
                 protocol: version.get("protocol")
                                       .and_then(Value::as_i64)
-                                      .ok_or(invalid_status())? as i32,
+                                      .ok_or_else(invalid_status)? as i32,
             },
             players: StatusPlayers {
                 max: players.get("max")
                                  .and_then(Value::as_i64)
-                                 .ok_or(invalid_status())? as i32,
+                                 .ok_or_else(invalid_status)? as i32,
                 online: players.get("online")
                                     .and_then(Value::as_i64)
-                                    .ok_or(invalid_status())? as i32,
-                sample: Vec::new(), /* TODO */
+                                    .ok_or_else(invalid_status)? as i32,
+                sample,
             },
             description: format::Component::from_value(val.get("description")
-                                                               .ok_or(invalid_status())?),
+                                                               .ok_or_else(invalid_status)?),
             favicon: val.get("favicon").and_then(Value::as_str).map(|v| v.to_owned()),
-        },
-            ping))
+            forge: ForgeInfo::from_status(val),
+        })
+    }
+
+    /// Parses the response to the legacy ping. 1.4 to 1.6 servers
+    /// reply with `§1\0protocol\0version\0motd\0online\0max`,
+    /// older ones with just `motd§online§max`.
+    pub fn from_legacy(response: &str) -> Result<Status, Error> {
+        let invalid_status = || Error::Err("Invalid legacy status".to_owned());
+        let parse = |v: &str| v.parse::<i32>().map_err(|_| invalid_status());
+        let (protocol, name, motd, online, max) = if response.starts_with("\u{a7}1\0") {
+            let parts: Vec<&str> = response.split('\0').collect();
+            if parts.len() < 6 {
+                return Err(invalid_status());
+            }
+            (parse(parts[1])?, parts[2], parts[3], parts[4], parts[5])
+        } else {
+            let mut parts = response.rsplitn(3, '\u{a7}');
+            let max = parts.next().ok_or_else(invalid_status)?;
+            let online = parts.next().ok_or_else(invalid_status)?;
+            let motd = parts.next().ok_or_else(invalid_status)?;
+            (0, "", motd, online, max)
+        };
+        Ok(Status {
+            version: StatusVersion {
+                name: name.to_owned(),
+                protocol,
+            },
+            players: StatusPlayers {
+                max: parse(max)?,
+                online: parse(online)?,
+                sample: vec![],
+            },
+            description: format::Component::Text(format::TextComponent::new(motd)),
+            favicon: None,
+            forge: None,
+        })
+    }
+
+    /// Returns the status in the json form servers send it in.
+    pub fn to_value(&self) -> serde_json::Value {
+        let mut val = serde_json::json!({
+            "version": {
+                "name": self.version.name,
+                "protocol": self.version.protocol,
+            },
+            "players": {
+                "max": self.players.max,
+                "online": self.players.online,
+                "sample": self.players.sample.iter().map(|p| serde_json::json!({
+                    "name": p.name,
+                    "id": p.id,
+                })).collect::<Vec<_>>(),
+            },
+            "description": self.description.to_value(),
+        });
+        if let Some(ref favicon) = self.favicon {
+            val["favicon"] = serde_json::Value::String(favicon.clone());
+        }
+        if let Some(ref forge) = self.forge {
+            if forge.fml_network_version == 1 {
+                val["modinfo"] = serde_json::json!({
+                    "type": "FML",
+                    "modList": forge.mods.iter().map(|m| serde_json::json!({
+                        "modid": m.id,
+                        "version": m.version,
+                    })).collect::<Vec<_>>(),
+                });
+            } else {
+                val["forgeData"] = serde_json::json!({
+                    "channels": [],
+                    "fmlNetworkVersion": forge.fml_network_version,
+                    "mods": forge.mods.iter().map(|m| serde_json::json!({
+                        "modId": m.id,
+                        "modmarker": m.version,
+                    })).collect::<Vec<_>>(),
+                });
+            }
+        }
+        val
     }
 }
 
-#[derive(Debug)]
-pub struct Status {
-    pub version: StatusVersion,
-    pub players: StatusPlayers,
-    pub description: format::Component,
-    pub favicon: Option<String>,
+/// The mod list Forge servers add to their status.
+#[derive(Debug, Clone)]
+pub struct ForgeInfo {
+    /// 1 for servers sending `modinfo` (up to 1.12), later servers
+    /// send their version with `forgeData`.
+    pub fml_network_version: i32,
+    pub mods: Vec<ForgeMod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeMod {
+    pub id: String,
+    pub version: String,
+}
+
+impl ForgeInfo {
+    fn from_status(val: &serde_json::Value) -> Option<ForgeInfo> {
+        use serde_json::Value;
+        let parse_mods = |mods: Option<&Value>, id: &str, version: &str| -> Vec<ForgeMod> {
+            mods.and_then(Value::as_array).map_or_else(Vec::new, |mods| mods.iter()
+                .filter_map(|m| Some(ForgeMod {
+                    id: m.get(id).and_then(Value::as_str)?.to_owned(),
+                    version: m.get(version).and_then(Value::as_str).unwrap_or("").to_owned(),
+                }))
+                .collect())
+        };
+        if let Some(data) = val.get("forgeData") {
+            Some(ForgeInfo {
+                fml_network_version: data.get("fmlNetworkVersion").and_then(Value::as_i64).unwrap_or(2) as i32,
+                mods: parse_mods(data.get("mods"), "modId", "modmarker"),
+            })
+        } else if let Some(info) = val.get("modinfo") {
+            if info.get("type").and_then(Value::as_str) != Some("FML") {
+                return None;
+            }
+            Some(ForgeInfo {
+                fml_network_version: 1,
+                mods: parse_mods(info.get("modList"), "modid", "version"),
+            })
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -1035,67 +1420,184 @@ pub struct StatusPlayers {
 
 #[derive(Debug)]
 pub struct StatusPlayer {
-    name: String,
-    id: String,
+    pub name: String,
+    pub id: String,
 }
 
 impl Read for Conn {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        match self.cipher.as_mut() {
-            Option::None => self.stream.read(buf),
-            Option::Some(cipher) => {
-                let ret = self.stream.read(buf)?;
-                cipher.decrypt(&mut buf[..ret]);
+        let ret = self.reader.read(buf)?;
+        if let Some(cipher) = self.cipher.as_mut() {
+            cipher.decrypt(&mut buf[..ret]);
+        }
+        Ok(ret)
+    }
+}
 
-                Ok(ret)
-            }
+/// Encrypts `parts` in place, if encryption is enabled, then writes
+/// and flushes them.
+fn send(writer: &mut BufWriter<TcpStream>, cipher: Option<&mut Aes128Cfb>, parts: &mut [&mut [u8]]) -> io::Result<()> {
+    if let Some(cipher) = cipher {
+        for part in parts.iter_mut() {
+            cipher.encrypt(part);
         }
     }
+    for part in parts.iter() {
+        writer.write_all(part)?;
+    }
+    writer.flush()
 }
 
+/// Writes are buffered in the packet buffer until flushed, where
+/// they're encrypted in place like packets are. Packets should be
+/// sent with `write_packet` instead which frames and flushes them.
 impl Write for Conn {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        match self.cipher.as_mut() {
-            Option::None => self.stream.write(buf),
-            Option::Some(cipher) => {
-                // TODO: avoid copying, but trait requires non-mutable buf
-                let mut data = vec![0; buf.len()];
-                for i in 0..buf.len() {
-                    data[i] = buf[i];
-                }
-
-                cipher.encrypt(&mut data);
-
-                self.stream.write_all(&data)?;
-                Ok(buf.len())
-            }
-        }
+        self.write_buf.extend_from_slice(buf);
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.stream.flush()
+        let res = send(&mut self.writer, self.cipher.as_mut(), &mut [&mut self.write_buf[..]]);
+        self.write_buf.clear();
+        res
     }
 }
 
 impl Clone for Conn {
     fn clone(&self) -> Self {
-        Conn {
-            stream: self.stream.try_clone().unwrap(),
-            host: self.host.clone(),
-            port: self.port,
-            direction: self.direction,
-            state: self.state,
-            protocol_version: self.protocol_version,
-            cipher: Option::None,
-            compression_threshold: self.compression_threshold,
-            compression_read: Option::None,
-            compression_write: Option::None,
-        }
+        // Anything already read from the stream but not used yet
+        // must be readable from the clone too.
+        let mut unread = self.reader.buffer().to_vec();
+        let (leftover, _) = self.reader.get_ref().get_ref();
+        unread.extend_from_slice(&leftover.get_ref()[leftover.position() as usize..]);
+        let mut conn = Conn::with_unread(
+            self.stream.try_clone().unwrap(),
+            self.host.clone(),
+            self.port,
+            self.direction,
+            self.protocol_version,
+            self.capture.clone(),
+            unread,
+        ).unwrap();
+        conn.state = self.state;
+        conn.framing.set_threshold(self.framing.threshold());
+        conn
     }
 }
 
 pub trait PacketType {
-    fn packet_id(&self, protocol_version: i32) -> i32;
+    fn packet_id(&self, protocol_version: i32) -> Result<i32, Error>;
 
     fn write<W: io::Write>(self, buf: &mut W) -> Result<(), Error>;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clone_keeps_buffered_packets() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Both pongs arrive together so the second is buffered
+            // by the time the first has been read.
+            let mut data = vec![];
+            let mut framing = framing::Framing::default();
+            for ping in 1..3 {
+                let mut packet = vec![0x01];
+                packet.write_i64::<BigEndian>(ping).unwrap();
+                let (header, body) = framing.encode(&mut packet).unwrap();
+                data.extend_from_slice(header);
+                data.extend_from_slice(body);
+            }
+            stream.write_all(&data).unwrap();
+        });
+        let mut conn = Conn::new(&addr.to_string(), SUPPORTED_PROTOCOLS[0]).unwrap();
+        conn.state = State::Status;
+        server.join().unwrap();
+        match conn.read_packet().unwrap() {
+            packet::Packet::StatusPong(pong) => assert_eq!(pong.ping, 1),
+            val => panic!("Wrong packet: {:?}", val),
+        }
+        match conn.clone().read_packet().unwrap() {
+            packet::Packet::StatusPong(pong) => assert_eq!(pong.ping, 2),
+            val => panic!("Wrong packet: {:?}", val),
+        }
+    }
+
+    #[test]
+    fn position_encoding() {
+        let pos = Position::new(-1234, 67, 5678);
+        for &version in &[404, 498] {
+            with_protocol_version(version, || {
+                let mut data = vec![];
+                pos.write_to(&mut data).unwrap();
+                assert_eq!(Position::read_from(&mut io::Cursor::new(data)).unwrap(), pos);
+            });
+        }
+        let mut data = vec![];
+        with_protocol_version(498, || Position::new(0, 1, 0).write_to(&mut data)).unwrap();
+        assert_eq!(data, vec![0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn status_sample_and_mods() {
+        let val: serde_json::Value = serde_json::from_str(r#"{
+            "version": {"name": "1.12.2", "protocol": 340},
+            "players": {"max": 20, "online": 2, "sample": [
+                {"name": "Steve", "id": "5627dd98-e6be-3c21-b8a8-e92344183641"},
+                {"name": "Alex", "id": "6d8a9e2f-2fa5-3e2b-8c87-61e9f1b3f1a4"}
+            ]},
+            "description": {"text": "A modded server"},
+            "modinfo": {"type": "FML", "modList": [
+                {"modid": "minecraft", "version": "1.12.2"},
+                {"modid": "forge", "version": "14.23.5.2768"}
+            ]}
+        }"#).unwrap();
+        let status = Status::from_value(&val).unwrap();
+        let names: Vec<&str> = status.players.sample.iter().map(|p| &p.name[..]).collect();
+        assert_eq!(names, vec!["Steve", "Alex"]);
+        let forge = status.forge.as_ref().unwrap();
+        assert_eq!(forge.fml_network_version, 1);
+        assert_eq!(forge.mods[1].id, "forge");
+        assert_eq!(forge.mods[1].version, "14.23.5.2768");
+
+        let status = Status::from_value(&status.to_value()).unwrap();
+        assert_eq!(status.players.sample.len(), 2);
+        assert_eq!(status.forge.unwrap().mods.len(), 2);
+
+        let val: serde_json::Value = serde_json::from_str(r#"{
+            "version": {"name": "1.13.2", "protocol": 404},
+            "players": {"max": 20, "online": 0},
+            "description": "Forge 1.13",
+            "forgeData": {"channels": [], "fmlNetworkVersion": 2, "mods": [
+                {"modId": "forge", "modmarker": "25.0.219"}
+            ]}
+        }"#).unwrap();
+        let status = Status::from_value(&val).unwrap();
+        assert!(status.players.sample.is_empty());
+        let forge = status.forge.unwrap();
+        assert_eq!(forge.fml_network_version, 2);
+        assert_eq!(forge.mods[0].version, "25.0.219");
+    }
+
+    #[test]
+    fn legacy_status() {
+        let status = Status::from_legacy("\u{a7}1\u{0}78\u{0}1.6.4\u{0}A \u{a7}aMinecraft\u{a7}r Server\u{0}3\u{0}20").unwrap();
+        assert_eq!(status.version.protocol, 78);
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.description.to_string(), "A \u{a7}aMinecraft\u{a7}r Server");
+        assert_eq!(status.players.online, 3);
+        assert_eq!(status.players.max, 20);
+
+        let status = Status::from_legacy("Old \u{a7}cserver\u{a7}1\u{a7}10").unwrap();
+        assert_eq!(status.description.to_string(), "Old \u{a7}cserver");
+        assert_eq!(status.players.online, 1);
+        assert_eq!(status.players.max, 10);
+
+        assert!(Status::from_legacy("garbage").is_err());
+    }
+}
@@ -12,24 +12,229 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::sync::Mutex;
 use sha1::{self, Digest};
 use serde_json::json;
 use reqwest;
+use lazy_static::lazy_static;
+use super::proxy;
+use super::signature::PublicKey;
 
 #[derive(Clone, Debug)]
 pub struct Profile {
     pub username: String,
     pub id: String,
     pub access_token: String,
+    /// Offline profiles never authenticate so they can only join
+    /// servers running in offline mode.
+    pub offline: bool,
+    /// The servers the profile authenticates with
+    pub endpoints: Endpoints,
 }
 
-const JOIN_URL: &str = "https://sessionserver.mojang.com/session/minecraft/join";
-const LOGIN_URL: &str = "https://authserver.mojang.com/authenticate";
-const REFRESH_URL: &str = "https://authserver.mojang.com/refresh";
-const VALIDATE_URL: &str = "https://authserver.mojang.com/validate";
+/// The servers used for authentication, Mojang's unless a
+/// Yggdrasil compatible replacement is configured.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoints {
+    /// Base url of the authserver, `authenticate`, `refresh` and
+    /// `validate` are requested relative to it.
+    pub auth: String,
+    /// Base url of the session server
+    pub session: String,
+    /// Url skin textures are downloaded from, the texture's hash is
+    /// appended to it.
+    pub textures: String,
+    /// The key player textures are signed with, or a url serving the
+    /// keys. See `fetch_texture_keys`.
+    pub texture_keys: String,
+}
+
+pub const DEFAULT_AUTH_SERVER: &str = "https://authserver.mojang.com";
+pub const DEFAULT_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+pub const DEFAULT_TEXTURE_SERVER: &str = "http://textures.minecraft.net/texture/";
+/// The session server's key, as distributed with the official
+/// launcher, so the default setup never has to fetch it.
+pub const DEFAULT_TEXTURE_KEYS: &str = "MIICIjANBgkqhkiG9w0BAQEFAAOCAg8AMIICCgKCAgEAylB4B6m5lz7jwrcFz6Fd/fnfUhcvlxsTSn5kIK/2aGG1C3kMy4VjhwlxF6BFUSnfxhNswPjh3ZitkBxEAFY25uzkJFRwHwVA9mdwjashXILtR6OqdLXXFVyUPIURLOSWqGNBtb08EN5fMnG8iFLgEJIBMxs9BvF3s3/FhuHyPKiVTZmXY0WY4ZyYqvoKR+XjaTRPPvBsDa4WI2u1zxXMeHlodT3lnCzVvyOYBLXL6CJgByuOxccJ8hnXfF9yY4F0aeL080Jz/3+EBNG8RO4ByhtBf4Ny8NQ6stWsjfeUIvH7bU/4zCYcYOq4WrInXHqS8qruDmIl7P5XXGcabuzQstPf/h2CRAUpP/PlHXcMlvewjmGU6MfDK+lifScNYwjPxRo4nKTGFZf/0aqHCh/EAsQyLKrOIYRE0lDG3bzBh8ogIMLAugsAfBb6M3mqCqKaTMAf/VAjh5FFJnjS+7bE+bZEV0qwax1CEoPPJL1fIQjOS8zj086gjpGRCtSy9+bTPTfTR/SJ+VUB5G2IeCItkNHpJX2ygojFZ9n5Fnj7R9ZnOM+L8nyIjPu3aePvtcrXlyLhH/hvOfIOjPxOlqW+O5QwSFP4OEcyLAUgDdUgyW36Z5mB285uKW/ighzZsOTevVUG2QwDItObIV6i8RCxFbN2oDHyPaO5j1tTaBNyVt8CAwEAAQ==";
+
+lazy_static! {
+    /// The keys found for each `texture_keys` setting so far
+    static ref TEXTURE_KEYS: Mutex<HashMap<String, Vec<PublicKey>>> = Mutex::new(HashMap::new());
+}
+
+impl Default for Endpoints {
+    fn default() -> Endpoints {
+        Endpoints {
+            auth: DEFAULT_AUTH_SERVER.to_owned(),
+            session: DEFAULT_SESSION_SERVER.to_owned(),
+            textures: DEFAULT_TEXTURE_SERVER.to_owned(),
+            texture_keys: DEFAULT_TEXTURE_KEYS.to_owned(),
+        }
+    }
+}
+
+impl Endpoints {
+    fn auth_url(&self, path: &str) -> String {
+        format!("{}/{}", self.auth.trim_end_matches('/'), path)
+    }
+
+    fn join_url(&self) -> String {
+        format!("{}/session/minecraft/join", self.session.trim_end_matches('/'))
+    }
+
+    /// Returns the keys player textures may be signed with.
+    ///
+    /// `texture_keys` is either the base64 of a DER encoded key, or a url
+    /// serving the keys as Mojang's services api (`profilePropertyKeys`)
+    /// or an authlib-injector api root (`signaturePublickey`) does.
+    pub fn fetch_texture_keys(&self, proxy: Option<&proxy::Proxy>) -> Result<Vec<PublicKey>, super::Error> {
+        let setting = self.texture_keys.trim();
+        if !setting.starts_with("http://") && !setting.starts_with("https://") {
+            return Ok(vec![PublicKey::from_base64(setting)?]);
+        }
+        let client = proxy::http_client(proxy)?;
+        let res = client.get(setting).send()?;
+        if !res.status().is_success() {
+            return Err(AuthError::Status(res.status()).into());
+        }
+        let ret: serde_json::Value = serde_json::from_reader(res)?;
+        parse_texture_keys(&ret)
+    }
+
+    /// Returns the keys player textures may be signed with if they
+    /// don't need fetching, either as they're set directly or as
+    /// they were fetched before.
+    pub fn cached_texture_keys(&self) -> Option<Vec<PublicKey>> {
+        let setting = self.texture_keys.trim();
+        let mut cache = TEXTURE_KEYS.lock().unwrap();
+        if let Some(keys) = cache.get(setting) {
+            return Some(keys.clone());
+        }
+        if setting.starts_with("http://") || setting.starts_with("https://") {
+            return None;
+        }
+        let keys = self.fetch_texture_keys(None).ok()?;
+        cache.insert(setting.to_owned(), keys.clone());
+        Some(keys)
+    }
+
+    /// Like `fetch_texture_keys` but only fetches the keys once, later
+    /// calls reuse them.
+    pub fn texture_keys(&self, proxy: Option<&proxy::Proxy>) -> Result<Vec<PublicKey>, super::Error> {
+        if let Some(keys) = self.cached_texture_keys() {
+            return Ok(keys);
+        }
+        let keys = self.fetch_texture_keys(proxy)?;
+        TEXTURE_KEYS.lock().unwrap().insert(self.texture_keys.trim().to_owned(), keys.clone());
+        Ok(keys)
+    }
+}
+
+fn parse_texture_keys(ret: &serde_json::Value) -> Result<Vec<PublicKey>, super::Error> {
+    if let Some(key) = ret.get("signaturePublickey").and_then(|v| v.as_str()) {
+        return Ok(vec![PublicKey::from_pem(key)?]);
+    }
+    let keys = ret.get("profilePropertyKeys")
+        .and_then(|v| v.as_array())
+        .ok_or(AuthError::MissingField("profilePropertyKeys"))?;
+    keys.iter()
+        .map(|key| key.get("publicKey")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| AuthError::MissingField("profilePropertyKeys.publicKey").into())
+            .and_then(PublicKey::from_pem))
+        .collect()
+}
+
+/// Whether a player's textures came signed by the session server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TexturesSignature {
+    /// The server sent no textures
+    Missing,
+    Unsigned,
+    /// The textures were signed, but not by a trusted key
+    Invalid,
+    Verified,
+}
+
+/// Checks the signature of a player's `textures` property, returning
+/// the skin url out of it only if it was signed by one of `keys`.
+pub fn verify_textures(value: &str, signature: Option<&str>, keys: &[PublicKey]) -> (TexturesSignature, Option<String>) {
+    let signature = match signature.map(base64::decode) {
+        Some(Ok(val)) => val,
+        Some(Err(_)) => return (TexturesSignature::Invalid, None),
+        None => return (TexturesSignature::Unsigned, None),
+    };
+    // The signature covers the base64 as sent, not the decoded json
+    if !keys.iter().any(|key| key.verify_sha1(value.as_bytes(), &signature)) {
+        return (TexturesSignature::Invalid, None);
+    }
+    let url = base64::decode(value).ok()
+        .and_then(|blob| serde_json::from_slice::<serde_json::Value>(&blob).ok())
+        .and_then(|blob| blob.pointer("/textures/SKIN/url").and_then(|v| v.as_str()).map(|v| v.to_owned()));
+    (TexturesSignature::Verified, url)
+}
+
+/// An error returned by the authentication or session server.
+#[derive(Debug)]
+pub enum AuthError {
+    /// The server refused the request, e.g. because of a wrong
+    /// password, giving the reason in its response.
+    Rejected {
+        error: String,
+        message: String,
+        cause: Option<String>,
+    },
+    /// The response was missing a field we need
+    MissingField(&'static str),
+    /// The server replied with an unexpected status and no reason
+    Status(reqwest::StatusCode),
+}
+
+impl ::std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            AuthError::Rejected { ref error, ref message, .. } => write!(f, "{}: {}", error, message),
+            AuthError::MissingField(field) => write!(f, "invalid response from the authentication server, missing {}", field),
+            AuthError::Status(status) => write!(f, "authentication server replied with {}", status),
+        }
+    }
+}
+
+impl AuthError {
+    /// Returns the error in a failed response, if there is one.
+    fn from_response(ret: &serde_json::Value) -> Option<AuthError> {
+        let error = ret.get("error").and_then(|v| v.as_str())?;
+        Some(AuthError::Rejected {
+            error: error.to_owned(),
+            message: ret.get("errorMessage").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
+            cause: ret.get("cause").and_then(|v| v.as_str()).map(|v| v.to_owned()),
+        })
+    }
+}
 
 impl Profile {
-    pub fn login(username: &str, password: &str, token: &str) -> Result<Profile, super::Error> {
+    /// Creates an offline profile for `username`, with the uuid
+    /// offline mode servers will give it.
+    pub fn offline(username: &str) -> Result<Profile, super::Error> {
+        if username.is_empty() || username.len() > 16
+            || !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(super::Error::Err(
+                "Offline usernames must be 1 to 16 letters, digits or underscores".to_owned()
+            ));
+        }
+        Ok(Profile {
+            username: username.to_owned(),
+            // Without hyphens, like the ids the authserver returns
+            id: super::UUID::offline(username).to_string().replace('-', ""),
+            access_token: String::new(),
+            offline: true,
+            endpoints: Endpoints::default(),
+        })
+    }
+
+    /// Logs in with the given credentials. The request is sent
+    /// through the default proxy, if one is set.
+    pub fn login(endpoints: &Endpoints, username: &str, password: &str, token: &str) -> Result<Profile, super::Error> {
         let req_msg = json!({
             "username": username,
             "password": password,
@@ -40,66 +245,77 @@ impl Profile {
             }});
         let req = serde_json::to_string(&req_msg)?;
 
-        let client = reqwest::Client::new();
-        let res = client.post(LOGIN_URL)
+        let client = proxy::http_client(proxy::default_proxy().as_ref())?;
+        let res = client.post(&endpoints.auth_url("authenticate"))
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .body(req)
             .send()?;
 
-        let ret: serde_json::Value = serde_json::from_reader(res)?;
-        if let Some(error) = ret.get("error").and_then(|v| v.as_str()) {
-            return Err(super::Error::Err(format!(
-                "{}: {}",
-                error,
-                ret.get("errorMessage").and_then(|v| v.as_str()).unwrap())
-            ));
+        Profile::from_response(endpoints, res)
+    }
+
+    /// Reads the profile out of a successful `authenticate` or
+    /// `refresh` response.
+    fn from_response(endpoints: &Endpoints, res: reqwest::Response) -> Result<Profile, super::Error> {
+        let status = res.status();
+        let ret: serde_json::Value = match serde_json::from_reader(res) {
+            Ok(val) => val,
+            Err(_) if !status.is_success() => return Err(AuthError::Status(status).into()),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(err) = AuthError::from_response(&ret) {
+            return Err(err.into());
         }
+        let field = |pointer: &str, name: &'static str| -> Result<String, super::Error> {
+            ret.pointer(pointer)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_owned())
+                .ok_or_else(|| AuthError::MissingField(name).into())
+        };
         Ok(Profile {
-            username: ret.pointer("/selectedProfile/name").and_then(|v| v.as_str()).unwrap().to_owned(),
-            id: ret.pointer("/selectedProfile/id").and_then(|v| v.as_str()).unwrap().to_owned(),
-            access_token: ret.get("accessToken").and_then(|v| v.as_str()).unwrap().to_owned(),
+            username: field("/selectedProfile/name", "selectedProfile.name")?,
+            id: field("/selectedProfile/id", "selectedProfile.id")?,
+            access_token: field("/accessToken", "accessToken")?,
+            offline: false,
+            endpoints: endpoints.clone(),
         })
     }
 
+    /// Refreshes the access token if it is no longer valid. The
+    /// requests are sent through the default proxy, if one is set.
     pub fn refresh(self, token: &str) -> Result<Profile, super::Error> {
+        if self.offline {
+            return Ok(self);
+        }
         let req_msg = json!({
             "accessToken": self.access_token.clone(),
             "clientToken": token
             });
         let req = serde_json::to_string(&req_msg)?;
 
-        let client = reqwest::Client::new();
-        let res = client.post(VALIDATE_URL)
+        let client = proxy::http_client(proxy::default_proxy().as_ref())?;
+        let res = client.post(&self.endpoints.auth_url("validate"))
             .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(req)
+            .body(req.clone())
             .send()?;
 
         if res.status() != reqwest::StatusCode::NO_CONTENT {
-            let req = serde_json::to_string(&req_msg)?; // TODO: fix parsing twice to avoid move
             // Refresh needed
-            let res = client.post(REFRESH_URL)
+            let res = client.post(&self.endpoints.auth_url("refresh"))
                 .header(reqwest::header::CONTENT_TYPE, "application/json")
                 .body(req)
                 .send()?;
-
-            let ret: serde_json::Value = serde_json::from_reader(res)?;
-            if let Some(error) = ret.get("error").and_then(|v| v.as_str()) {
-                return Err(super::Error::Err(format!(
-                    "{}: {}",
-                    error,
-                    ret.get("errorMessage").and_then(|v| v.as_str()).unwrap())
-                ));
-            }
-            return Ok(Profile {
-                username: ret.pointer("/selectedProfile/name").and_then(|v| v.as_str()).unwrap().to_owned(),
-                id: ret.pointer("/selectedProfile/id").and_then(|v| v.as_str()).unwrap().to_owned(),
-                access_token: ret.get("accessToken").and_then(|v| v.as_str()).unwrap().to_owned(),
-            });
+            return Profile::from_response(&self.endpoints, res);
         }
         Ok(self)
     }
 
-    pub fn join_server(&self, server_id: &str, shared_key: &[u8], public_key: &[u8]) -> Result<(), super::Error> {
+    /// Tells the session server that we are joining the server,
+    /// sending the request through `proxy` if given.
+    pub fn join_server(&self, server_id: &str, shared_key: &[u8], public_key: &[u8], proxy: Option<&proxy::Proxy>) -> Result<(), super::Error> {
+        if self.offline {
+            return Err(super::Error::Err("Offline profiles can't join online mode servers".to_owned()));
+        }
         let mut hasher = sha1::Sha1::new();
         hasher.input(server_id.as_bytes());
         hasher.input(shared_key);
@@ -127,21 +343,24 @@ impl Profile {
         });
         let join = serde_json::to_string(&join_msg).unwrap();
 
-        let client = reqwest::Client::new();
-        let res = client.post(JOIN_URL)
+        let client = proxy::http_client(proxy)?;
+        let res = client.post(&self.endpoints.join_url())
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .body(join)
             .send()?;
 
-        if res.status() == reqwest::StatusCode::NO_CONTENT {
-            Ok(())
-        } else {
-            Err(super::Error::Err("Failed to auth with server".to_owned()))
+        let status = res.status();
+        if status == reqwest::StatusCode::NO_CONTENT {
+            return Ok(());
         }
+        let err = serde_json::from_reader::<_, serde_json::Value>(res).ok()
+            .and_then(|ret| AuthError::from_response(&ret))
+            .unwrap_or(AuthError::Status(status));
+        Err(err.into())
     }
 
     pub fn is_complete(&self) -> bool {
-        !self.username.is_empty() && !self.id.is_empty() && !self.access_token.is_empty()
+        !self.username.is_empty() && !self.id.is_empty() && (self.offline || !self.access_token.is_empty())
     }
 }
 
@@ -155,3 +374,124 @@ fn twos_compliment(data: &mut [u8]) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_http;
+    use std::thread;
+
+    /// Serves one canned response per request, returning the
+    /// requests received.
+    fn mock_server(responses: Vec<(u16, String)>) -> (Endpoints, thread::JoinHandle<Vec<mock_http::Request>>) {
+        let mut responses = responses.into_iter();
+        let (url, handle) = mock_http::serve(responses.len(), move |_| {
+            let (status, body) = responses.next().unwrap();
+            (format!("{} Mock", status), body.into_bytes())
+        });
+        let endpoints = Endpoints {
+            auth: url.clone(),
+            session: url.clone() + "/",
+            textures: url.clone() + "/textures/",
+            texture_keys: url + "/publickeys",
+        };
+        (endpoints, handle)
+    }
+
+    #[test]
+    fn login_with_custom_server() {
+        let (endpoints, handle) = mock_server(vec![
+            (200, r#"{"accessToken":"abc","selectedProfile":{"id":"0123456789abcdef0123456789abcdef","name":"Steve"}}"#.to_owned()),
+            (204, "".to_owned()),
+        ]);
+        let profile = Profile::login(&endpoints, "steve@example.com", "hunter2", "client").unwrap();
+        assert_eq!(profile.username, "Steve");
+        assert_eq!(profile.id, "0123456789abcdef0123456789abcdef");
+        assert_eq!(profile.access_token, "abc");
+        assert_eq!(profile.endpoints, endpoints);
+        profile.join_server("", &[], &[], None).unwrap();
+
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].line, "POST /authenticate HTTP/1.1");
+        assert!(requests[0].body.contains(r#""password":"hunter2""#));
+        assert_eq!(requests[1].line, "POST /session/minecraft/join HTTP/1.1");
+        assert!(requests[1].body.contains(r#""accessToken":"abc""#));
+    }
+
+    #[test]
+    fn structured_errors() {
+        let (endpoints, handle) = mock_server(vec![
+            (403, r#"{"error":"ForbiddenOperationException","errorMessage":"Invalid credentials. Invalid username or password."}"#.to_owned()),
+            (200, r#"{"accessToken":"abc"}"#.to_owned()),
+            (500, "Internal Server Error".to_owned()),
+        ]);
+        match Profile::login(&endpoints, "steve", "wrong", "client") {
+            Err(super::super::Error::Auth(AuthError::Rejected { error, message, cause })) => {
+                assert_eq!(error, "ForbiddenOperationException");
+                assert_eq!(message, "Invalid credentials. Invalid username or password.");
+                assert_eq!(cause, None);
+            },
+            res => panic!("expected a rejection, got {:?}", res),
+        }
+        match Profile::login(&endpoints, "steve", "hunter2", "client") {
+            Err(super::super::Error::Auth(AuthError::MissingField("selectedProfile.name"))) => {},
+            res => panic!("expected a missing field, got {:?}", res),
+        }
+        match Profile::login(&endpoints, "steve", "hunter2", "client") {
+            Err(super::super::Error::Auth(AuthError::Status(status))) => assert_eq!(status.as_u16(), 500),
+            res => panic!("expected a status error, got {:?}", res),
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn texture_signatures() {
+        use super::super::signature::test::{TEST_KEY, TEST_TEXTURES, TEST_SIGNATURE};
+        let (endpoints, handle) = mock_server(vec![
+            (200, json!({ "profilePropertyKeys": [{ "publicKey": TEST_KEY }] }).to_string()),
+        ]);
+        let keys = endpoints.fetch_texture_keys(None).unwrap();
+        assert_eq!(handle.join().unwrap()[0].line, "GET /publickeys HTTP/1.1");
+
+        let (status, url) = verify_textures(TEST_TEXTURES, Some(TEST_SIGNATURE), &keys);
+        assert_eq!(status, TexturesSignature::Verified);
+        assert_eq!(url.unwrap(), "http://textures.minecraft.net/texture/292009a4925b58f02c77dadc3ecef07ea4c7472f64e0fdc32ce5522489362680");
+        assert_eq!(verify_textures(TEST_TEXTURES, None, &keys), (TexturesSignature::Unsigned, None));
+        assert_eq!(verify_textures(TEST_TEXTURES, Some("bm90IGEgc2lnbmF0dXJl"), &keys), (TexturesSignature::Invalid, None));
+        // Signed, but not by a key we trust
+        assert_eq!(verify_textures(TEST_TEXTURES, Some(TEST_SIGNATURE), &[]), (TexturesSignature::Invalid, None));
+
+        // authlib-injector serves its key from the api root
+        let pem = format!("-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n", TEST_KEY);
+        let keys = parse_texture_keys(&json!({ "signaturePublickey": pem })).unwrap();
+        assert_eq!(verify_textures(TEST_TEXTURES, Some(TEST_SIGNATURE), &keys).0, TexturesSignature::Verified);
+        let endpoints = Endpoints {
+            texture_keys: TEST_KEY.to_owned(),
+            ..Endpoints::default()
+        };
+        assert_eq!(endpoints.fetch_texture_keys(None).unwrap().len(), 1);
+
+        // Mojang's key is built in, so needs no fetching
+        assert_eq!(Endpoints::default().cached_texture_keys().unwrap().len(), 1);
+        let endpoints = Endpoints {
+            texture_keys: "http://127.0.0.1:1/publickeys".to_owned(),
+            ..Endpoints::default()
+        };
+        assert!(endpoints.cached_texture_keys().is_none());
+    }
+
+    #[test]
+    fn offline_profiles() {
+        let profile = Profile::offline("Steve").unwrap();
+        assert_eq!(super::super::UUID::from_str(&profile.id), super::super::UUID::offline("Steve"));
+        assert_eq!(profile.id.len(), 32);
+        assert!(profile.is_complete());
+        // Neither of these may contact the authserver
+        let profile = profile.refresh("token").unwrap();
+        assert!(profile.join_server("", &[], &[], None).is_err());
+
+        assert!(Profile::offline("").is_err());
+        assert!(Profile::offline("Steve Jobs").is_err());
+        assert!(Profile::offline("abcdefghijklmnopq").is_err());
+    }
+}
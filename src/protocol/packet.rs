@@ -57,6 +57,10 @@ state_packets!(
                 field transaction_id: VarInt =,
                 field location: Position =,
             }
+            /// SetDifficulty changes the difficulty of a singleplayer world.
+            packet SetDifficulty {
+                field new_difficulty: u8 =,
+            }
             /// TabComplete is sent by the client when the client presses tab in
             /// the chat box.
             packet TabComplete {
@@ -202,6 +206,11 @@ state_packets!(
             packet KeepAliveServerbound_i32 {
                 field id: i32 =,
             }
+            /// LockDifficulty stops the difficulty of a singleplayer world
+            /// from being changed.
+            packet LockDifficulty {
+                field locked: bool =,
+            }
             /// PlayerPosition is used to update the player's position.
             packet PlayerPosition {
                 field x: f64 =,
@@ -370,6 +379,12 @@ state_packets!(
                 field slot: i16 =,
                 field clicked_item: Option<item::Stack> =,
             }
+            packet UpdateJigsawBlock {
+                field location: Position =,
+                field attachment_type: String =,
+                field target_pool: String =,
+                field final_state: String =,
+            }
             packet UpdateStructureBlock {
                 field location: Position =,
                 field action: VarInt =,
@@ -430,6 +445,15 @@ state_packets!(
                 field cursor_y: f32 =,
                 field cursor_z: f32 =,
             }
+            packet PlayerBlockPlacement_insideblock {
+                field hand: VarInt =,
+                field location: Position =,
+                field face: VarInt =,
+                field cursor_x: f32 =,
+                field cursor_y: f32 =,
+                field cursor_z: f32 =,
+                field inside_block: bool =,
+            }
             packet PlayerBlockPlacement_u8 {
                 field location: Position =,
                 field face: VarInt =,
@@ -479,6 +503,20 @@ state_packets!(
                 field velocity_y: i16 =,
                 field velocity_z: i16 =,
             }
+            packet SpawnObject_VarInt {
+                field entity_id: VarInt =,
+                field uuid: UUID =,
+                field ty: VarInt =,
+                field x: f64 =,
+                field y: f64 =,
+                field z: f64 =,
+                field pitch: i8 =,
+                field yaw: i8 =,
+                field data: i32 =,
+                field velocity_x: i16 =,
+                field velocity_y: i16 =,
+                field velocity_z: i16 =,
+            }
             packet SpawnObject_i32 {
                 field entity_id: VarInt =,
                 field uuid: UUID =,
@@ -609,6 +647,13 @@ state_packets!(
                 field location: Position =,
                 field direction: u8 =,
             }
+            packet SpawnPainting_VarInt {
+                field entity_id: VarInt =,
+                field uuid: UUID =,
+                field motive: VarInt =,
+                field location: Position =,
+                field direction: u8 =,
+            }
             packet SpawnPainting_NoUUID {
                 field entity_id: VarInt =,
                 field title: String =,
@@ -670,6 +715,18 @@ state_packets!(
                 field current_item: u16 =,
                 field metadata: types::Metadata =,
             }
+            packet SpawnPlayer_i32_HeldItem_String_NoProperties {
+                field entity_id: VarInt =,
+                field uuid: String =,
+                field name: String =,
+                field x: i32 =,
+                field y: i32 =,
+                field z: i32 =,
+                field yaw: i8 =,
+                field pitch: i8 =,
+                field current_item: u16 =,
+                field metadata: types::Metadata =,
+            }
 
             /// Animation is sent by the server to play an animation on a specific entity.
             packet Animation {
@@ -753,6 +810,10 @@ state_packets!(
             packet ServerDifficulty {
                 field difficulty: u8 =,
             }
+            packet ServerDifficulty_Locked {
+                field difficulty: u8 =,
+                field locked: bool =,
+            }
             /// TabCompleteReply is sent as a reply to a tab completion request.
             /// The matches should be possible completions for the command/chat the
             /// player sent.
@@ -1024,6 +1085,21 @@ state_packets!(
                 field data1: VarInt = when(|p: &Particle| p.particle_id == 36 || p.particle_id == 37 || p.particle_id == 38 || p.particle_id == 46),
                 field data2: VarInt = when(|p: &Particle| p.particle_id == 36),
             }
+            packet Particle_Data {
+                field particle_id: i32 =,
+                field long_distance: bool =,
+                field x: f32 =,
+                field y: f32 =,
+                field z: f32 =,
+                field offset_x: f32 =,
+                field offset_y: f32 =,
+                field offset_z: f32 =,
+                field speed: f32 =,
+                field count: i32 =,
+                /// Extra data for the particle, its format depends on
+                /// the particle type
+                field data: Vec<u8> =,
+            }
             packet Particle_Named {
                 field particle_id: String =,
                 field x: f32 =,
@@ -1054,6 +1130,16 @@ state_packets!(
                 /// information it displays in F3 mode
                 field reduced_debug_info: bool =,
             }
+            packet JoinGame_i32_ViewDistance {
+                field entity_id: i32 =,
+                field gamemode: u8 =,
+                field dimension: i32 =,
+                field max_players: u8 =,
+                field level_type: String =,
+                /// The server's view distance in chunks
+                field view_distance: VarInt =,
+                field reduced_debug_info: bool =,
+            }
             packet JoinGame_i8 {
                 /// The entity id the client will be referenced by
                 field entity_id: i32 =,
@@ -1091,6 +1177,20 @@ state_packets!(
                 field z: Option<u8> = when(|p: &Maps| p.columns > 0),
                 field data: Option<LenPrefixedBytes<VarInt>> = when(|p: &Maps| p.columns > 0),
             }
+            /// Maps_WithLocked is the 1.14 version of Maps, which
+            /// added whether the map is locked and names for icons.
+            packet Maps_WithLocked {
+                field item_damage: VarInt =,
+                field scale: i8 =,
+                field tracking_position: bool =,
+                field locked: bool =,
+                field icons: LenPrefixed<VarInt, packet::NamedMapIcon> =,
+                field columns: u8 =,
+                field rows: Option<u8> = when(|p: &Maps_WithLocked| p.columns > 0),
+                field x: Option<u8> = when(|p: &Maps_WithLocked| p.columns > 0),
+                field z: Option<u8> = when(|p: &Maps_WithLocked| p.columns > 0),
+                field data: Option<LenPrefixedBytes<VarInt>> = when(|p: &Maps_WithLocked| p.columns > 0),
+            }
             packet Maps_NoTracking {
                 field item_damage: VarInt =,
                 field scale: i8 =,
@@ -1316,6 +1416,11 @@ state_packets!(
                 field gamemode: u8 =,
                 field level_type: String =,
             }
+            packet Respawn_NoDifficulty {
+                field dimension: i32 =,
+                field gamemode: u8 =,
+                field level_type: String =,
+            }
             /// EntityHeadLook rotates an entity's head to the new angle.
             packet EntityHeadLook {
                 field entity_id: VarInt =,
@@ -1681,12 +1786,40 @@ state_packets!(
                 field sky_light_mask: VarInt =,
                 field block_light_mask: VarInt =,
                 field empty_sky_light_mask: VarInt =,
+                field empty_block_light_mask: VarInt =,
                 field light_arrays: Vec<u8> =,
             }
+            /// UpdateViewPosition tells the client which chunk is the
+            /// center of the loaded area.
+            packet UpdateViewPosition {
+                field chunk_x: VarInt =,
+                field chunk_z: VarInt =,
+            }
+            packet UpdateViewDistance {
+                field view_distance: VarInt =,
+            }
+            /// AcknowledgePlayerDigging confirms (or denies) a block
+            /// change caused by the client digging.
+            packet AcknowledgePlayerDigging {
+                field location: Position =,
+                field block: VarInt =,
+                field status: VarInt =,
+                field successful: bool =,
+            }
             packet TradeList {
                 field id: VarInt =,
                 field trades: LenPrefixed<u8, packet::Trade> =,
             }
+            /// TradeList_WithLevel lists the trades a villager offers
+            /// along with its level, as sent since 1.14.
+            packet TradeList_WithLevel {
+                field id: VarInt =,
+                field trades: LenPrefixed<u8, packet::TradeOffer> =,
+                field villager_level: VarInt =,
+                field experience: VarInt =,
+                field is_regular_villager: bool =,
+                field can_restock: Option<bool> = when(|_: &TradeList_WithLevel| crate::protocol::current_protocol_version() >= 490),
+            }
        }
     }
     login Login {
@@ -1964,6 +2097,44 @@ impl Default for MapIcon {
     }
 }
 
+/// A map icon as sent since 1.13, which can be labelled.
+#[derive(Debug, Default)]
+pub struct NamedMapIcon {
+    pub icon_type: VarInt,
+    pub x: i8,
+    pub z: i8,
+    pub direction: i8,
+    pub display_name: Option<format::Component>,
+}
+
+impl Serializable for NamedMapIcon {
+    fn read_from<R: io::Read>(buf: &mut R) -> Result<Self, Error> {
+        Ok(NamedMapIcon {
+            icon_type: Serializable::read_from(buf)?,
+            x: Serializable::read_from(buf)?,
+            z: Serializable::read_from(buf)?,
+            direction: Serializable::read_from(buf)?,
+            display_name: if bool::read_from(buf)? {
+                Some(Serializable::read_from(buf)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), Error> {
+        self.icon_type.write_to(buf)?;
+        self.x.write_to(buf)?;
+        self.z.write_to(buf)?;
+        self.direction.write_to(buf)?;
+        self.display_name.is_some().write_to(buf)?;
+        if let Some(ref name) = self.display_name {
+            name.write_to(buf)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Advancement {
     pub id: String,
@@ -2378,6 +2549,11 @@ pub enum RecipeData {
         experience: f32,
         cooking_time: VarInt,
     },
+    Stonecutting {
+        group: String,
+        ingredient: RecipeIngredient,
+        result: Option<item::Stack>,
+    },
 }
 
 impl Default for RecipeData {
@@ -2395,11 +2571,17 @@ pub struct Recipe {
 
 impl Serializable for Recipe {
     fn read_from<R: io::Read>(buf: &mut R) -> Result<Self, Error> {
-        let id = String::read_from(buf)?;
-        let ty = String::read_from(buf)?;
+        // 1.14 swapped the order and namespaced the type
+        let (id, ty) = if crate::protocol::current_protocol_version() >= 477 {
+            let ty = String::read_from(buf)?;
+            (String::read_from(buf)?, ty)
+        } else {
+            let id = String::read_from(buf)?;
+            (id, String::read_from(buf)?)
+        };
 
         let data =
-        match ty.as_ref() {
+        match ty.trim_start_matches("minecraft:") {
             "crafting_shapeless" => RecipeData::Shapeless {
                 group: Serializable::read_from(buf)?,
                 ingredients: Serializable::read_from(buf)?,
@@ -2455,14 +2637,19 @@ impl Serializable for Recipe {
                 experience: Serializable::read_from(buf)?,
                 cooking_time: Serializable::read_from(buf)?,
             },
-            "campfire" => RecipeData::Campfire {
+            "campfire_cooking" => RecipeData::Campfire {
                 group: Serializable::read_from(buf)?,
                 ingredient: Serializable::read_from(buf)?,
                 result: Serializable::read_from(buf)?,
                 experience: Serializable::read_from(buf)?,
                 cooking_time: Serializable::read_from(buf)?,
             },
-            _ => panic!("unrecognized recipe type: {}", ty)
+            "stonecutting" => RecipeData::Stonecutting {
+                group: Serializable::read_from(buf)?,
+                ingredient: Serializable::read_from(buf)?,
+                result: Serializable::read_from(buf)?,
+            },
+            _ => return Err(Error::Err(format!("Unrecognized recipe type: {}", ty))),
         };
 
         Ok(Recipe { id, ty, data })
@@ -2521,6 +2708,68 @@ impl Serializable for Trade {
     }
 }
 
+/// A trade offered by a villager, as sent since 1.14.
+#[derive(Debug, Default)]
+pub struct TradeOffer {
+    pub input_item_1: Option<item::Stack>,
+    pub output_item: Option<item::Stack>,
+    pub input_item_2: Option<item::Stack>,
+    pub trade_disabled: bool,
+    pub uses: i32,
+    pub max_uses: i32,
+    pub experience: i32,
+    pub special_price: i32,
+    pub price_multiplier: f32,
+    /// Only sent since 1.14.3
+    pub demand: Option<i32>,
+}
+
+impl Serializable for TradeOffer {
+    fn read_from<R: io::Read>(buf: &mut R) -> Result<Self, Error> {
+        let input_item_1 = Serializable::read_from(buf)?;
+        let output_item = Serializable::read_from(buf)?;
+        let input_item_2 = if bool::read_from(buf)? {
+            Serializable::read_from(buf)?
+        } else {
+            None
+        };
+        Ok(TradeOffer {
+            input_item_1,
+            output_item,
+            input_item_2,
+            trade_disabled: Serializable::read_from(buf)?,
+            uses: Serializable::read_from(buf)?,
+            max_uses: Serializable::read_from(buf)?,
+            experience: Serializable::read_from(buf)?,
+            special_price: Serializable::read_from(buf)?,
+            price_multiplier: Serializable::read_from(buf)?,
+            demand: if crate::protocol::current_protocol_version() >= 490 {
+                Some(Serializable::read_from(buf)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), Error> {
+        self.input_item_1.write_to(buf)?;
+        self.output_item.write_to(buf)?;
+        self.input_item_2.is_some().write_to(buf)?;
+        if self.input_item_2.is_some() {
+            self.input_item_2.write_to(buf)?;
+        }
+        self.trade_disabled.write_to(buf)?;
+        self.uses.write_to(buf)?;
+        self.max_uses.write_to(buf)?;
+        self.experience.write_to(buf)?;
+        self.special_price.write_to(buf)?;
+        self.price_multiplier.write_to(buf)?;
+        if crate::protocol::current_protocol_version() >= 490 {
+            self.demand.unwrap_or(0).write_to(buf)?;
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct CommandNode {
@@ -0,0 +1,443 @@
+// Resolution of user supplied server addresses into socket addresses.
+//
+// Handles `host`, `host:port`, `[v6]:port` and bare IPv6 literals and
+// follows `_minecraft._tcp` SRV records in the same way as the vanilla
+// client when no port is given.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Once;
+use std::time::Duration;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::{self, Rng};
+use log::{debug, warn};
+use super::Error;
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+const DNS_PORT: u16 = 53;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// A server address as typed by the user, split into its host
+/// and (optional) port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl ServerAddress {
+    pub fn parse(target: &str) -> Result<ServerAddress, Error> {
+        let target = target.trim();
+        let (host, port) = if target.starts_with('[') {
+            // Bracketed IPv6 literal, optionally followed by a port
+            let end = target.find(']')
+                .ok_or_else(|| Error::Err(format!("Missing ']' in address {}", target)))?;
+            let rest = &target[end + 1..];
+            let port = if rest.is_empty() {
+                None
+            } else if rest.starts_with(':') {
+                Some(&rest[1..])
+            } else {
+                return Err(Error::Err(format!("Unexpected characters after ']' in address {}", target)));
+            };
+            (&target[1..end], port)
+        } else if target.matches(':').count() > 1 {
+            // A bare IPv6 literal can't carry a port
+            (target, None)
+        } else {
+            let mut parts = target.splitn(2, ':');
+            (parts.next().unwrap(), parts.next())
+        };
+        if host.is_empty() {
+            return Err(Error::Err(format!("Missing host in address {}", target)));
+        }
+        let port = match port {
+            Some(port) => Some(port.parse::<u16>()
+                .map_err(|_| Error::Err(format!("Invalid port in address {}", target)))?),
+            None => None,
+        };
+        Ok(ServerAddress {
+            host: host.to_owned(),
+            port,
+        })
+    }
+}
+
+/// A single SRV record pointing at a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+/// The final result of resolving an address: the host and port
+/// that should be sent in the handshake and every socket address
+/// that may be tried, in order.
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub host: String,
+    pub port: u16,
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// Resolves server addresses, using a DNS server for SRV lookups.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    nameserver: Option<SocketAddr>,
+    timeout: Duration,
+}
+
+impl Default for Resolver {
+    fn default() -> Resolver {
+        Resolver::new()
+    }
+}
+
+impl Resolver {
+    /// Creates a resolver using the system's configured nameserver
+    /// for SRV lookups. If none can be found SRV lookups are skipped.
+    pub fn new() -> Resolver {
+        let nameserver = system_nameserver();
+        if nameserver.is_none() {
+            static WARN: Once = Once::new();
+            WARN.call_once(|| warn!("No nameserver found in /etc/resolv.conf, SRV records won't be followed unless cl_nameserver is set"));
+        }
+        Resolver {
+            nameserver,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Creates a resolver which sends SRV queries to the given
+    /// nameserver instead of the system one.
+    pub fn with_nameserver(nameserver: SocketAddr) -> Resolver {
+        Resolver {
+            nameserver: Some(nameserver),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Resolver {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn resolve(&self, target: &str) -> Result<Resolved, Error> {
+        let address = ServerAddress::parse(target)?;
+
+        if let Ok(ip) = address.host.parse::<IpAddr>() {
+            let port = address.port.unwrap_or(DEFAULT_PORT);
+            return Ok(Resolved {
+                host: address.host,
+                port,
+                addresses: vec![SocketAddr::new(ip, port)],
+            });
+        }
+
+        // SRV records are only consulted when the user didn't pick a port
+        if address.port.is_none() {
+            let records = match self.lookup_srv(&format!("_minecraft._tcp.{}", address.host)) {
+                Ok(val) => val,
+                Err(err) => {
+                    debug!("SRV lookup for {} failed: {}", address.host, err);
+                    vec![]
+                }
+            };
+            let mut resolved: Option<Resolved> = None;
+            for record in records {
+                let addrs = match (&*record.target, record.port).to_socket_addrs() {
+                    Ok(val) => val,
+                    Err(err) => {
+                        debug!("Failed to resolve SRV target {}: {}", record.target, err);
+                        continue;
+                    }
+                };
+                match resolved {
+                    Some(ref mut resolved) => resolved.addresses.extend(addrs),
+                    None => resolved = Some(Resolved {
+                        host: record.target.clone(),
+                        port: record.port,
+                        addresses: addrs.collect(),
+                    }),
+                }
+            }
+            if let Some(resolved) = resolved {
+                if !resolved.addresses.is_empty() {
+                    return Ok(resolved);
+                }
+            }
+        }
+
+        let port = address.port.unwrap_or(DEFAULT_PORT);
+        let addresses = (&*address.host, port).to_socket_addrs()?.collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Err(Error::Err(format!("No addresses found for {}", address.host)));
+        }
+        Ok(Resolved {
+            host: address.host,
+            port,
+            addresses,
+        })
+    }
+
+    /// Looks up the SRV records for the given name, sorted by
+    /// priority and then weight.
+    pub fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>, Error> {
+        let nameserver = match self.nameserver {
+            Some(val) => val,
+            None => return Ok(vec![]),
+        };
+        let bind: SocketAddr = if nameserver.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.connect(nameserver)?;
+
+        let id: u16 = rand::thread_rng().gen();
+        socket.send(&build_query(id, name, TYPE_SRV)?)?;
+
+        let mut buf = [0; 4096];
+        loop {
+            let len = socket.recv(&mut buf)?;
+            let msg = &buf[..len];
+            if msg.len() < 2 || (&msg[..2]).read_u16::<BigEndian>()? != id {
+                // Stray reply to an older query
+                continue;
+            }
+            let mut records = parse_srv_response(msg)?;
+            records.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.weight.cmp(&a.weight)));
+            return Ok(records);
+        }
+    }
+}
+
+/// Parses a nameserver given as an IP address with an optional
+/// port, e.g. `1.1.1.1`, `8.8.8.8:53` or `[::1]:53`.
+pub fn parse_nameserver(value: &str) -> Result<SocketAddr, Error> {
+    let address = ServerAddress::parse(value)?;
+    let ip = address.host.parse::<IpAddr>()
+        .map_err(|_| Error::Err(format!("Nameserver {} isn't an IP address", address.host)))?;
+    Ok(SocketAddr::new(ip, address.port.unwrap_or(DNS_PORT)))
+}
+
+/// Returns the first nameserver listed in `/etc/resolv.conf`
+fn system_nameserver() -> Option<SocketAddr> {
+    let file = fs::File::open("/etc/resolv.conf").ok()?;
+    for line in BufReader::new(file).lines() {
+        let line = line.ok()?;
+        let mut parts = line.split_whitespace();
+        if parts.next() != Some("nameserver") {
+            continue;
+        }
+        // Scoped addresses (fe80::1%eth0) can't be parsed by std
+        if let Some(ip) = parts.next().and_then(|v| v.parse::<IpAddr>().ok()) {
+            return Some(SocketAddr::new(ip, DNS_PORT));
+        }
+    }
+    None
+}
+
+fn build_query(id: u16, name: &str, ty: u16) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(32 + name.len());
+    buf.write_u16::<BigEndian>(id)?;
+    buf.write_u16::<BigEndian>(0x0100)?; // Recursion desired
+    buf.write_u16::<BigEndian>(1)?; // Questions
+    buf.write_u16::<BigEndian>(0)?; // Answers
+    buf.write_u16::<BigEndian>(0)?; // Authority
+    buf.write_u16::<BigEndian>(0)?; // Additional
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(Error::Err(format!("Invalid DNS name {}", name)));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf.write_u16::<BigEndian>(ty)?;
+    buf.write_u16::<BigEndian>(CLASS_IN)?;
+    Ok(buf)
+}
+
+fn parse_srv_response(msg: &[u8]) -> Result<Vec<SrvRecord>, Error> {
+    let invalid = || Error::Err("Invalid DNS response".to_owned());
+    if msg.len() < 12 {
+        return Err(invalid());
+    }
+    let mut header = &msg[2..12];
+    let flags = header.read_u16::<BigEndian>()?;
+    let questions = header.read_u16::<BigEndian>()?;
+    let answers = header.read_u16::<BigEndian>()?;
+    if flags & 0x8000 == 0 {
+        return Err(invalid());
+    }
+    match flags & 0xF {
+        0 => {},
+        // No such name
+        3 => return Ok(vec![]),
+        code => return Err(Error::Err(format!("DNS server returned error {}", code))),
+    }
+
+    let mut offset = 12;
+    for _ in 0 .. questions {
+        offset = read_name(msg, offset, &mut String::new())?;
+        offset += 4;
+    }
+
+    let mut records = vec![];
+    for _ in 0 .. answers {
+        offset = read_name(msg, offset, &mut String::new())?;
+        if offset + 10 > msg.len() {
+            return Err(invalid());
+        }
+        let mut fixed = &msg[offset..offset + 10];
+        let ty = fixed.read_u16::<BigEndian>()?;
+        let class = fixed.read_u16::<BigEndian>()?;
+        let _ttl = fixed.read_u32::<BigEndian>()?;
+        let len = fixed.read_u16::<BigEndian>()? as usize;
+        offset += 10;
+        if offset + len > msg.len() {
+            return Err(invalid());
+        }
+        if ty == TYPE_SRV && class == CLASS_IN && len >= 7 {
+            let mut data = &msg[offset..offset + 6];
+            let priority = data.read_u16::<BigEndian>()?;
+            let weight = data.read_u16::<BigEndian>()?;
+            let port = data.read_u16::<BigEndian>()?;
+            let mut target = String::new();
+            read_name(msg, offset + 6, &mut target)?;
+            records.push(SrvRecord {
+                priority,
+                weight,
+                port,
+                target,
+            });
+        }
+        offset += len;
+    }
+    Ok(records)
+}
+
+/// Reads a (possibly compressed) domain name starting at `offset`,
+/// returning the offset directly after it.
+fn read_name(msg: &[u8], mut offset: usize, out: &mut String) -> Result<usize, Error> {
+    let invalid = || Error::Err("Invalid name in DNS response".to_owned());
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *msg.get(offset).ok_or_else(invalid)? as usize;
+        if len & 0xC0 == 0xC0 {
+            let low = *msg.get(offset + 1).ok_or_else(invalid)? as usize;
+            if end.is_none() {
+                end = Some(offset + 2);
+            }
+            // Guard against pointer loops
+            jumps += 1;
+            if jumps > 32 {
+                return Err(invalid());
+            }
+            offset = ((len & 0x3F) << 8) | low;
+            continue;
+        }
+        offset += 1;
+        if len == 0 {
+            break;
+        }
+        let label = msg.get(offset .. offset + len).ok_or_else(invalid)?;
+        if !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(&String::from_utf8_lossy(label));
+        offset += len;
+    }
+    Ok(end.unwrap_or(offset))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn parse_addresses() {
+        let parse = |v| ServerAddress::parse(v).unwrap();
+        assert_eq!(parse("example.com"), ServerAddress { host: "example.com".to_owned(), port: None });
+        assert_eq!(parse("example.com:25566"), ServerAddress { host: "example.com".to_owned(), port: Some(25566) });
+        assert_eq!(parse("[::1]:25565"), ServerAddress { host: "::1".to_owned(), port: Some(25565) });
+        assert_eq!(parse("[::1]"), ServerAddress { host: "::1".to_owned(), port: None });
+        assert_eq!(parse("fe80::1"), ServerAddress { host: "fe80::1".to_owned(), port: None });
+        assert!(ServerAddress::parse("example.com:abc").is_err());
+        assert!(ServerAddress::parse("[::1").is_err());
+        assert!(ServerAddress::parse(":25565").is_err());
+    }
+
+    #[test]
+    fn parse_nameservers() {
+        assert_eq!(parse_nameserver("1.1.1.1").unwrap(), "1.1.1.1:53".parse().unwrap());
+        assert_eq!(parse_nameserver("8.8.8.8:5353").unwrap(), "8.8.8.8:5353".parse().unwrap());
+        assert_eq!(parse_nameserver("[::1]:53").unwrap(), "[::1]:53".parse().unwrap());
+        assert!(parse_nameserver("dns.example.com").is_err());
+    }
+
+    #[test]
+    fn resolve_literals() {
+        let resolver = Resolver { nameserver: None, timeout: Duration::from_secs(1) };
+        let res = resolver.resolve("[::1]:1234").unwrap();
+        assert_eq!(res.addresses, vec!["[::1]:1234".parse::<SocketAddr>().unwrap()]);
+        let res = resolver.resolve("127.0.0.1").unwrap();
+        assert_eq!(res.addresses, vec!["127.0.0.1:25565".parse::<SocketAddr>().unwrap()]);
+        // Without a nameserver hostnames skip SRV and go straight
+        // to the system's lookup
+        let res = resolver.resolve("localhost").unwrap();
+        assert_eq!((&*res.host, res.port), ("localhost", 25565));
+        assert!(res.addresses.iter().all(|v| v.ip().is_loopback()));
+    }
+
+    #[test]
+    fn srv_lookup() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (len, from) = server.recv_from(&mut buf).unwrap();
+            let query = &buf[..len];
+            let mut reply = Vec::new();
+            reply.extend_from_slice(&query[..2]);
+            reply.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0]);
+            reply.extend_from_slice(&query[12..]);
+            for &(priority, port, target) in &[(10u16, 25570u16, "b.example.com"), (5, 25580, "a.example.com")] {
+                // Pointer back to the question name
+                reply.extend_from_slice(&[0xC0, 12]);
+                reply.write_u16::<BigEndian>(TYPE_SRV).unwrap();
+                reply.write_u16::<BigEndian>(CLASS_IN).unwrap();
+                reply.write_u32::<BigEndian>(60).unwrap();
+                let mut data = Vec::new();
+                data.write_u16::<BigEndian>(priority).unwrap();
+                data.write_u16::<BigEndian>(0).unwrap();
+                data.write_u16::<BigEndian>(port).unwrap();
+                for label in target.split('.') {
+                    data.push(label.len() as u8);
+                    data.extend_from_slice(label.as_bytes());
+                }
+                data.push(0);
+                reply.write_u16::<BigEndian>(data.len() as u16).unwrap();
+                reply.extend_from_slice(&data);
+            }
+            server.send_to(&reply, from).unwrap();
+        });
+
+        let resolver = Resolver::with_nameserver(addr);
+        let records = resolver.lookup_srv("_minecraft._tcp.example.com").unwrap();
+        assert_eq!(records, vec![
+            SrvRecord { priority: 5, weight: 0, port: 25580, target: "a.example.com".to_owned() },
+            SrvRecord { priority: 10, weight: 0, port: 25570, target: "b.example.com".to_owned() },
+        ]);
+    }
+}
@@ -0,0 +1,98 @@
+// Checking RSA signatures (PKCS#1 v1.5 with SHA-1) made by the
+// session server, as found on the `textures` property of players.
+
+use std::fmt;
+use rsa::{self, PaddingScheme, PublicKeyParts, RSAPublicKey};
+use sha1::{self, Digest};
+use super::Error;
+
+#[derive(Clone)]
+pub struct PublicKey {
+    key: RSAPublicKey,
+}
+
+impl PublicKey {
+    /// Reads a DER encoded `SubjectPublicKeyInfo`, the format the
+    /// session server's key is distributed in.
+    pub fn from_der(data: &[u8]) -> Result<PublicKey, Error> {
+        let key = RSAPublicKey::from_pkcs8(data)
+            .map_err(|err| Error::Err(format!("Invalid public key: {}", err)))?;
+        Ok(PublicKey { key })
+    }
+
+    /// Reads the base64 of a DER encoded key, all on one line.
+    pub fn from_base64(data: &str) -> Result<PublicKey, Error> {
+        match base64::decode(data) {
+            Ok(der) => PublicKey::from_der(&der),
+            Err(err) => Err(Error::Err(format!("Invalid public key: {}", err))),
+        }
+    }
+
+    /// Reads a PEM encoded key, or the base64 of a DER encoded one.
+    pub fn from_pem(pem: &str) -> Result<PublicKey, Error> {
+        let data: String = pem.lines()
+            .filter(|line| !line.starts_with("-----"))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        match base64::decode(&data) {
+            Ok(der) => PublicKey::from_der(&der),
+            Err(err) => Err(Error::Err(format!("Invalid public key: {}", err))),
+        }
+    }
+
+    /// Returns whether `signature` is a valid signature of `message`
+    /// made with the private half of this key.
+    pub fn verify_sha1(&self, message: &[u8], signature: &[u8]) -> bool {
+        use rsa::PublicKey;
+        let mut hasher = sha1::Sha1::new();
+        hasher.input(message);
+        let padding = PaddingScheme::new_pkcs1v15_sign(Some(rsa::Hash::SHA1));
+        self.key.verify(padding, &hasher.result(), signature).is_ok()
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PublicKey({} bits)", self.key.size() * 8)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    // A 1024 bit key and a textures property signed with it by
+    // `openssl dgst -sha1 -sign`.
+    pub const TEST_KEY: &str = "MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDOIO28uZWkInkrSuSF0JRvCNgCh6pxUqhyUh9qXZkWUFGn0SxqCMK2wAMn0H+wtrh2xi/lD5lleLGTsfZ/gXgcZvVtsfeN3hTwHorAM5o7AT6lC5LbUGqBtb+A139CUh7B+H9TYcL8NAoOj7jovqpPb65s8hnIX6R+W42bKNIvxwIDAQAB";
+    pub const TEST_TEXTURES: &str = "eyJ0aW1lc3RhbXAiOjE1NDYzMDA4MDAwMDAsInByb2ZpbGVJZCI6IjA2OWE3OWY0NDRlOTQ3MjZhNWJlZmNhOTBlMzhhYWY1IiwicHJvZmlsZU5hbWUiOiJOb3RjaCIsInRleHR1cmVzIjp7IlNLSU4iOnsidXJsIjoiaHR0cDovL3RleHR1cmVzLm1pbmVjcmFmdC5uZXQvdGV4dHVyZS8yOTIwMDlhNDkyNWI1OGYwMmM3N2RhZGMzZWNlZjA3ZWE0Yzc0NzJmNjRlMGZkYzMyY2U1NTIyNDg5MzYyNjgwIn19fQ==";
+    pub const TEST_SIGNATURE: &str = "KNWjHPteE72QgQ1pZn4gkO3bLDkaeozHLQFcr3ycAc9vqcLYSExi9iAHEIIk9kvh6c01bmCF1G9ISgmljiHaLf6UMuQJQFCrShR3LjeDdQYvdJ3GZKGBJvivuDnJ28IoaRNBJmTezpbryKybFSj5OJiSNMCwxmcYl64spoY6uf0=";
+
+    #[test]
+    fn verify_signatures() {
+        let key = PublicKey::from_pem(TEST_KEY).unwrap();
+        let signature = base64::decode(TEST_SIGNATURE).unwrap();
+        assert!(key.verify_sha1(TEST_TEXTURES.as_bytes(), &signature));
+
+        let mut tampered = TEST_TEXTURES.as_bytes().to_vec();
+        tampered[10] ^= 1;
+        assert!(!key.verify_sha1(&tampered, &signature));
+        let mut bad_signature = signature.clone();
+        bad_signature[20] ^= 1;
+        assert!(!key.verify_sha1(TEST_TEXTURES.as_bytes(), &bad_signature));
+        assert!(!key.verify_sha1(TEST_TEXTURES.as_bytes(), &signature[1..]));
+    }
+
+    #[test]
+    fn pem_keys() {
+        let pem = format!("-----BEGIN PUBLIC KEY-----\n{}\n{}\n-----END PUBLIC KEY-----\n", &TEST_KEY[..64], &TEST_KEY[64..]);
+        let key = PublicKey::from_pem(&pem).unwrap();
+        assert_eq!(format!("{:?}", key), "PublicKey(1024 bits)");
+        let signature = base64::decode(TEST_SIGNATURE).unwrap();
+        assert!(key.verify_sha1(TEST_TEXTURES.as_bytes(), &signature));
+        assert!(PublicKey::from_pem("not a key").is_err());
+        assert!(PublicKey::from_base64(TEST_KEY).is_ok());
+        assert!(PublicKey::from_base64(&pem).is_err());
+        assert!(PublicKey::from_der(&[0x30, 0x03, 0x02, 0x01, 0x01]).is_err());
+    }
+}
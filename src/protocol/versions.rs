@@ -1,22 +1,42 @@
 use crate::protocol::*;
 
+mod v1_14_x;
 mod v19w02a;
 mod v18w50a;
 mod v1_13_2;
 mod v1_12_2;
+mod v1_12_1;
+mod v1_12;
 mod v1_11_2;
 mod v1_10_2;
+mod v1_9_4;
 mod v1_9_2;
 mod v1_9;
 mod v15w39c;
 mod v1_8_9;
 mod v1_7_10;
+mod v1_7_2;
 
-pub fn translate_internal_packet_id_for_version(version: i32, state: State, dir: Direction, id: i32, to_internal: bool) -> i32 {
-    match version {
+pub fn translate_internal_packet_id_for_version(version: i32, state: State, dir: Direction, id: i32, to_internal: bool) -> Result<i32, Error> {
+    let translated = match version {
         // https://wiki.vg/Protocol_History
         // https://wiki.vg/Protocol_version_numbers#Versions_after_the_Netty_rewrite
        
+        // 1.14.4
+        498 => v1_14_x::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.14.3
+        490 => v1_14_x::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.14.2
+        485 => v1_14_x::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.14.1
+        480 => v1_14_x::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.14
+        477 => v1_14_x::translate_internal_packet_id(state, dir, id, to_internal),
+
         // 19w02a
         452 => v19w02a::translate_internal_packet_id(state, dir, id, to_internal),
 
@@ -26,21 +46,39 @@ pub fn translate_internal_packet_id_for_version(version: i32, state: State, dir:
         // 1.13.2
         404 => v1_13_2::translate_internal_packet_id(state, dir, id, to_internal),
 
+        // 1.13.1
+        401 => v1_13_2::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.13
+        393 => v1_13_2::translate_internal_packet_id(state, dir, id, to_internal),
+
         // 1.12.2
         340 => v1_12_2::translate_internal_packet_id(state, dir, id, to_internal),
 
+        // 1.12.1
+        338 => v1_12_1::translate_internal_packet_id(state, dir, id, to_internal),
+
+        // 1.12
+        335 => v1_12::translate_internal_packet_id(state, dir, id, to_internal),
+
         // 1.11.2
         316 => v1_11_2::translate_internal_packet_id(state, dir, id, to_internal),
 
         // 1.11
         315 => v1_11_2::translate_internal_packet_id(state, dir, id, to_internal),
 
-        // 1.10.2
+        // 1.10.2 - 1.10
         210 => v1_10_2::translate_internal_packet_id(state, dir, id, to_internal),
 
+        // 1.9.4 - 1.9.3
+        110 => v1_9_4::translate_internal_packet_id(state, dir, id, to_internal),
+
         // 1.9.2
         109 => v1_9_2::translate_internal_packet_id(state, dir, id, to_internal),
 
+        // 1.9.1
+        108 => v1_9_2::translate_internal_packet_id(state, dir, id, to_internal),
+
         // 1.9
         107 => v1_9::translate_internal_packet_id(state, dir, id, to_internal),
 
@@ -53,6 +91,187 @@ pub fn translate_internal_packet_id_for_version(version: i32, state: State, dir:
         // 1.7.10 - 1.7.6
         5 => v1_7_10::translate_internal_packet_id(state, dir, id, to_internal),
 
-        _ => panic!("unsupported protocol version"),
+        // 1.7.5 - 1.7.2
+        4 => v1_7_2::translate_internal_packet_id(state, dir, id, to_internal),
+
+        _ => return Err(Error::UnsupportedVersion(version)),
+    };
+    translated.ok_or(Error::UnknownPacketId { state, direction: dir, id })
+}
+
+/// Ranges of server protocol versions, by release, along with the
+/// supported version whose packet table is used for the whole range.
+const COMPATIBLE_VERSIONS: &[(i32, i32, i32)] = &[
+    // 1.14 - 1.14.4
+    (477, 498, 498),
+    // 1.13 - 1.13.2
+    (393, 404, 404),
+    // 1.12 - 1.12.2
+    (335, 340, 340),
+    // 1.11 - 1.11.2
+    (315, 316, 316),
+    // 1.10 - 1.10.2
+    (205, 210, 210),
+    // 1.9 - 1.9.4
+    (107, 110, 110),
+    // 1.7.2 - 1.7.10
+    (4, 5, 5),
+];
+
+/// Picks the protocol version to use with a server reporting
+/// `server_version`, or `None` if nothing supported is close enough.
+pub fn best_protocol_version(server_version: i32) -> Option<i32> {
+    if SUPPORTED_PROTOCOLS.contains(&server_version) {
+        return Some(server_version);
+    }
+    COMPATIBLE_VERSIONS.iter()
+        .find(|&&(min, max, _)| min <= server_version && server_version <= max)
+        .map(|&(_, _, version)| version)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unknown_packet_id() {
+        let mut data = io::Cursor::new(vec![1, 2, 3]);
+        match packet::packet_by_id(404, State::Play, Direction::Clientbound, 0x7f, &mut data) {
+            Ok(Some(packet::Packet::Unknown { id, data })) => {
+                assert_eq!(id, 0x7f);
+                assert_eq!(data, vec![1, 2, 3]);
+            },
+            other => panic!("expected unknown packet, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut data = io::Cursor::new(vec![]);
+        match packet::packet_by_id(1, State::Status, Direction::Clientbound, 0x00, &mut data) {
+            Err(Error::UnsupportedVersion(1)) => {},
+            other => panic!("expected unsupported version, got {:?}", other),
+        }
+        let ping = packet::status::serverbound::StatusPing { ping: 0 };
+        assert!(ping.packet_id(1).is_err());
+    }
+
+    #[test]
+    fn best_version() {
+        assert_eq!(best_protocol_version(340), Some(340));
+        assert_eq!(best_protocol_version(452), Some(452));
+        assert_eq!(best_protocol_version(338), Some(338));
+        assert_eq!(best_protocol_version(339), Some(340));
+        assert_eq!(best_protocol_version(403), Some(404));
+        assert_eq!(best_protocol_version(4), Some(4));
+        assert_eq!(best_protocol_version(78), None);
+        assert_eq!(best_protocol_version(1000), None);
+    }
+    #[test]
+    fn packet_ids_round_trip() {
+        let states = [State::Handshaking, State::Play, State::Status, State::Login];
+        let dirs = [Direction::Serverbound, Direction::Clientbound];
+        for &version in SUPPORTED_PROTOCOLS.iter() {
+            for &state in &states {
+                for &dir in &dirs {
+                    for id in 0 .. 0x100 {
+                        let internal = match translate_internal_packet_id_for_version(version, state, dir, id, true) {
+                            Ok(internal) => internal,
+                            Err(_) => continue,
+                        };
+                        let back = translate_internal_packet_id_for_version(version, state, dir, internal, false).ok();
+                        assert_eq!(back, Some(id), "{:?} {:?} packet 0x{:02x} in version {}", state, dir, id, version);
+                    }
+                }
+            }
+            // Every version needs at least the handshake and login
+            assert!(translate_internal_packet_id_for_version(version, State::Handshaking, Direction::Serverbound, 0, true).is_ok());
+            assert!(translate_internal_packet_id_for_version(version, State::Login, Direction::Clientbound, 2, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn trade_list_1_14() {
+        for &(version, restock) in &[(485, None), (498, Some(true))] {
+            let trades = packet::play::clientbound::TradeList_WithLevel {
+                id: VarInt(1),
+                trades: LenPrefixed::new(vec![packet::TradeOffer {
+                    uses: 3,
+                    max_uses: 12,
+                    price_multiplier: 0.05,
+                    demand: restock.map(|_| 4),
+                    ..Default::default()
+                }]),
+                villager_level: VarInt(2),
+                experience: VarInt(10),
+                is_regular_villager: true,
+                can_restock: restock,
+            };
+            let mut data = vec![];
+            with_protocol_version(version, || trades.write(&mut data)).unwrap();
+            let len = data.len() as u64;
+            let mut data = io::Cursor::new(data);
+            match packet::packet_by_id(version, State::Play, Direction::Clientbound, 0x27, &mut data) {
+                Ok(Some(packet::Packet::TradeList_WithLevel(val))) => {
+                    assert_eq!(val.trades.data[0].uses, 3);
+                    assert_eq!(val.trades.data[0].max_uses, 12);
+                    assert_eq!(val.trades.data[0].demand, restock.map(|_| 4));
+                    assert_eq!(val.villager_level.0, 2);
+                    assert_eq!(val.can_restock, restock);
+                },
+                other => panic!("expected a trade list, got {:?}", other),
+            }
+            assert_eq!(data.position(), len);
+        }
+    }
+
+    #[test]
+    fn declare_recipes_1_14() {
+        // Two recipes as sent by a vanilla 1.14.4 server: the type
+        // comes before the id and is namespaced.
+        let mut data = vec![];
+        VarInt(2).write_to(&mut data).unwrap();
+        "minecraft:crafting_special_armordye".to_owned().write_to(&mut data).unwrap();
+        "minecraft:armor_dye".to_owned().write_to(&mut data).unwrap();
+        "minecraft:campfire_cooking".to_owned().write_to(&mut data).unwrap();
+        "minecraft:cooked_beef_from_campfire_cooking".to_owned().write_to(&mut data).unwrap();
+        "".to_owned().write_to(&mut data).unwrap(); // Group
+        data.extend_from_slice(&[
+            0x01, // One ingredient stack
+            0x01, 0x80, 0x06, 0x01, 0x00, // Present, beef, count 1, no nbt
+            0x01, 0x81, 0x06, 0x01, 0x00, // Result: cooked beef
+            0x3e, 0xb3, 0x33, 0x33, // 0.35 experience
+            0xd8, 0x04, // 600 ticks
+        ]);
+        let len = data.len() as u64;
+        let mut data = io::Cursor::new(data);
+        match packet::packet_by_id(498, State::Play, Direction::Clientbound, 0x5a, &mut data) {
+            Ok(Some(packet::Packet::DeclareRecipes(val))) => {
+                let recipes = &val.recipes.data;
+                assert_eq!(recipes.len(), 2);
+                assert_eq!(recipes[0].id, "minecraft:armor_dye");
+                assert!(match recipes[0].data { packet::RecipeData::ArmorDye => true, _ => false });
+                assert_eq!(recipes[1].id, "minecraft:cooked_beef_from_campfire_cooking");
+                match recipes[1].data {
+                    packet::RecipeData::Campfire { ref ingredient, ref result, experience, cooking_time, .. } => {
+                        assert_eq!(ingredient.data.len(), 1);
+                        assert!(result.is_some());
+                        assert!((experience - 0.35).abs() < 0.001);
+                        assert_eq!(cooking_time.0, 600);
+                    },
+                    ref other => panic!("expected a campfire recipe, got {:?}", other),
+                }
+            },
+            other => panic!("expected recipes, got {:?}", other),
+        }
+        assert_eq!(data.position(), len);
+
+        // Unknown types are an error rather than a crash
+        let mut data = vec![];
+        VarInt(1).write_to(&mut data).unwrap();
+        "minecraft:brewing".to_owned().write_to(&mut data).unwrap();
+        "minecraft:awkward_potion".to_owned().write_to(&mut data).unwrap();
+        let mut data = io::Cursor::new(data);
+        assert!(packet::packet_by_id(498, State::Play, Direction::Clientbound, 0x5a, &mut data).is_err());
     }
 }
@@ -27,7 +27,7 @@ protocol_packet_ids!(
             0x10 => PlayerDigging_u8
             0x11 => PlayerAction
             0x12 => SteerVehicle
-            0x13 => ResourcePackStatus
+            0x13 => ResourcePackStatus_hash
             0x14 => HeldItemChange
             0x15 => CreativeInventoryAction
             0x16 => SetSign
@@ -0,0 +1,148 @@
+protocol_packet_ids!(
+    handshake Handshaking {
+        serverbound Serverbound {
+            0x00 => Handshake
+        }
+        clientbound Clientbound {
+        }
+    }
+    play Play {
+        serverbound Serverbound {
+            0x00 => TeleportConfirm
+            0x02 => TabComplete
+            0x03 => ChatMessage
+            0x04 => ClientStatus
+            0x05 => ClientSettings
+            0x06 => ConfirmTransactionServerbound
+            0x07 => EnchantItem
+            0x08 => ClickWindow
+            0x09 => CloseWindow
+            0x0a => PluginMessageServerbound
+            0x0b => UseEntity
+            0x0c => KeepAliveServerbound_VarInt
+            0x0d => Player
+            0x0e => PlayerPosition
+            0x0f => PlayerPositionLook
+            0x10 => PlayerLook
+            0x11 => VehicleMove
+            0x12 => SteerBoat
+            0x13 => ClientAbilities
+            0x14 => PlayerDigging
+            0x15 => PlayerAction
+            0x16 => SteerVehicle
+            0x17 => CraftingBookData
+            0x18 => ResourcePackStatus
+            0x19 => AdvancementTab
+            0x1a => HeldItemChange
+            0x1b => CreativeInventoryAction
+            0x1c => SetSign
+            0x1d => ArmSwing
+            0x1e => SpectateTeleport
+            0x1f => PlayerBlockPlacement_f32
+            0x20 => UseItem
+        }
+        clientbound Clientbound {
+            0x00 => SpawnObject
+            0x01 => SpawnExperienceOrb
+            0x02 => SpawnGlobalEntity
+            0x03 => SpawnMob
+            0x04 => SpawnPainting
+            0x05 => SpawnPlayer_f64
+            0x06 => Animation
+            0x07 => Statistics
+            0x08 => BlockBreakAnimation
+            0x09 => UpdateBlockEntity
+            0x0a => BlockAction
+            0x0b => BlockChange_VarInt
+            0x0c => BossBar
+            0x0d => ServerDifficulty
+            0x0e => TabCompleteReply
+            0x0f => ServerMessage
+            0x10 => MultiBlockChange_VarInt
+            0x11 => ConfirmTransaction
+            0x12 => WindowClose
+            0x13 => WindowOpen
+            0x14 => WindowItems
+            0x15 => WindowProperty
+            0x16 => WindowSetSlot
+            0x17 => SetCooldown
+            0x18 => PluginMessageClientbound
+            0x19 => NamedSoundEffect
+            0x1a => Disconnect
+            0x1b => EntityAction
+            0x1c => Explosion
+            0x1d => ChunkUnload
+            0x1e => ChangeGameState
+            0x1f => KeepAliveClientbound_VarInt
+            0x20 => ChunkData
+            0x21 => Effect
+            0x22 => Particle
+            0x23 => JoinGame_i32
+            0x24 => Maps
+            0x25 => Entity
+            0x26 => EntityMove_i16
+            0x27 => EntityLookAndMove_i16
+            0x28 => EntityLook_VarInt
+            0x29 => VehicleTeleport
+            0x2a => SignEditorOpen
+            0x2b => PlayerAbilities
+            0x2c => CombatEvent
+            0x2d => PlayerInfo
+            0x2e => TeleportPlayer_WithConfirm
+            0x2f => EntityUsedBed
+            0x30 => UnlockRecipes_NoSmelting
+            0x31 => EntityDestroy
+            0x32 => EntityRemoveEffect
+            0x33 => ResourcePackSend
+            0x34 => Respawn
+            0x35 => EntityHeadLook
+            0x36 => SelectAdvancementTab
+            0x37 => WorldBorder
+            0x38 => Camera
+            0x39 => SetCurrentHotbarSlot
+            0x3a => ScoreboardDisplay
+            0x3b => EntityMetadata
+            0x3c => EntityAttach
+            0x3d => EntityVelocity
+            0x3e => EntityEquipment
+            0x3f => SetExperience
+            0x40 => UpdateHealth
+            0x41 => ScoreboardObjective
+            0x42 => SetPassengers
+            0x43 => Teams
+            0x44 => UpdateScore
+            0x45 => SpawnPosition
+            0x46 => TimeUpdate
+            0x47 => Title
+            0x48 => SoundEffect
+            0x49 => PlayerListHeaderFooter
+            0x4a => CollectItem
+            0x4b => EntityTeleport_f64
+            0x4c => Advancements
+            0x4d => EntityProperties
+            0x4e => EntityEffect
+        }
+    }
+    login Login {
+        serverbound Serverbound {
+            0x00 => LoginStart
+            0x01 => EncryptionResponse
+        }
+        clientbound Clientbound {
+            0x00 => LoginDisconnect
+            0x01 => EncryptionRequest
+            0x02 => LoginSuccess
+            0x03 => SetInitialCompression
+        }
+    }
+    status Status {
+        serverbound Serverbound {
+            0x00 => StatusRequest
+            0x01 => StatusPing
+        }
+        clientbound Clientbound {
+            0x00 => StatusResponse
+            0x01 => StatusPong
+        }
+    }
+);
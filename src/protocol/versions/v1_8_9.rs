@@ -33,7 +33,7 @@ protocol_packet_ids!(
             0x16 => ClientStatus
             0x17 => PluginMessageServerbound
             0x18 => SpectateTeleport
-            0x19 => ResourcePackStatus
+            0x19 => ResourcePackStatus_hash
         }
         clientbound Clientbound {
             0x00 => KeepAliveClientbound_VarInt
@@ -5,21 +5,10 @@ use cgmath::{Point3, Matrix4};
 use byteorder::{WriteBytesExt, NativeEndian};
 use crate::gl;
 use super::glsl;
+use super::shaders;
 
 pub struct Clouds {
-    program: gl::Program,
-    // Shader props
-    _a_position: gl::Attribute,
-    u_perspective_matrix: gl::Uniform,
-    u_camera_matrix: gl::Uniform,
-    u_light_level: gl::Uniform,
-    u_sky_offset: gl::Uniform,
-    u_offset: gl::Uniform,
-    u_texture_info: gl::Uniform,
-    u_atlas: gl::Uniform,
-    u_textures: gl::Uniform,
-    u_cloud_map: gl::Uniform,
-    u_cloud_offset: gl::Uniform,
+    shader: CloudShader,
 
     array: gl::VertexArray,
     _buffer: gl::Buffer,
@@ -34,76 +23,55 @@ pub struct Clouds {
     num_points: usize,
 }
 
-impl Clouds {
-    pub fn new(greg: &glsl::Registry, textures: Arc<RwLock<super::TextureManager>>) -> Clouds {
-        let program = gl::Program::new();
+struct CloudShader {
+    program: gl::Program,
+    // Shader props
+    a_position: gl::Attribute,
+    u_perspective_matrix: gl::Uniform,
+    u_camera_matrix: gl::Uniform,
+    u_light_level: gl::Uniform,
+    u_sky_offset: gl::Uniform,
+    u_offset: gl::Uniform,
+    u_texture_info: gl::Uniform,
+    u_atlas: gl::Uniform,
+    u_textures: gl::Uniform,
+    u_cloud_map: gl::Uniform,
+    u_cloud_offset: gl::Uniform,
+}
 
+impl CloudShader {
+    fn new(greg: &glsl::Registry, attributes: &[(&str, gl::Attribute)]) -> Result<CloudShader, String> {
         let vertex = greg.get("clouds_vertex");
         let geo = greg.get("clouds_geo");
         let fragment = greg.get("clouds_frag");
+        let program = shaders::try_create_program(&[
+            (gl::VERTEX_SHADER, &vertex),
+            (gl::GEOMETRY_SHADER, &geo),
+            (gl::FRAGMENT_SHADER, &fragment),
+        ], attributes)?;
+
+        let uniform = |name: &str| program.uniform_location(name).ok_or_else(|| format!("Missing uniform {}", name));
+        Ok(CloudShader {
+            a_position: program.attribute_location("aPosition").ok_or_else(|| "Missing attribute aPosition".to_owned())?,
+            u_perspective_matrix: uniform("perspectiveMatrix")?,
+            u_camera_matrix: uniform("cameraMatrix")?,
+            u_light_level: uniform("lightLevel")?,
+            u_sky_offset: uniform("skyOffset")?,
+            u_offset: uniform("offset")?,
+            u_texture_info: uniform("textureInfo")?,
+            u_atlas: uniform("atlas")?,
+            u_textures: uniform("textures")?,
+            u_cloud_map: uniform("cloudMap")?,
+            u_cloud_offset: uniform("cloudOffset")?,
+            program,
+        })
+    }
+}
 
-        let v = gl::Shader::new(gl::VERTEX_SHADER);
-        v.set_source(&vertex);
-        v.compile();
-
-        if v.get_parameter(gl::COMPILE_STATUS) == 0 {
-            println!("Src: {}", vertex);
-            panic!("Shader error: {}", v.get_info_log());
-        } else {
-            let log = v.get_info_log();
-            let log = log.trim().trim_matches('\u{0}');
-            if !log.is_empty() {
-                println!("{}", log);
-            }
-        }
-
-        let g = gl::Shader::new(gl::GEOMETRY_SHADER);
-        g.set_source(&geo);
-        g.compile();
-
-        if g.get_parameter(gl::COMPILE_STATUS) == 0 {
-            println!("Src: {}", geo);
-            panic!("Shader error: {}", g.get_info_log());
-        } else {
-            let log = g.get_info_log();
-            let log = log.trim().trim_matches('\u{0}');
-            if !log.is_empty() {
-                println!("{}", log);
-            }
-        }
-
-        let f = gl::Shader::new(gl::FRAGMENT_SHADER);
-        f.set_source(&fragment);
-        f.compile();
-
-        if f.get_parameter(gl::COMPILE_STATUS) == 0 {
-            println!("Src: {}", fragment);
-            panic!("Shader error: {}", f.get_info_log());
-        } else {
-            let log = f.get_info_log();
-            let log = log.trim().trim_matches('\u{0}');
-            if !log.is_empty() {
-                println!("{}", log);
-            }
-        }
-
-        program.attach_shader(v);
-        program.attach_shader(g);
-        program.attach_shader(f);
-        program.link();
-        program.use_program();
-
-        let a_position = program.attribute_location("aPosition").unwrap();
-        let u_perspective_matrix = program.uniform_location("perspectiveMatrix").unwrap();
-        let u_camera_matrix = program.uniform_location("cameraMatrix").unwrap();
-        let u_light_level = program.uniform_location("lightLevel").unwrap();
-        let u_sky_offset = program.uniform_location("skyOffset").unwrap();
-        let u_offset = program.uniform_location("offset").unwrap();
-        let u_texture_info = program.uniform_location("textureInfo").unwrap();
-        let u_atlas = program.uniform_location("atlas").unwrap();
-        let u_textures = program.uniform_location("textures").unwrap();
-        let u_cloud_map = program.uniform_location("cloudMap").unwrap();
-        let u_cloud_offset = program.uniform_location("cloudOffset").unwrap();
+impl Clouds {
+    pub fn new(greg: &glsl::Registry, textures: Arc<RwLock<super::TextureManager>>) -> Clouds {
+        let shader = CloudShader::new(greg, &[]).unwrap_or_else(|err| panic!("Shader error: {}", err));
+        let a_position = shader.a_position;
 
         let array = gl::VertexArray::new();
         array.bind();
@@ -134,19 +102,7 @@ impl Clouds {
         texture.set_parameter(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
 
         Clouds {
-            program,
-            // Shader props
-            _a_position: a_position,
-            u_perspective_matrix,
-            u_camera_matrix,
-            u_light_level,
-            u_sky_offset,
-            u_offset,
-            u_texture_info,
-            u_atlas,
-            u_textures,
-            u_cloud_map,
-            u_cloud_offset,
+            shader,
 
             array,
             _buffer: buffer,
@@ -162,26 +118,34 @@ impl Clouds {
         }
     }
 
+    /// Recompiles the shaders from `greg`, keeping the current program
+    /// if that fails.
+    pub fn reload(&mut self, greg: &glsl::Registry) -> Result<(), String> {
+        self.shader = CloudShader::new(greg, &[("aPosition", self.shader.a_position)])?;
+        Ok(())
+    }
+
     pub fn draw(&mut self, camera_pos: &Point3<f64>, perspective_matrix: &Matrix4<f32>, camera_matrix: &Matrix4<f32>, light_level: f32, sky_offset: f32, delta: f64) {
         self.offset += delta;
 
         let tex = super::Renderer::get_texture(&self.textures, "steven:environment/clouds");
 
-        self.program.use_program();
-        self.u_perspective_matrix.set_matrix4(perspective_matrix);
-        self.u_camera_matrix.set_matrix4(camera_matrix);
-        self.u_sky_offset.set_float(sky_offset);
-        self.u_light_level.set_float(light_level);
-        self.u_offset.set_float3(camera_pos.x.floor() as f32, 0.0, camera_pos.z.floor() as f32);
-        self.u_texture_info.set_float4(
+        let shader = &self.shader;
+        shader.program.use_program();
+        shader.u_perspective_matrix.set_matrix4(perspective_matrix);
+        shader.u_camera_matrix.set_matrix4(camera_matrix);
+        shader.u_sky_offset.set_float(sky_offset);
+        shader.u_light_level.set_float(light_level);
+        shader.u_offset.set_float3(camera_pos.x.floor() as f32, 0.0, camera_pos.z.floor() as f32);
+        shader.u_texture_info.set_float4(
             tex.get_x() as f32,
             tex.get_y() as f32,
             tex.get_width() as f32,
             tex.get_height() as f32
         );
-        self.u_atlas.set_float(tex.atlas as f32);
-        self.u_cloud_offset.set_float((self.offset / 60.0) as f32);
-        self.u_textures.set_int(0);
+        shader.u_atlas.set_float(tex.atlas as f32);
+        shader.u_cloud_offset.set_float((self.offset / 60.0) as f32);
+        shader.u_textures.set_int(0);
 
         gl::active_texture(1);
         self.texture.bind(gl::TEXTURE_2D);
@@ -189,7 +153,7 @@ impl Clouds {
             self.texture.sub_image_2d(gl::TEXTURE_2D, 0, 0, 0, 512, 512, gl::RED, gl::UNSIGNED_BYTE, &self.heightmap_data);
             self.dirty = false;
         }
-        self.u_cloud_map.set_int(1);
+        shader.u_cloud_map.set_int(1);
         self.array.bind();
         gl::draw_arrays(gl::POINTS, 0, self.num_points);
     }
@@ -29,6 +29,18 @@ impl Registry {
         self.shaders.insert(name.to_owned(), source.trim().to_owned());
     }
 
+    /// Replaces the source of the already registered shader `name`.
+    pub fn replace(&mut self, name: &str, source: &str) {
+        if !self.shaders.contains_key(name) {
+            panic!("shader {} isn't defined", name);
+        }
+        self.shaders.insert(name.to_owned(), source.trim().to_owned());
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.shaders.keys().cloned().collect()
+    }
+
     pub fn get(&self, name: &str) -> String {
         let mut out = String::new();
         out.push_str("#version 150\n");
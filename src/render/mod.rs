@@ -21,9 +21,13 @@ pub mod model;
 pub mod clouds;
 
 use std::collections::HashMap;
+use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 use std::io::Write;
+use std::path::PathBuf;
 use crate::resources;
+use crate::watcher;
 use crate::gl;
 use image;
 use image::{GenericImage, GenericImageView};
@@ -31,8 +35,9 @@ use byteorder::{WriteBytesExt, NativeEndian};
 use serde_json;
 use cgmath::prelude::*;
 use crate::world;
+use crate::protocol::mojang;
 use collision;
-use log::{error, trace};
+use log::{error, info, trace};
 
 use std::hash::BuildHasherDefault;
 use crate::types::hash::FNVHash;
@@ -66,11 +71,18 @@ pub struct Renderer {
     chunk_shader_alpha: ChunkShaderAlpha,
     trans_shader: TransShader,
 
+    shader_dir: Option<PathBuf>,
+    shader_watcher: Option<watcher::Watcher>,
+
     element_buffer: gl::Buffer,
     element_buffer_size: usize,
     element_buffer_type: gl::Type,
 
     pub camera: Camera,
+    /// The vertical field of view in degrees, shared so that it can
+    /// be changed from outside of the renderer
+    fov: Rc<Cell<f64>>,
+    perspective_fov: f64,
     perspective_matrix: cgmath::Matrix4<f32>,
     camera_matrix: cgmath::Matrix4<f32>,
     pub frustum: collision::Frustum<f32>,
@@ -86,7 +98,7 @@ pub struct Renderer {
     // Light renderering
     pub light_level: f32,
     pub sky_offset: f32,
-    skin_request: mpsc::Sender<String>,
+    skin_request: mpsc::Sender<(String, String)>,
     skin_reply: mpsc::Receiver<(String, Option<image::DynamicImage>)>,
 }
 
@@ -209,6 +221,9 @@ impl Renderer {
             chunk_shader_alpha,
             trans_shader,
 
+            shader_dir: None,
+            shader_watcher: None,
+
             element_buffer: gl::Buffer::new(),
             element_buffer_size: 0,
             element_buffer_type: gl::UNSIGNED_BYTE,
@@ -221,6 +236,8 @@ impl Renderer {
                 yaw: 0.0,
                 pitch: ::std::f64::consts::PI,
             },
+            fov: Rc::new(Cell::new(90.0)),
+            perspective_fov: 0.0,
             perspective_matrix: cgmath::Matrix4::identity(),
             camera_matrix: cgmath::Matrix4::identity(),
             frustum: collision::Frustum::from_matrix4(cgmath::Matrix4::identity()).unwrap(),
@@ -251,21 +268,25 @@ impl Renderer {
             }
         }
 
-        if self.height != height || self.width != width {
+        let resized = self.height != height || self.width != width;
+        if resized {
             self.width = width;
             self.height = height;
             gl::viewport(0, 0, width as i32, height as i32);
 
+            self.init_trans(width, height);
+        }
+        let fov = self.fov.get();
+        if resized || self.perspective_fov != fov {
+            self.perspective_fov = fov;
             self.perspective_matrix = cgmath::Matrix4::from(
                 cgmath::PerspectiveFov {
-                    fovy: cgmath::Rad::from(cgmath::Deg(90f32)),
+                    fovy: cgmath::Rad::from(cgmath::Deg(fov as f32)),
                     aspect: (width as f32 / height as f32),
                     near: 0.1f32,
                     far: 500.0f32,
                 }
             );
-
-            self.init_trans(width, height);
         }
 
         self.view_vector = cgmath::Vector3::new(
@@ -283,7 +304,53 @@ impl Renderer {
         self.frustum = collision::Frustum::from_matrix4(self.perspective_matrix * self.camera_matrix).unwrap();
     }
 
+    /// Sets the directory of shaders overriding the built in ones,
+    /// optionally reloading them whenever it changes.
+    pub fn set_shader_dir(&mut self, dir: Option<PathBuf>, hot_reload: bool) {
+        if self.shader_dir != dir {
+            self.shader_dir = dir;
+            self.reload_shaders();
+        }
+        self.shader_watcher = match self.shader_dir {
+            Some(ref dir) if hot_reload => Some(watcher::Watcher::new(vec![dir.clone()])),
+            _ => None,
+        };
+    }
+
+    /// Recompiles every shader, applying the overrides in the shader
+    /// directory. A shader that fails to compile keeps its current
+    /// program.
+    pub fn reload_shaders(&mut self) {
+        let mut greg = glsl::Registry::new();
+        shaders::add_shaders(&mut greg);
+        if let Some(ref dir) = self.shader_dir {
+            shaders::load_overrides(&mut greg, dir);
+        }
+
+        let results = vec![
+            ("chunk", self.chunk_shader.reload(&greg)),
+            ("chunk alpha", self.chunk_shader_alpha.reload(&greg)),
+            ("transparency", self.trans_shader.reload(&greg)),
+            ("ui", self.ui.reload_shaders(&greg)),
+            ("model", self.model.reload_shaders(&greg)),
+            ("clouds", self.clouds.reload(&greg)),
+        ];
+        for (name, res) in results {
+            if let Err(err) = res {
+                error!("Failed to reload the {} shader: {}", name, err);
+            }
+        }
+        // Rebinds the fragment outputs of the new programs
+        if self.width > 0 && self.height > 0 {
+            self.init_trans(self.width, self.height);
+        }
+    }
+
     pub fn tick(&mut self, world: &mut world::World, delta: f64, width: u32, height: u32, physical_width: u32, physical_height: u32) {
+        if self.shader_watcher.as_ref().map_or(false, |w| w.changed()) {
+            info!("Reloading shaders");
+            self.reload_shaders();
+        }
         self.update_textures(delta);
 
         let trans = self.trans.as_mut().unwrap();
@@ -587,6 +654,12 @@ impl Renderer {
         self.trans = Some(TransInfo::new(width, height, &self.chunk_shader_alpha, &self.trans_shader));
     }
 
+    /// Returns the field of view, which is applied on the next frame
+    /// after it is changed.
+    pub fn fov(&self) -> Rc<Cell<f64>> {
+        self.fov.clone()
+    }
+
     pub fn get_textures(&self) -> Arc<RwLock<TextureManager>> {
         self.textures.clone()
     }
@@ -787,12 +860,20 @@ pub struct TextureManager {
     free_dynamics: Vec<Texture>,
 
     skins: HashMap<String, AtomicIsize, BuildHasherDefault<FNVHash>>,
+    /// Url skins are downloaded from, the hash is appended to it
+    texture_server: String,
 
     _skin_thread: thread::JoinHandle<()>,
 }
 
+/// Returns the hash of the skin at `url`, the last segment of its
+/// path, which is what the skin is downloaded and cached by.
+fn skin_hash(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
 impl TextureManager {
-    fn new(res: Arc<RwLock<resources::Manager>>) -> (TextureManager, mpsc::Sender<String>, mpsc::Receiver<(String, Option<image::DynamicImage>)>) {
+    fn new(res: Arc<RwLock<resources::Manager>>) -> (TextureManager, mpsc::Sender<(String, String)>, mpsc::Receiver<(String, Option<image::DynamicImage>)>) {
         let (tx, rx) = mpsc::channel();
         let (stx, srx) = mpsc::channel();
         let skin_thread = thread::spawn(|| Self::process_skins(srx, tx));
@@ -810,6 +891,7 @@ impl TextureManager {
             dynamic_textures: HashMap::with_hasher(BuildHasherDefault::default()),
             free_dynamics: Vec::new(),
             skins: HashMap::with_hasher(BuildHasherDefault::default()),
+            texture_server: mojang::DEFAULT_TEXTURE_SERVER.to_owned(),
 
             _skin_thread: skin_thread,
         };
@@ -837,15 +919,21 @@ impl TextureManager {
         ]);
     }
 
-    fn process_skins(recv: mpsc::Receiver<String>, reply: mpsc::Sender<(String, Option<image::DynamicImage>)>) {
+    /// Sets the url skins are downloaded from. Skins already loaded
+    /// are kept.
+    pub fn set_texture_server(&mut self, url: &str) {
+        self.texture_server = url.to_owned();
+    }
+
+    fn process_skins(recv: mpsc::Receiver<(String, String)>, reply: mpsc::Sender<(String, Option<image::DynamicImage>)>) {
         use reqwest;
         let client = reqwest::Client::new();
         loop {
-            let hash = match recv.recv() {
+            let (hash, url) = match recv.recv() {
                 Ok(val) => val,
                 Err(_) => return, // Most likely shutting down
             };
-            match Self::obtain_skin(&client, &hash) {
+            match Self::obtain_skin(&client, &hash, &url) {
                 Ok(img) => {
                     let _ = reply.send((hash, Some(img)));
                 },
@@ -857,7 +945,7 @@ impl TextureManager {
         }
     }
 
-    fn obtain_skin(client: &::reqwest::Client, hash: &str) -> Result<image::DynamicImage, ::std::io::Error> {
+    fn obtain_skin(client: &::reqwest::Client, hash: &str, url: &str) -> Result<image::DynamicImage, ::std::io::Error> {
         use std::io::Read;
         use std::fs;
         use std::path::Path;
@@ -872,7 +960,6 @@ impl TextureManager {
             file.read_to_end(&mut buf)?;
         } else {
             // Need to download it
-            let url = &format!("http://textures.minecraft.net/texture/{}", hash);
             let mut res = match client.get(url).send() {
                 Ok(val) => val,
                 Err(err) => {
@@ -975,7 +1062,7 @@ impl TextureManager {
     }
 
     fn get_skin(&self, url: &str) -> Option<Texture> {
-        let hash = &url["http://textures.minecraft.net/texture/".len()..];
+        let hash = skin_hash(url);
         if let Some(skin) = self.skins.get(hash) {
             skin.fetch_add(1, Ordering::Relaxed);
         }
@@ -983,14 +1070,14 @@ impl TextureManager {
     }
 
     pub fn release_skin(&self, url: &str) {
-        let hash = &url["http://textures.minecraft.net/texture/".len()..];
+        let hash = skin_hash(url);
         if let Some(skin) = self.skins.get(hash) {
             skin.fetch_sub(1, Ordering::Relaxed);
         }
     }
 
     fn load_skin(&mut self, renderer: &Renderer, url: &str) {
-        let hash = &url["http://textures.minecraft.net/texture/".len()..];
+        let hash = skin_hash(url);
         let res = self.resources.clone();
         // TODO: This shouldn't be hardcoded to steve but instead
         // have a way to select alex as a default.
@@ -1003,7 +1090,8 @@ impl TextureManager {
         };
         self.put_dynamic(&format!("skin-{}", hash), img);
         self.skins.insert(hash.to_owned(), AtomicIsize::new(0));
-        renderer.skin_request.send(hash.to_owned()).unwrap();
+        let url = format!("{}{}", self.texture_server, hash);
+        renderer.skin_request.send((hash.to_owned(), url)).unwrap();
     }
 
     fn update_skin(&mut self, hash: String, img: image::DynamicImage) {
@@ -40,21 +40,23 @@ impl Manager {
             max_index: 0,
         };
         m.add_collection(
-            &greg.get("model_vertex"),
-            &greg.get("model_frag"),
+            greg,
+            "model_vertex", "model_frag",
             gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA
         );
         m.add_collection(
-            &greg.get("sun_vertex"),
-            &greg.get("sun_frag"),
+            greg,
+            "sun_vertex", "sun_frag",
             gl::SRC_ALPHA, gl::ONE_FACTOR
         );
         m
     }
 
-    pub fn add_collection(&mut self, vert: &str, frag: &str, blend_s: gl::Factor, blend_d: gl::Factor) -> CollectionKey {
+    pub fn add_collection(&mut self, greg: &glsl::Registry, vert: &'static str, frag: &'static str, blend_s: gl::Factor, blend_d: gl::Factor) -> CollectionKey {
         let collection = Collection {
-            shader: ModelShader::new_manual(vert, frag),
+            shader: ModelShader::new_manual(&greg.get(vert), &greg.get(frag)),
+            vert,
+            frag,
             models: HashMap::with_hasher(BuildHasherDefault::default()),
             blend_s,
             blend_d,
@@ -64,6 +66,22 @@ impl Manager {
         CollectionKey(self.collections.len())
     }
 
+    /// Recompiles the shaders of every collection, those that fail
+    /// keep their current program.
+    pub fn reload_shaders(&mut self, greg: &glsl::Registry) -> Result<(), String> {
+        let mut errors = vec![];
+        for collection in &mut self.collections {
+            if let Err(err) = collection.shader.reload_manual(&greg.get(collection.vert), &greg.get(collection.frag)) {
+                errors.push(format!("{}: {}", collection.vert, err));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
     pub fn get_model(&mut self, key: ModelKey) -> Option<&mut Model> {
         let collection = &mut self.collections[(key.0).0];
         collection.models.get_mut(&key)
@@ -231,6 +249,8 @@ impl Manager {
 
 struct Collection {
     shader: ModelShader,
+    vert: &'static str,
+    frag: &'static str,
 
     models: HashMap<ModelKey, Model, BuildHasherDefault<FNVHash>>,
     blend_s: gl::Factor,
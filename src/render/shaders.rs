@@ -12,6 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fs;
+use std::path::Path;
+use log::error;
+
 use crate::render::glsl;
 use crate::gl;
 
@@ -97,6 +101,43 @@ macro_rules! init_shader {
                 $name::new_manual(&v, &f)
             }
 
+            /// Recompiles the program from `reg`, keeping the current
+            /// one if that fails.
+            #[allow(dead_code)]
+            pub fn reload(&mut self, reg: &glsl::Registry) -> Result<(), String> {
+                let v = get_shader!(reg, $vert $(,stringify!($vdef))*);
+                let f = get_shader!(reg, $frag $(,stringify!($fdef))*);
+                self.reload_manual(&v, &f)
+            }
+
+            #[allow(dead_code)]
+            pub fn reload_manual(&mut self, v: &str, f: &str) -> Result<(), String> {
+                #[allow(unused_mut)]
+                let mut attributes = vec![$(($glname, self.$field)),*];
+                $(
+                    if let Some(location) = self.$ofield {
+                        attributes.push(($oglname, location));
+                    }
+                )*
+                let shader = shaders::try_create_program(&[(gl::VERTEX_SHADER, v), (gl::FRAGMENT_SHADER, f)], &attributes)?;
+                *self = $name {
+                    $(
+                        $field: shader.attribute_location($glname).ok_or_else(|| format!("Missing attribute {}", $glname))?,
+                    )*
+                    $(
+                        $ofield: shader.attribute_location($oglname),
+                    )*
+                    $(
+                        $ufield: shader.uniform_location($uglname).ok_or_else(|| format!("Missing uniform {}", $uglname))?,
+                    )*
+                    $(
+                        $oufield: shader.uniform_location($ouglname),
+                    )*
+                    program: shader,
+                };
+                Ok(())
+            }
+
             #[allow(dead_code)]
             pub fn new_manual(v: &str, f: &str) -> $name {
                 let shader = shaders::create_program(&v, &f);
@@ -121,41 +162,60 @@ macro_rules! init_shader {
 }
 
 pub fn create_program(vertex: &str, fragment: &str) -> gl::Program {
+    match try_create_program(&[(gl::VERTEX_SHADER, vertex), (gl::FRAGMENT_SHADER, fragment)], &[]) {
+        Ok(program) => program,
+        Err(err) => {
+            println!("Src: {}\n{}", vertex, fragment);
+            panic!("Shader error: {}", err);
+        },
+    }
+}
+
+/// Compiles and links a program from its stages, returning the log
+/// of the stage that failed.
+///
+/// `attributes` are bound to the given locations before linking so
+/// a reloaded program works with vertex arrays set up for the old one.
+pub fn try_create_program(stages: &[(gl::ShaderType, &str)], attributes: &[(&str, gl::Attribute)]) -> Result<gl::Program, String> {
     let program = gl::Program::new();
 
-    let v = gl::Shader::new(gl::VERTEX_SHADER);
-    v.set_source(vertex);
-    v.compile();
+    for &(ty, src) in stages {
+        let s = gl::Shader::new(ty);
+        s.set_source(src);
+        s.compile();
 
-    if v.get_parameter(gl::COMPILE_STATUS) == 0 {
-        println!("Src: {}", vertex);
-        panic!("Shader error: {}", v.get_info_log());
-    } else {
-        let log = v.get_info_log();
+        let log = s.get_info_log();
         let log = log.trim().trim_matches('\u{0}');
-        if !log.is_empty() {
+        if s.get_parameter(gl::COMPILE_STATUS) == 0 {
+            return Err(log.to_owned());
+        } else if !log.is_empty() {
             println!("{}", log);
         }
+        program.attach_shader(s);
     }
 
-    let f = gl::Shader::new(gl::FRAGMENT_SHADER);
-    f.set_source(fragment);
-    f.compile();
-
-    if f.get_parameter(gl::COMPILE_STATUS) == 0 {
-        println!("Src: {}", fragment);
-        panic!("Shader error: {}", f.get_info_log());
-    } else {
-        let log = f.get_info_log();
-        let log = log.trim().trim_matches('\u{0}');
-        if !log.is_empty() {
-            println!("{}", log);
-        }
+    for &(name, location) in attributes {
+        program.bind_attribute_location(location, name);
     }
-
-    program.attach_shader(v);
-    program.attach_shader(f);
     program.link();
+    if program.get_parameter(gl::LINK_STATUS) == 0 {
+        return Err(program.get_info_log().trim().trim_matches('\u{0}').to_owned());
+    }
     program.use_program();
-    program
+    Ok(program)
+}
+
+/// Replaces the built in shaders with the `<name>.glsl` files in
+/// `dir`, for trying out changes without rebuilding.
+pub fn load_overrides(reg: &mut glsl::Registry, dir: &Path) {
+    for name in reg.names() {
+        let location = dir.join(format!("{}.glsl", name));
+        if !location.is_file() {
+            continue;
+        }
+        match fs::read_to_string(&location) {
+            Ok(source) => reg.replace(&name, &source),
+            Err(err) => error!("Failed to read {}: {}", location.display(), err),
+        }
+    }
 }
@@ -134,6 +134,12 @@ impl UIState {
         state
     }
 
+    /// Recompiles the ui shader, keeping the current program if that
+    /// fails.
+    pub fn reload_shaders(&mut self, greg: &glsl::Registry) -> Result<(), String> {
+        self.shader.reload(greg)
+    }
+
     pub fn tick(&mut self, width: u32, height: u32) {
         {
             let version = self.resources.read().unwrap().version();
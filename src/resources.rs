@@ -20,28 +20,63 @@ use std::io;
 use std::fs;
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::BuildHasherDefault;
 use serde_json;
+use log::{info, warn, error};
 
 use reqwest;
 use zip;
 
 use crate::types::hash::FNVHash;
+use crate::protocol;
+use crate::downloader;
+use crate::watcher::Watcher;
 use crate::ui;
 
 const RESOURCES_VERSION: &str = "1.12.2";
 const VANILLA_CLIENT_URL: &str = "https://launcher.mojang.com/v1/objects/0f275bc1547d01fa5f56ba34bdc87d981ee12daf/client.jar";
+const VANILLA_CLIENT_HASH: &str = "0f275bc1547d01fa5f56ba34bdc87d981ee12daf";
 const ASSET_VERSION: &str = "1.12";
 const ASSET_INDEX_URL: &str = "https://launchermeta.mojang.com/mc/assets/1.12/67e29e024e664064c1f04c728604f83c24cbc218/1.12.json";
+const ASSET_INDEX_HASH: &str = "67e29e024e664064c1f04c728604f83c24cbc218";
+const ASSET_OBJECTS_URL: &str = "http://resources.download.minecraft.net";
+/// How many assets are downloaded at once
+const ASSET_DOWNLOAD_THREADS: usize = 8;
+/// Where resource packs picked by the user are loaded from
+const RESOURCE_PACKS_DIR: &str = "./resourcepacks";
 
 pub trait Pack: Sync + Send {
     fn open(&self, name: &str) -> Option<Box<io::Read>>;
+
+    /// The directory the pack is read from, if it isn't packed into
+    /// a single file.
+    fn dir(&self) -> Option<&path::Path> {
+        None
+    }
+}
+
+/// Local copies of the vanilla files to use instead of downloading
+/// them, for machines without network access.
+#[derive(Clone, Debug, Default)]
+pub struct LocalAssets {
+    /// A vanilla client.jar to load the core assets from
+    pub client_jar: Option<path::PathBuf>,
+    /// An assets directory as laid out by the launcher, holding the
+    /// `indexes` and `objects` directories
+    pub assets_dir: Option<path::PathBuf>,
 }
 
 pub struct Manager {
     packs: Vec<Box<Pack>>,
     version: usize,
+    /// The enabled resource packs, always the last packs in `packs`
+    resource_packs: Vec<String>,
+    /// The pack sent by the server we are connected to, which takes
+    /// priority over every other pack
+    server_pack: Option<Box<Pack>>,
+    /// Watches the directory packs for changes when hot reloading
+    watcher: Option<Watcher>,
 
     vanilla_chan: Option<mpsc::Receiver<bool>>,
     vanilla_assets_chan: Option<mpsc::Receiver<bool>>,
@@ -78,10 +113,13 @@ struct Task {
 unsafe impl Sync for Manager {}
 
 impl Manager {
-    pub fn new() -> (Manager, ManagerUI) {
+    pub fn new(local: &LocalAssets) -> (Manager, ManagerUI) {
         let mut m = Manager {
             packs: Vec::new(),
             version: 0,
+            resource_packs: Vec::new(),
+            server_pack: None,
+            watcher: None,
             vanilla_chan: None,
             vanilla_assets_chan: None,
             vanilla_progress: Arc::new(Mutex::new(Progress {
@@ -89,8 +127,14 @@ impl Manager {
             })),
         };
         m.add_pack(Box::new(InternalPack));
-        m.download_vanilla();
-        m.download_assets();
+        match local.client_jar {
+            Some(ref client_jar) => m.load_local_vanilla(client_jar),
+            None => m.download_vanilla(),
+        }
+        match local.assets_dir {
+            Some(ref assets_dir) => m.load_local_assets(assets_dir),
+            None => m.download_assets(),
+        }
         (m, ManagerUI { progress_ui: vec!{}, num_tasks: 0 })
     }
 
@@ -102,7 +146,7 @@ impl Manager {
 
     pub fn open(&self, plugin: &str, name: &str) -> Option<Box<io::Read>> {
         let path = format!("assets/{}/{}", plugin, name);
-        for pack in self.packs.iter().rev() {
+        for pack in self.server_pack.iter().chain(self.packs.iter().rev()) {
             if let Some(val) = pack.open(&path) {
                 return Some(val);
             }
@@ -113,7 +157,7 @@ impl Manager {
     pub fn open_all(&self, plugin: &str, name: &str) -> Vec<Box<io::Read>> {
         let mut ret = Vec::new();
         let path = format!("assets/{}/{}", plugin, name);
-        for pack in self.packs.iter().rev() {
+        for pack in self.server_pack.iter().chain(self.packs.iter().rev()) {
             if let Some(val) = pack.open(&path) {
                 ret.push(val);
             }
@@ -145,6 +189,10 @@ impl Manager {
             self.vanilla_assets_chan = None;
             self.load_assets();
         }
+        if self.watcher.as_ref().map_or(false, |w| w.changed()) {
+            info!("Resource packs changed, reloading");
+            self.version += 1;
+        }
 
         const UI_HEIGHT: f64 = 32.0;
 
@@ -262,16 +310,134 @@ impl Manager {
         self.version += 1;
     }
 
+    /// Replaces the enabled resource packs with `names`, the names of
+    /// zip files or directories in `resourcepacks/`. Later packs
+    /// override files from earlier ones. Packs that fail to open are
+    /// skipped.
+    pub fn set_resource_packs(&mut self, names: &[String]) {
+        if self.resource_packs[..] == names[..] {
+            return;
+        }
+        let loaded = self.packs.len() - self.resource_packs.len();
+        self.packs.truncate(loaded);
+        self.resource_packs.clear();
+        let root = path::Path::new(RESOURCE_PACKS_DIR);
+        for name in names {
+            let location = root.join(name);
+            let pack: Box<Pack> = if location.is_dir() {
+                Box::new(DirPack { root: location })
+            } else {
+                match ZipPack::new(&location) {
+                    Ok(val) => Box::new(val),
+                    Err(err) => {
+                        warn!("Failed to load resource pack {}: {}", name, err);
+                        continue;
+                    },
+                }
+            };
+            self.packs.push(pack);
+            self.resource_packs.push(name.clone());
+        }
+        self.version += 1;
+        self.update_watcher();
+    }
+
+    /// Reopens the enabled resource packs and reloads everything
+    /// that uses them, picking up changes made to the packs.
+    pub fn reload(&mut self) {
+        let names = ::std::mem::replace(&mut self.resource_packs, vec![]);
+        let loaded = self.packs.len() - names.len();
+        self.packs.truncate(loaded);
+        self.set_resource_packs(&names);
+        self.version += 1;
+    }
+
+    /// Enables reloading the directory packs picked by the user
+    /// whenever a file in them changes.
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        if enabled == self.watcher.is_some() {
+            return;
+        }
+        self.watcher = if enabled {
+            Some(Watcher::new(self.pack_dirs()))
+        } else {
+            None
+        };
+    }
+
+    fn update_watcher(&self) {
+        if let Some(ref watcher) = self.watcher {
+            watcher.set_roots(self.pack_dirs());
+        }
+    }
+
+    /// Returns the directories of the enabled resource packs. The
+    /// vanilla files are never edited so aren't watched.
+    fn pack_dirs(&self) -> Vec<path::PathBuf> {
+        let enabled = self.packs.len() - self.resource_packs.len();
+        self.packs[enabled..].iter()
+            .filter_map(|pack| pack.dir())
+            .map(|dir| dir.to_path_buf())
+            .collect()
+    }
+
+    /// Sets the zip file of the pack the server sent, or removes it
+    /// when `None`. The previous pack is removed even if the new one
+    /// can't be opened.
+    pub fn set_server_pack(&mut self, location: Option<&path::Path>) -> zip::result::ZipResult<()> {
+        let had_pack = self.server_pack.take().is_some();
+        let res = match location {
+            Some(location) => ZipPack::new(location).map(|pack| self.server_pack = Some(Box::new(pack))),
+            None => Ok(()),
+        };
+        if had_pack || self.server_pack.is_some() {
+            self.version += 1;
+        }
+        res
+    }
+
     fn load_vanilla(&mut self) {
         let loc = format!("./resources-{}", RESOURCES_VERSION);
         let location = path::Path::new(&loc);
         self.packs.insert(1, Box::new(DirPack { root: location.to_path_buf() }));
         self.version += 1;
+        self.update_watcher();
     }
 
     fn load_assets(&mut self) {
-        self.packs.insert(1, Box::new(ObjectPack::new()));
-        self.version += 1;
+        let loc = format!("./index/{}.json", ASSET_VERSION);
+        match ObjectPack::new(path::Path::new(&loc), path::Path::new("./objects/")) {
+            Ok(pack) => {
+                self.packs.insert(1, Box::new(pack));
+                self.version += 1;
+            },
+            Err(err) => error!("Failed to load the asset index {}: {}", loc, err),
+        }
+    }
+
+    /// Uses the objects in `assets_dir`, a directory laid out like
+    /// the launcher's, instead of downloading them.
+    fn load_local_assets(&mut self, assets_dir: &path::Path) {
+        let index = assets_dir.join("indexes").join(format!("{}.json", ASSET_VERSION));
+        match ObjectPack::new(&index, &assets_dir.join("objects")) {
+            Ok(pack) => {
+                self.packs.insert(1, Box::new(pack));
+                self.version += 1;
+            },
+            Err(err) => error!("Failed to load the asset index {} from cl_assets_dir: {}", index.display(), err),
+        }
+    }
+
+    /// Uses the vanilla resources straight from `client_jar` instead
+    /// of downloading them.
+    fn load_local_vanilla(&mut self, client_jar: &path::Path) {
+        match ZipPack::new(client_jar) {
+            Ok(pack) => {
+                self.packs.insert(1, Box::new(pack));
+                self.version += 1;
+            },
+            Err(err) => error!("Failed to open {} from cl_assets_jar: {}", client_jar.display(), err),
+        }
     }
 
     fn download_assets(&mut self) {
@@ -279,67 +445,89 @@ impl Manager {
         let location = path::Path::new(&loc).to_owned();
         let progress_info = self.vanilla_progress.clone();
         let (send, recv) = mpsc::channel();
-        if fs::metadata(&location).is_ok(){
+        if downloader::verify(&location, ASSET_INDEX_HASH) {
             self.load_assets();
         } else {
             self.vanilla_assets_chan = Some(recv);
         }
         thread::spawn(move || {
-            let client = reqwest::Client::new();
-            if fs::metadata(&location).is_err(){
-                fs::create_dir_all(location.parent().unwrap()).unwrap();
-                let res = client.get(ASSET_INDEX_URL)
-                                .send()
-                                .unwrap();
-
-                let length = res.headers().get(reqwest::header::CONTENT_LENGTH).unwrap().to_str().unwrap().parse::<u64>().unwrap();
-                Self::add_task(&progress_info, "Downloading Asset Index", &*location.to_string_lossy(), length);
-                {
-                    let mut file = fs::File::create(format!("index-{}.tmp", ASSET_VERSION)).unwrap();
-                    let mut progress = ProgressRead {
-                        read: res,
-                        progress: &progress_info,
-                        task_name: "Downloading Asset Index".into(),
-                        task_file: location.to_string_lossy().into_owned(),
-                    };
-                    io::copy(&mut progress, &mut file).unwrap();
-                }
-                fs::rename(format!("index-{}.tmp", ASSET_VERSION), &location).unwrap();
-                send.send(true).unwrap();
+            if let Err(err) = Self::fetch_assets(&location, &progress_info, &send) {
+                error!("Failed to download the vanilla assets: {}", err);
+                error!("Set cl_assets_dir to a launcher assets directory to start without network access");
             }
-            let file = fs::File::open(&location).unwrap();
-            let index: serde_json::Value = serde_json::from_reader(&file).unwrap();
-            let root_location = path::Path::new("./objects/");
-            let objects = index.get("objects").and_then(|v| v.as_object()).unwrap();
-            Self::add_task(&progress_info, "Downloading Assets", "./objects", objects.len() as u64);
-            for (k, v) in objects {
-                let hash = v.get("hash").and_then(|v| v.as_str()).unwrap();
-                let hash_path = format!("{}/{}", &hash[..2], hash);
-                let location = root_location.join(&hash_path);
-                if fs::metadata(&location).is_err(){
-                    fs::create_dir_all(location.parent().unwrap()).unwrap();
-                    let res = client.get(&format!("http://resources.download.minecraft.net/{}", hash_path))
-                                    .send()
-                                    .unwrap();
-                    let length = v.get("size").and_then(|v| v.as_u64()).unwrap();
-                    Self::add_task(&progress_info, "Downloading Asset", k, length);
-                    let mut tmp_file = location.to_owned();
-                    tmp_file.set_file_name(format!("{}.tmp", hash));
-                    {
-                        let mut file = fs::File::create(&tmp_file).unwrap();
-                        let mut progress = ProgressRead {
-                            read: res,
-                            progress: &progress_info,
-                            task_name: "Downloading Asset".into(),
-                            task_file: k.to_owned(),
-                        };
-                        io::copy(&mut progress, &mut file).unwrap();
-                    }
-                    fs::rename(&tmp_file, &location).unwrap();
+        });
+    }
+
+    /// Downloads the asset index to `location`, unless it is already
+    /// there, followed by any objects that are missing or corrupt.
+    /// `send` is told once the index has been downloaded.
+    fn fetch_assets(location: &path::Path, progress_info: &Arc<Mutex<Progress>>, send: &mpsc::Sender<bool>) -> Result<(), protocol::Error> {
+        let downloader = downloader::Downloader::new(reqwest::Client::new());
+        let index = downloader::Download {
+            name: location.to_string_lossy().into_owned(),
+            url: ASSET_INDEX_URL.to_owned(),
+            location: location.to_owned(),
+            hash: ASSET_INDEX_HASH.to_owned(),
+            size: 0,
+        };
+        downloader.fetch(&index, &Self::download_listener(progress_info, "Downloading Asset Index"))?;
+        let _ = send.send(true);
+
+        let file = fs::File::open(location)?;
+        let index: serde_json::Value = serde_json::from_reader(&file)?;
+        let root_location = path::Path::new("./objects/");
+        let objects = index.get("objects").and_then(|v| v.as_object())
+            .ok_or_else(|| protocol::Error::Err("Asset index is missing its objects".to_owned()))?;
+        // Assets with the same contents share an object, which must
+        // only be downloaded once
+        let mut seen = HashSet::new();
+        let downloads: Vec<_> = objects.iter()
+            .filter_map(|(k, v)| {
+                let hash = v.get("hash").and_then(|v| v.as_str()).filter(|v| v.len() > 2)?;
+                if !seen.insert(hash) {
+                    return None;
                 }
-                Self::add_task_progress(&progress_info, "Downloading Assets", "./objects", 1);
+                let hash_path = format!("{}/{}", &hash[..2], hash);
+                Some(downloader::Download {
+                    name: k.to_owned(),
+                    url: format!("{}/{}", ASSET_OBJECTS_URL, hash_path),
+                    location: root_location.join(&hash_path),
+                    hash: hash.to_owned(),
+                    size: v.get("size").and_then(|v| v.as_u64()).unwrap_or(0),
+                })
+            })
+            .collect();
+        Self::add_task(progress_info, "Downloading Assets", "./objects", downloads.len() as u64);
+
+        let asset_listener = Self::download_listener(progress_info, "Downloading Asset");
+        let progress = progress_info.clone();
+        let listener: downloader::Listener = Arc::new(move |event| {
+            asset_listener(event);
+            if let downloader::Event::Finished(_) = event {
+                Self::add_task_progress(&progress, "Downloading Assets", "./objects", 1);
             }
         });
+        let failed = downloader.fetch_all(downloads, ASSET_DOWNLOAD_THREADS, listener);
+        if !failed.is_empty() {
+            return Err(protocol::Error::Err(format!("{} assets couldn't be downloaded", failed.len())));
+        }
+        Ok(())
+    }
+
+    /// Returns a listener which shows the progress of each download
+    /// that has to be fetched as a `task_name` task.
+    fn download_listener(progress: &Arc<Mutex<Progress>>, task_name: &'static str) -> downloader::Listener {
+        let progress = progress.clone();
+        Arc::new(move |event| match event {
+            downloader::Event::Started(download, offset) => {
+                Self::add_task(&progress, task_name, &download.name, download.size);
+                Self::add_task_progress(&progress, task_name, &download.name, offset);
+            },
+            downloader::Event::Read(download, size) => Self::add_task_progress(&progress, task_name, &download.name, size),
+            // Completes the task, even if the download failed or the
+            // size wasn't known
+            downloader::Event::Finished(download) => Self::complete_task(&progress, task_name, &download.name),
+        })
     }
 
     fn download_vanilla(&mut self) {
@@ -354,52 +542,56 @@ impl Manager {
 
         let progress_info = self.vanilla_progress.clone();
         thread::spawn(move || {
-            let client = reqwest::Client::new();
-            let res = client.get(VANILLA_CLIENT_URL)
-                            .send()
-                            .unwrap();
-            let mut file = fs::File::create(format!("{}.tmp", RESOURCES_VERSION)).unwrap();
-
-            let length = res.headers().get(reqwest::header::CONTENT_LENGTH).unwrap().to_str().unwrap().parse::<u64>().unwrap();
-            let task_file = format!("./resources-{}", RESOURCES_VERSION);
-            Self::add_task(&progress_info, "Downloading Core Assets", &task_file, length);
-            {
-                let mut progress = ProgressRead {
-                    read: res,
-                    progress: &progress_info,
-                    task_name: "Downloading Core Assets".into(),
-                    task_file,
-                };
-                io::copy(&mut progress, &mut file).unwrap();
+            match Self::fetch_vanilla(&progress_info) {
+                Ok(()) => {
+                    let _ = send.send(true);
+                },
+                Err(err) => {
+                    error!("Failed to download the vanilla resources from {}: {}", VANILLA_CLIENT_URL, err);
+                    error!("Set cl_assets_jar to a {} client.jar to start without network access", RESOURCES_VERSION);
+                },
             }
+        });
+    }
 
-            // Copy the resources from the zip
-            let file = fs::File::open(format!("{}.tmp", RESOURCES_VERSION)).unwrap();
-            let mut zip = zip::ZipArchive::new(file).unwrap();
-
-            let task_file = format!("./resources-{}", RESOURCES_VERSION);
-            Self::add_task(&progress_info, "Unpacking Core Assets", &task_file, zip.len() as u64);
-
-            let loc = format!("./resources-{}", RESOURCES_VERSION);
-            let location = path::Path::new(&loc);
-            let count = zip.len();
-            for i in 0..count {
-                Self::add_task_progress(&progress_info, "Unpacking Core Assets", &task_file, 1);
-                let mut file = zip.by_index(i).unwrap();
-                if !file.name().starts_with("assets/") {
-                    continue;
-                }
-                let path = location.join(file.name());
-                fs::create_dir_all(path.parent().unwrap()).unwrap();
-                let mut out = fs::File::create(path).unwrap();
-                io::copy(&mut file, &mut out).unwrap();
-            }
+    /// Downloads the vanilla client and unpacks its assets into
+    /// `resources-<version>`.
+    fn fetch_vanilla(progress_info: &Arc<Mutex<Progress>>) -> Result<(), protocol::Error> {
+        let downloader = downloader::Downloader::new(reqwest::Client::new());
+        let client_jar = downloader::Download {
+            name: format!("./resources-{}", RESOURCES_VERSION),
+            url: VANILLA_CLIENT_URL.to_owned(),
+            location: path::PathBuf::from(format!("./{}.jar", RESOURCES_VERSION)),
+            hash: VANILLA_CLIENT_HASH.to_owned(),
+            size: 0,
+        };
+        downloader.fetch(&client_jar, &Self::download_listener(progress_info, "Downloading Core Assets"))?;
 
-            fs::File::create(location.join("steven.assets")).unwrap(); // Marker file
-            send.send(true).unwrap();
+        // Copy the resources from the zip
+        let file = fs::File::open(&client_jar.location)?;
+        let mut zip = zip::ZipArchive::new(file).map_err(zip_error)?;
 
-            fs::remove_file(format!("{}.tmp", RESOURCES_VERSION)).unwrap();
-        });
+        let task_file = format!("./resources-{}", RESOURCES_VERSION);
+        Self::add_task(progress_info, "Unpacking Core Assets", &task_file, zip.len() as u64);
+
+        let loc = format!("./resources-{}", RESOURCES_VERSION);
+        let location = path::Path::new(&loc);
+        let count = zip.len();
+        for i in 0..count {
+            Self::add_task_progress(progress_info, "Unpacking Core Assets", &task_file, 1);
+            let mut file = zip.by_index(i).map_err(zip_error)?;
+            if !file.name().starts_with("assets/") {
+                continue;
+            }
+            let path = location.join(file.name());
+            fs::create_dir_all(path.parent().unwrap())?;
+            let mut out = fs::File::create(path)?;
+            io::copy(&mut file, &mut out)?;
+        }
+
+        fs::File::create(location.join("steven.assets"))?; // Marker file
+        fs::remove_file(&client_jar.location)?;
+        Ok(())
     }
 
     fn add_task(progress: &Arc<Mutex<Progress>>, name: &str, file: &str, length: u64) {
@@ -420,6 +612,19 @@ impl Manager {
             task.progress += prog as u64;
         }
     }
+
+    fn complete_task(progress: &Arc<Mutex<Progress>>, name: &str, file: &str) {
+        let mut progress = progress.lock().unwrap();
+        for task in progress.tasks.iter_mut()
+            .filter(|v| v.task_file == file)
+            .filter(|v| v.task_name == name) {
+            task.progress = task.progress.max(task.total.max(1));
+        }
+    }
+}
+
+fn zip_error(err: zip::result::ZipError) -> protocol::Error {
+    protocol::Error::Err(format!("Invalid zip: {}", err))
 }
 
 struct DirPack {
@@ -433,6 +638,37 @@ impl Pack for DirPack {
             Err(_) => None,
         }
     }
+
+    fn dir(&self) -> Option<&path::Path> {
+        Some(&self.root)
+    }
+}
+
+/// A resource pack loaded straight from its zip file.
+struct ZipPack {
+    // Reading an entry needs mutable access to the archive
+    archive: Mutex<zip::ZipArchive<fs::File>>,
+}
+
+impl ZipPack {
+    fn new(location: &path::Path) -> zip::result::ZipResult<ZipPack> {
+        let file = fs::File::open(location)?;
+        Ok(ZipPack {
+            archive: Mutex::new(zip::ZipArchive::new(file)?),
+        })
+    }
+}
+
+impl Pack for ZipPack {
+    fn open(&self, name: &str) -> Option<Box<io::Read>> {
+        use std::io::Read;
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = archive.by_name(name).ok()?;
+        // Entries borrow the archive so they can't outlive the lock
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data).ok()?;
+        Some(Box::new(io::Cursor::new(data)))
+    }
 }
 
 struct InternalPack;
@@ -447,23 +683,28 @@ impl Pack for InternalPack {
 }
 
 struct ObjectPack {
+    root: path::PathBuf,
     objects: HashMap<String, String, BuildHasherDefault<FNVHash>>,
 }
 
 impl ObjectPack {
-    fn new() -> ObjectPack {
-        let loc = format!("./index/{}.json", ASSET_VERSION);
-        let location = path::Path::new(&loc);
-        let file = fs::File::open(&location).unwrap();
-        let index: serde_json::Value = serde_json::from_reader(&file).unwrap();
-        let objects = index.get("objects").and_then(|v| v.as_object()).unwrap();
+    /// Loads the asset index at `index`, whose objects are stored by
+    /// hash under `root`.
+    fn new(index: &path::Path, root: &path::Path) -> Result<ObjectPack, protocol::Error> {
+        let file = fs::File::open(index)?;
+        let index: serde_json::Value = serde_json::from_reader(&file)?;
+        let objects = index.get("objects").and_then(|v| v.as_object())
+            .ok_or_else(|| protocol::Error::Err("Asset index is missing its objects".to_owned()))?;
         let mut hash_objs = HashMap::with_hasher(BuildHasherDefault::default());
         for (k, v) in objects {
-            hash_objs.insert(k.clone(), v.get("hash").and_then(|v| v.as_str()).unwrap().to_owned());
+            if let Some(hash) = v.get("hash").and_then(|v| v.as_str()) {
+                hash_objs.insert(k.clone(), hash.to_owned());
+            }
         }
-        ObjectPack {
+        Ok(ObjectPack {
+            root: root.to_path_buf(),
             objects: hash_objs,
-        }
+        })
     }
 }
 
@@ -474,9 +715,8 @@ impl Pack for ObjectPack {
         }
         let name = &name["assets/".len()..];
         if let Some(hash) = self.objects.get(name) {
-            let root_location = path::Path::new("./objects/");
             let hash_path = format!("{}/{}", &hash[..2], hash);
-            let location = root_location.join(&hash_path);
+            let location = self.root.join(&hash_path);
             match fs::File::open(location) {
                 Ok(val) => Some(Box::new(val)),
                 Err(_) => None,
@@ -487,17 +727,70 @@ impl Pack for ObjectPack {
     }
 }
 
-struct ProgressRead<'a, T> {
-    read: T,
-    progress: &'a Arc<Mutex<Progress>>,
-    task_name: String,
-    task_file: String,
-}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn zip_packs() {
+        let location = std::env::temp_dir().join(format!("steven-zip-pack-{}.zip", std::process::id()));
+        {
+            let mut zip = zip::ZipWriter::new(fs::File::create(&location).unwrap());
+            zip.start_file("pack.mcmeta", zip::write::FileOptions::default()).unwrap();
+            zip.write_all(br#"{"pack":{"pack_format":3,"description":"Test"}}"#).unwrap();
+            zip.start_file("assets/minecraft/textures/blocks/stone.png", zip::write::FileOptions::default()).unwrap();
+            zip.write_all(b"stone").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let pack = ZipPack::new(&location).unwrap();
+        let mut data = vec![];
+        pack.open("assets/minecraft/textures/blocks/stone.png").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"stone");
+        // Can be read more than once
+        assert!(pack.open("assets/minecraft/textures/blocks/stone.png").is_some());
+        assert!(pack.open("assets/minecraft/textures/blocks/dirt.png").is_none());
+        drop(pack);
+        fs::remove_file(&location).unwrap();
+
+        assert!(ZipPack::new(&location).is_err());
+    }
 
-impl <'a, T: io::Read> io::Read for ProgressRead<'a, T> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let size = self.read.read(buf)?;
-        Manager::add_task_progress(self.progress, &self.task_name, &self.task_file, size as u64);
-        Ok(size)
+    #[test]
+    fn local_assets() {
+        let root = std::env::temp_dir().join(format!("steven-local-assets-{}", std::process::id()));
+        let client_jar = root.join("client.jar");
+        let assets_dir = root.join("assets");
+        fs::create_dir_all(assets_dir.join("indexes")).unwrap();
+        fs::create_dir_all(assets_dir.join("objects/ab")).unwrap();
+        {
+            let mut zip = zip::ZipWriter::new(fs::File::create(&client_jar).unwrap());
+            zip.start_file("assets/minecraft/textures/blocks/stone.png", zip::write::FileOptions::default()).unwrap();
+            zip.write_all(b"stone").unwrap();
+            zip.finish().unwrap();
+        }
+        let hash = format!("ab{}", "0".repeat(38));
+        fs::write(
+            assets_dir.join("indexes").join(format!("{}.json", ASSET_VERSION)),
+            format!(r#"{{"objects":{{"minecraft/sounds/step/stone1.ogg":{{"hash":"{}","size":4}}}}}}"#, hash),
+        ).unwrap();
+        fs::write(assets_dir.join("objects/ab").join(&hash), b"step").unwrap();
+
+        let (manager, _) = Manager::new(&LocalAssets {
+            client_jar: Some(client_jar),
+            assets_dir: Some(assets_dir),
+        });
+        // Both are loaded straight away, without downloading anything
+        assert!(manager.vanilla_chan.is_none());
+        assert!(manager.vanilla_assets_chan.is_none());
+        let mut data = vec![];
+        manager.open("minecraft", "textures/blocks/stone.png").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"stone");
+        data.clear();
+        manager.open("minecraft", "sounds/step/stone1.ogg").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"step");
+        drop(manager);
+        fs::remove_dir_all(&root).unwrap();
     }
 }
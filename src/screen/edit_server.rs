@@ -14,15 +14,33 @@
 
 use std::fs;
 use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
 
 use crate::ui;
 use crate::render;
+use crate::accounts;
+use crate::server::resource_pack;
+use crate::protocol;
 
 use serde_json::{self, Value};
 
 pub struct EditServerEntry {
     elements: Option<UIElements>,
-    entry_info: Option<(usize, String, String)>,
+    entry_info: Option<EntryInfo>,
+}
+
+/// The saved settings of the server being edited
+pub struct EntryInfo {
+    /// The position of the server in `servers.json`
+    pub index: usize,
+    pub name: String,
+    pub address: String,
+    pub protocol_version: Option<i32>,
+    pub proxy: String,
+    /// The id of the account to join with, the current one if `None`
+    pub account: Option<String>,
+    pub resource_packs: resource_pack::Policy,
 }
 
 struct UIElements {
@@ -30,19 +48,38 @@ struct UIElements {
 
     _name: ui::TextBoxRef,
     _address: ui::TextBoxRef,
+    _protocol_version: ui::TextBoxRef,
+    _proxy: ui::TextBoxRef,
+    _account: ui::ButtonRef,
+    _resource_packs: ui::ButtonRef,
     _done: ui::ButtonRef,
     _cancel: ui::ButtonRef,
 }
 
 impl EditServerEntry {
-    pub fn new(entry_info: Option<(usize, String, String)>) -> EditServerEntry {
+    pub fn new(entry_info: Option<EntryInfo>) -> EditServerEntry {
         EditServerEntry {
             elements: None,
             entry_info,
         }
     }
 
-    fn save_servers(index: Option<usize>, name: &str, address: &str) {
+    /// Parses the protocol version typed in, which may be left blank
+    /// but otherwise must be one a packet table exists for.
+    fn parse_protocol_version(input: &str) -> Result<Option<i32>, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        let version = input.parse::<i32>()
+            .map_err(|_| format!("Protocol version {} isn't a number", input))?;
+        match protocol::versions::best_protocol_version(version) {
+            Some(_) => Ok(Some(version)),
+            None => Err(format!("Protocol version {} isn't supported", version)),
+        }
+    }
+
+    fn save_servers(index: Option<usize>, name: &str, address: &str, protocol_version: Option<i32>, proxy: &str, account: Option<&str>, resource_packs: resource_pack::Policy) {
         let mut servers_info = match fs::File::open("servers.json") {
             Ok(val) => serde_json::from_reader(val).unwrap(),
             Err(_) => {
@@ -56,6 +93,18 @@ impl EditServerEntry {
             let mut entry = BTreeMap::default();
             entry.insert("name".to_owned(), Value::String(name.to_owned()));
             entry.insert("address".to_owned(), Value::String(address.to_owned()));
+            if let Some(version) = protocol_version {
+                entry.insert("protocol_version".to_owned(), Value::from(version));
+            }
+            if !proxy.trim().is_empty() {
+                entry.insert("proxy".to_owned(), Value::String(proxy.trim().to_owned()));
+            }
+            if let Some(account) = account {
+                entry.insert("account".to_owned(), Value::String(account.to_owned()));
+            }
+            if resource_packs != resource_pack::Policy::Prompt {
+                entry.insert("resource_packs".to_owned(), Value::String(resource_packs.as_str().to_owned()));
+            }
             Value::Object(entry.into_iter().collect())
         };
 
@@ -77,6 +126,22 @@ impl EditServerEntry {
         serde_json::to_writer_pretty(&mut out, &servers_info).unwrap();
     }
 
+    fn account_label(account: Option<&accounts::Account>) -> String {
+        match account {
+            Some(account) if account.kind() == accounts::Kind::Offline => format!("Account: {} (offline)", account.profile.username),
+            Some(account) => format!("Account: {}", account.profile.username),
+            None => "Account: Current".to_owned(),
+        }
+    }
+
+    fn resource_packs_label(policy: resource_pack::Policy) -> String {
+        let policy = match policy {
+            resource_pack::Policy::Prompt => "Prompt",
+            resource_pack::Policy::Enabled => "Enabled",
+            resource_pack::Policy::Disabled => "Disabled",
+        };
+        format!("Server resource packs: {}", policy)
+    }
 }
 
 impl super::Screen for EditServerEntry {
@@ -85,8 +150,8 @@ impl super::Screen for EditServerEntry {
 
         // Name
         let server_name = ui::TextBoxBuilder::new()
-            .input(self.entry_info.as_ref().map_or("", |v| &v.1))
-            .position(0.0, -20.0)
+            .input(self.entry_info.as_ref().map_or("", |v| &v.name))
+            .position(0.0, -100.0)
             .size(400.0, 40.0)
             .alignment(ui::VAttach::Middle, ui::HAttach::Center)
             .create(ui_container);
@@ -98,8 +163,8 @@ impl super::Screen for EditServerEntry {
 
         // Address
         let server_address = ui::TextBoxBuilder::new()
-            .input(self.entry_info.as_ref().map_or("", |v| &v.2))
-            .position(0.0, 40.0)
+            .input(self.entry_info.as_ref().map_or("", |v| &v.address))
+            .position(0.0, -42.0)
             .size(400.0, 40.0)
             .alignment(ui::VAttach::Middle, ui::HAttach::Center)
             .create(ui_container);
@@ -109,9 +174,97 @@ impl super::Screen for EditServerEntry {
             .position(0.0, -18.0)
             .attach(&mut *server_address.borrow_mut());
 
+        // Protocol version
+        let protocol_version = ui::TextBoxBuilder::new()
+            .input(&self.entry_info.as_ref().and_then(|v| v.protocol_version).map_or_else(String::new, |v| v.to_string()))
+            .position(0.0, 16.0)
+            .size(400.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        ui::TextBox::make_focusable(&protocol_version, ui_container);
+        let protocol_version_label = ui::TextBuilder::new()
+            .text("Protocol version (blank for automatic)")
+            .position(0.0, -18.0)
+            .attach(&mut *protocol_version.borrow_mut());
+
+        // Proxy
+        let proxy = ui::TextBoxBuilder::new()
+            .input(self.entry_info.as_ref().map_or("", |v| &v.proxy))
+            .position(0.0, 74.0)
+            .size(400.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        ui::TextBox::make_focusable(&proxy, ui_container);
+        ui::TextBuilder::new()
+            .text("Proxy (blank to use cl_proxy)")
+            .position(0.0, -18.0)
+            .attach(&mut *proxy.borrow_mut());
+
+        // Account, clicking cycles through the saved accounts
+        let saved = Rc::new(accounts::Accounts::load());
+        let account_id = Rc::new(RefCell::new(self.entry_info.as_ref().and_then(|v| v.account.clone())));
+        let account = ui::ButtonBuilder::new()
+            .position(0.0, 119.0)
+            .size(400.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        {
+            let mut account = account.borrow_mut();
+            // An account that has since been removed is kept until
+            // another is picked.
+            let label = match *account_id.borrow() {
+                Some(ref id) => saved.find(id).map_or_else(|| "Account: Removed".to_owned(), |a| Self::account_label(Some(a))),
+                None => Self::account_label(None),
+            };
+            let txt = ui::TextBuilder::new()
+                .text(label)
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *account);
+            account.add_text(txt.clone());
+            let account_id = account_id.clone();
+            let saved = saved.clone();
+            account.add_click_func(move |_, _| {
+                let list = saved.accounts();
+                let next = {
+                    let current = account_id.borrow();
+                    let pos = current.as_ref().and_then(|id| list.iter().position(|a| &a.profile.id == id));
+                    match pos {
+                        Some(pos) => list.get(pos + 1),
+                        None => list.first(),
+                    }
+                };
+                *account_id.borrow_mut() = next.map(|a| a.profile.id.clone());
+                txt.borrow_mut().text = Self::account_label(next);
+                true
+            });
+        }
+
+        // What to do with the server's resource pack, clicking cycles
+        // through the policies
+        let resource_packs_policy = Rc::new(Cell::new(self.entry_info.as_ref().map_or(resource_pack::Policy::Prompt, |v| v.resource_packs)));
+        let resource_packs = ui::ButtonBuilder::new()
+            .position(0.0, 163.0)
+            .size(400.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        {
+            let mut resource_packs = resource_packs.borrow_mut();
+            let txt = ui::TextBuilder::new()
+                .text(Self::resource_packs_label(resource_packs_policy.get()))
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *resource_packs);
+            resource_packs.add_text(txt.clone());
+            let resource_packs_policy = resource_packs_policy.clone();
+            resource_packs.add_click_func(move |_, _| {
+                resource_packs_policy.set(resource_packs_policy.get().next());
+                txt.borrow_mut().text = Self::resource_packs_label(resource_packs_policy.get());
+                true
+            });
+        }
+
         // Done
         let done = ui::ButtonBuilder::new()
-            .position(110.0, 100.0)
+            .position(110.0, 207.0)
             .size(200.0, 40.0)
             .alignment(ui::VAttach::Middle, ui::HAttach::Center)
             .create(ui_container);
@@ -122,14 +275,29 @@ impl super::Screen for EditServerEntry {
                 .alignment(ui::VAttach::Middle, ui::HAttach::Center)
                 .attach(&mut *done);
             done.add_text(txt);
-            let index = self.entry_info.as_ref().map(|v| v.0);
+            let index = self.entry_info.as_ref().map(|v| v.index);
             let server_name = server_name.clone();
             let server_address = server_address.clone();
+            let protocol_version = protocol_version.clone();
+            let proxy = proxy.clone();
+            let account_id = account_id.clone();
+            let resource_packs_policy = resource_packs_policy.clone();
             done.add_click_func(move |_, game| {
+                let version = match Self::parse_protocol_version(&protocol_version.borrow().input) {
+                    Ok(version) => version,
+                    Err(err) => {
+                        protocol_version_label.borrow_mut().text = err;
+                        return true;
+                    },
+                };
                 Self::save_servers(
                     index,
                     &server_name.borrow().input,
                     &server_address.borrow().input,
+                    version,
+                    &proxy.borrow().input,
+                    account_id.borrow().as_ref().map(|v| &v[..]),
+                    resource_packs_policy.get(),
                 );
                 game.screen_sys.replace_screen(Box::new(super::ServerList::new(None)));
                 true
@@ -138,7 +306,7 @@ impl super::Screen for EditServerEntry {
 
         // Cancel
         let cancel = ui::ButtonBuilder::new()
-            .position(-110.0, 100.0)
+            .position(-110.0, 207.0)
             .size(200.0, 40.0)
             .alignment(ui::VAttach::Middle, ui::HAttach::Center)
             .create(ui_container);
@@ -159,6 +327,10 @@ impl super::Screen for EditServerEntry {
             logo,
             _name: server_name,
             _address: server_address,
+            _protocol_version: protocol_version,
+            _proxy: proxy,
+            _account: account,
+            _resource_packs: resource_packs,
             _done: done,
             _cancel: cancel,
         });
@@ -183,3 +355,19 @@ impl super::Screen for EditServerEntry {
         true
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn protocol_versions() {
+        assert_eq!(EditServerEntry::parse_protocol_version(" "), Ok(None));
+        assert_eq!(EditServerEntry::parse_protocol_version("340"), Ok(Some(340)));
+        // Kept as typed, it is mapped onto a supported version when
+        // connecting
+        assert_eq!(EditServerEntry::parse_protocol_version("339"), Ok(Some(339)));
+        assert!(EditServerEntry::parse_protocol_version("1.12").is_err());
+        assert!(EditServerEntry::parse_protocol_version("78").is_err());
+    }
+}
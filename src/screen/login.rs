@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread;
@@ -25,10 +25,13 @@ use crate::console;
 use crate::protocol;
 use crate::protocol::mojang;
 use crate::auth;
+use crate::accounts;
 
 pub struct Login {
     elements: Option<UIElements>,
     vars: Rc<console::Vars>,
+    /// Whether to log straight back in to the current account
+    auto_login: bool,
 }
 
 struct UIElements {
@@ -36,11 +39,17 @@ struct UIElements {
 
     login_btn: ui::ButtonRef,
     login_btn_text: ui::TextRef,
+    _offline_btn: ui::ButtonRef,
     login_error: ui::TextRef,
     username_txt: ui::TextBoxRef,
     password_txt: ui::TextBoxRef,
     _disclaimer: ui::TextRef,
+    _accounts_title: ui::TextRef,
+    _accounts: Vec<ui::ButtonRef>,
     try_login: Rc<Cell<bool>>,
+    try_offline: Rc<Cell<bool>>,
+    /// The saved account picked from the list, by id
+    try_account: Rc<RefCell<Option<String>>>,
     refresh: bool,
     login_res: Option<mpsc::Receiver<Result<mojang::Profile, protocol::Error>>>,
 
@@ -50,7 +59,24 @@ struct UIElements {
 
 impl Login {
     pub fn new(vars: Rc<console::Vars>) -> Login {
-        Login { elements: None, vars: vars }
+        Login { elements: None, vars: vars, auto_login: true }
+    }
+
+    /// Creates the login screen without logging back in to the
+    /// current account, so that another can be picked.
+    pub fn accounts(vars: Rc<console::Vars>) -> Login {
+        Login { elements: None, vars: vars, auto_login: false }
+    }
+
+    /// Makes `profile` the current account and remembers it.
+    fn save_profile(vars: &console::Vars, profile: &mojang::Profile) {
+        vars.set(auth::CL_USERNAME, profile.username.clone());
+        vars.set(auth::CL_UUID, profile.id.clone());
+        vars.set(auth::AUTH_TOKEN, profile.access_token.clone());
+        vars.set(auth::AUTH_OFFLINE, profile.offline);
+        let mut saved = accounts::Accounts::load();
+        saved.insert(profile.clone());
+        saved.save();
     }
 }
 
@@ -82,10 +108,31 @@ impl super::Screen for Login {
         }
 
 
+        // Offline
+        let try_offline = Rc::new(Cell::new(false));
+        let offline_btn = ui::ButtonBuilder::new()
+            .position(0.0, 150.0)
+            .size(400.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        {
+            let mut btn = offline_btn.borrow_mut();
+            let txt = ui::TextBuilder::new()
+                .text("Play Offline")
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *btn);
+            btn.add_text(txt);
+            let to = try_offline.clone();
+            btn.add_click_func(move |_, _| {
+                to.set(true);
+                true
+            });
+        }
+
         // Login Error
         let login_error = ui::TextBuilder::new()
             .text("")
-            .position(0.0, 150.0)
+            .position(0.0, 200.0)
             .colour((255, 50, 50, 255))
             .alignment(ui::VAttach::Middle, ui::HAttach::Center)
             .create(ui_container);
@@ -127,12 +174,75 @@ impl super::Screen for Login {
             .alignment(ui::VAttach::Bottom, ui::HAttach::Right)
             .create(ui_container);
 
+        // Saved accounts
+        let accounts_title = ui::TextBuilder::new()
+            .text("Accounts:")
+            .position(10.0, 10.0)
+            .alignment(ui::VAttach::Top, ui::HAttach::Left)
+            .create(ui_container);
+        let try_account = Rc::new(RefCell::new(None));
+        let current = self.vars.get(auth::CL_UUID).clone();
+        let mut account_btns = vec![];
+        for (i, account) in accounts::Accounts::load().accounts().iter().enumerate() {
+            let btn = ui::ButtonBuilder::new()
+                .position(10.0, 30.0 + i as f64 * 45.0)
+                .size(250.0, 40.0)
+                .alignment(ui::VAttach::Top, ui::HAttach::Left)
+                .create(ui_container);
+            {
+                let mut btn = btn.borrow_mut();
+                let name = match account.kind() {
+                    accounts::Kind::Mojang => account.profile.username.clone(),
+                    accounts::Kind::Offline => format!("{} (offline)", account.profile.username),
+                };
+                let txt = ui::TextBuilder::new()
+                    .text(name)
+                    .position(10.0, 0.0)
+                    .colour(if account.profile.id == current { (255, 255, 85, 255) } else { (255, 255, 255, 255) })
+                    .alignment(ui::VAttach::Middle, ui::HAttach::Left)
+                    .attach(&mut *btn);
+                btn.add_text(txt);
+                let ta = try_account.clone();
+                let id = account.profile.id.clone();
+                btn.add_click_func(move |_, _| {
+                    *ta.borrow_mut() = Some(id.clone());
+                    true
+                });
+            }
+
+            // Forget the account
+            let remove = ui::ButtonBuilder::new()
+                .position(7.5, 0.0)
+                .size(25.0, 25.0)
+                .alignment(ui::VAttach::Middle, ui::HAttach::Right)
+                .attach(&mut *btn.borrow_mut());
+            {
+                let mut remove = remove.borrow_mut();
+                let txt = ui::TextBuilder::new()
+                    .text("X")
+                    .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                    .attach(&mut *remove);
+                remove.add_text(txt);
+                let id = account.profile.id.clone();
+                remove.add_click_func(move |_, game| {
+                    let mut saved = accounts::Accounts::load();
+                    saved.remove(&id);
+                    saved.save();
+                    game.screen_sys.replace_screen(Box::new(Login::accounts(game.vars.clone())));
+                    true
+                });
+            }
+            account_btns.push(btn);
+        }
+
         let profile = mojang::Profile {
             username: self.vars.get(auth::CL_USERNAME).clone(),
             id: self.vars.get(auth::CL_UUID).clone(),
             access_token: self.vars.get(auth::AUTH_TOKEN).clone(),
+            offline: *self.vars.get(auth::AUTH_OFFLINE),
+            endpoints: auth::endpoints(&self.vars),
         };
-        let refresh = profile.is_complete();
+        let refresh = self.auto_login && profile.is_complete();
         try_login.set(refresh);
 
         self.elements = Some(UIElements {
@@ -140,12 +250,17 @@ impl super::Screen for Login {
             profile,
             login_btn,
             login_btn_text,
+            _offline_btn: offline_btn,
             login_error,
             try_login,
+            try_offline,
             refresh,
             login_res: None,
 
             _disclaimer: disclaimer,
+            _accounts_title: accounts_title,
+            _accounts: account_btns,
+            try_account,
 
             username_txt,
             password_txt,
@@ -162,6 +277,41 @@ impl super::Screen for Login {
             _ui_container: &mut ui::Container) -> Option<Box<super::Screen>> {
         let elements = self.elements.as_mut().unwrap();
 
+        let picked = elements.try_account.borrow_mut().take();
+        if let Some(id) = picked {
+            if elements.login_res.is_none() {
+                match accounts::Accounts::load().find(&id) {
+                    Some(account) if account.kind() == accounts::Kind::Offline => {
+                        Self::save_profile(&self.vars, &account.profile);
+                        return Some(Box::new(super::ServerList::new(None)));
+                    },
+                    Some(account) => {
+                        // Make sure the token is still valid first
+                        elements.profile = account.profile.clone();
+                        elements.refresh = true;
+                        elements.try_login.set(true);
+                    },
+                    None => {},
+                }
+            }
+        }
+
+        if elements.try_offline.get() && elements.login_res.is_none() {
+            elements.try_offline.set(false);
+            // The username is used as is, no need to ask the authserver
+            let username = elements.username_txt.borrow().input.clone();
+            match mojang::Profile::offline(&username) {
+                Ok(val) => {
+                    Self::save_profile(&self.vars, &val);
+                    elements.profile = val;
+                    return Some(Box::new(super::ServerList::new(None)));
+                },
+                Err(err) => {
+                    elements.login_error.borrow_mut().text = format!("{}", err);
+                },
+            }
+        }
+
         if elements.try_login.get() && elements.login_res.is_none() {
             elements.try_login.set(false);
             let (tx, rx) = mpsc::channel();
@@ -181,11 +331,12 @@ impl super::Screen for Login {
             let password = elements.password_txt.borrow().input.clone();
             let refresh = elements.refresh;
             let profile = elements.profile.clone();
+            let endpoints = auth::endpoints(&self.vars);
             thread::spawn(move || {
                 if refresh {
                     tx.send(profile.refresh(&client_token)).unwrap();
                 } else {
-                    tx.send(mojang::Profile::login(&username, &password, &client_token)).unwrap();
+                    tx.send(mojang::Profile::login(&endpoints, &username, &password, &client_token)).unwrap();
                 }
             });
         }
@@ -197,14 +348,14 @@ impl super::Screen for Login {
                 elements.login_btn_text.borrow_mut().text = "Login".into();
                 match res {
                     Ok(val) => {
-                        self.vars.set(auth::CL_USERNAME, val.username.clone());
-                        self.vars.set(auth::CL_UUID, val.id.clone());
-                        self.vars.set(auth::AUTH_TOKEN, val.access_token.clone());
+                        Self::save_profile(&self.vars, &val);
                         elements.profile = val;
                         return Some(Box::new(super::ServerList::new(None)));
                     },
                     Err(err) => {
                         elements.login_error.borrow_mut().text = format!("{}", err);
+                        // Use the credentials typed in next time
+                        elements.refresh = false;
                     },
                 }
             }
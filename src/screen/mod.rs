@@ -19,6 +19,7 @@ pub use self::login::*;
 
 pub mod connecting;
 pub mod edit_server;
+pub mod resource_pack_prompt;
 
 pub mod settings_menu;
 pub use self::settings_menu::{SettingsMenu, VideoSettingsMenu, AudioSettingsMenu};
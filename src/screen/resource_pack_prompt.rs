@@ -0,0 +1,131 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use crate::ui;
+use crate::render;
+use crate::server::resource_pack;
+
+pub struct ResourcePackPrompt {
+    elements: Option<UIElements>,
+    request: resource_pack::Request,
+    answerer: mpsc::Sender<(resource_pack::Request, bool)>,
+    /// Whether either button was clicked
+    answered: Rc<Cell<bool>>,
+}
+
+struct UIElements {
+    _background: ui::ImageRef,
+    _msg: ui::TextRef,
+    _url: ui::TextRef,
+    _accept: ui::ButtonRef,
+    _decline: ui::ButtonRef,
+}
+
+impl ResourcePackPrompt {
+    pub fn new(request: resource_pack::Request, answerer: mpsc::Sender<(resource_pack::Request, bool)>) -> ResourcePackPrompt {
+        ResourcePackPrompt {
+            elements: None,
+            request,
+            answerer,
+            answered: Rc::new(Cell::new(false)),
+        }
+    }
+
+    fn button(&self, ui_container: &mut ui::Container, x: f64, label: &str, accept: bool) -> ui::ButtonRef {
+        let button = ui::ButtonBuilder::new()
+            .position(x, 40.0)
+            .size(200.0, 40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+        {
+            let mut button = button.borrow_mut();
+            let txt = ui::TextBuilder::new()
+                .text(label)
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *button);
+            button.add_text(txt);
+            let request = self.request.clone();
+            let answered = self.answered.clone();
+            button.add_click_func(move |_, game| {
+                answered.set(true);
+                game.server.respond_to_resource_pack(request.clone(), accept);
+                game.screen_sys.pop_screen();
+                game.focused = true;
+                true
+            });
+        }
+        button
+    }
+}
+
+impl super::Screen for ResourcePackPrompt {
+    fn on_active(&mut self, _renderer: &mut render::Renderer, ui_container: &mut ui::Container) {
+        let background = ui::ImageBuilder::new()
+            .texture("steven:solid")
+            .position(0.0, 0.0)
+            .size(854.0, 480.0)
+            .colour((0, 0, 0, 100))
+            .create(ui_container);
+
+        let msg = ui::TextBuilder::new()
+            .text("The server wants you to use a resource pack")
+            .position(0.0, -40.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+
+        let url = ui::TextBuilder::new()
+            .text(self.request.url.clone())
+            .position(0.0, -16.0)
+            .colour((255, 255, 85, 255))
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .create(ui_container);
+
+        let accept = self.button(ui_container, -110.0, "Yes", true);
+        let decline = self.button(ui_container, 110.0, "No", false);
+
+        self.elements = Some(UIElements {
+            _background: background,
+            _msg: msg,
+            _url: url,
+            _accept: accept,
+            _decline: decline,
+        });
+    }
+
+    fn on_deactive(&mut self, _renderer: &mut render::Renderer, _ui_container: &mut ui::Container) {
+        // Clean up
+        self.elements = None;
+        // Closed without picking, the server still needs an answer
+        if !self.answered.replace(true) {
+            let _ = self.answerer.send((self.request.clone(), false));
+        }
+    }
+
+    fn tick(&mut self,
+            _delta: f64,
+            _renderer: &mut render::Renderer,
+            _ui_container: &mut ui::Container) -> Option<Box<super::Screen>> {
+        None
+    }
+
+    // Has to be closable for the buttons to be clickable whilst in
+    // game. Closing it declines the pack.
+    fn is_closable(&self) -> bool {
+        true
+    }
+}
@@ -17,13 +17,15 @@ use std::thread;
 use std::sync::mpsc;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::collections::HashMap;
 
 use crate::ui;
 use crate::render;
 use crate::format;
 use crate::format::{Component, TextComponent};
 use crate::protocol;
+use crate::server;
+use crate::accounts;
+use crate::server::resource_pack;
 
 use serde_json;
 use std::time::{Duration};
@@ -37,7 +39,6 @@ pub struct ServerList {
     disconnect_reason: Option<Component>,
 
     needs_reload: Rc<RefCell<bool>>,
-    server_protocol_versions: HashMap<String, i32>,
 }
 
 struct UIElements {
@@ -45,6 +46,8 @@ struct UIElements {
     servers: Vec<Server>,
 
     _add_btn: ui::ButtonRef,
+    _accounts_btn: ui::ButtonRef,
+    _singleplayer_btn: ui::ButtonRef,
     _refresh_btn: ui::ButtonRef,
     _options_btn: ui::ButtonRef,
     _disclaimer: ui::TextRef,
@@ -60,6 +63,7 @@ struct Server {
     motd: ui::FormattedRef,
     ping: ui::ImageRef,
     players: ui::TextRef,
+    sample: ui::TextRef,
     version: ui::FormattedRef,
 
     icon: ui::ImageRef,
@@ -79,6 +83,8 @@ struct PingInfo {
     protocol_version: i32,
     protocol_name: String,
     favicon: Option<image::DynamicImage>,
+    sample: Vec<String>,
+    forge_mods: Option<Vec<String>>,
 }
 
 impl Server {
@@ -97,7 +103,6 @@ impl ServerList {
             elements: None,
             disconnect_reason,
             needs_reload: Rc::new(RefCell::new(false)),
-            server_protocol_versions: HashMap::new(),
         }
     }
 
@@ -128,6 +133,15 @@ impl ServerList {
         for (index, svr) in servers.iter().enumerate() {
             let name = svr.get("name").unwrap().as_str().unwrap().to_owned();
             let address = svr.get("address").unwrap().as_str().unwrap().to_owned();
+            // Skips negotiating the version when set
+            let protocol_version = svr.get("protocol_version").and_then(|v| v.as_i64()).map(|v| v as i32);
+            // Overrides cl_proxy when set
+            let proxy_setting = svr.get("proxy").and_then(|v| v.as_str()).unwrap_or("").to_owned();
+            let proxy = protocol::proxy::Proxy::parse(&proxy_setting);
+            // Joins as this account instead of the current one when set
+            let account = svr.get("account").and_then(|v| v.as_str()).map(|v| v.to_owned());
+            // What to do when the server sends a resource pack
+            let resource_packs = resource_pack::Policy::parse(svr.get("resource_packs").and_then(|v| v.as_str()).unwrap_or(""));
 
             // Everything is attached to this
             let back = ui::ImageBuilder::new()
@@ -143,6 +157,8 @@ impl ServerList {
             {
                 let mut backr = back.borrow_mut();
                 let address = address.clone();
+                let proxy_setting = proxy_setting.clone();
+                let account = account.clone();
                 backr.add_hover_func(move |this, over, _| {
                     this.colour.3 = if over {
                         200
@@ -152,8 +168,25 @@ impl ServerList {
                     false
                 });
                 backr.add_click_func(move |_, game| {
-                    game.screen_sys.replace_screen(Box::new(super::connecting::Connecting::new(&address)));
-                    game.connect_to(&address);
+                    let profile = match account {
+                        Some(ref id) => match accounts::Accounts::load().find(id) {
+                            Some(account) => Ok(Some(account.profile.clone())),
+                            None => Err(protocol::Error::Err("The account picked for this server has been removed".to_owned())),
+                        },
+                        None => Ok(None),
+                    };
+                    match profile.and_then(|profile| protocol::proxy::Proxy::parse(&proxy_setting).map(|proxy| (proxy, profile))) {
+                        Ok((proxy, profile)) => {
+                            game.screen_sys.replace_screen(Box::new(super::connecting::Connecting::new(&address)));
+                            game.resource_pack_policy = resource_packs;
+                            game.connect_to(&address, protocol_version, proxy.or_else(protocol::proxy::default_proxy), profile);
+                        },
+                        Err(err) => {
+                            let mut msg = TextComponent::new(&format!("{}", err));
+                            msg.modifier.color = Some(format::Color::Red);
+                            game.screen_sys.replace_screen(Box::new(ServerList::new(Some(Component::Text(msg)))));
+                        },
+                    }
                     true
                 });
             }
@@ -188,6 +221,14 @@ impl ServerList {
                 .alignment(ui::VAttach::Top, ui::HAttach::Right)
                 .attach(&mut *back.borrow_mut());
 
+            // Some of the players online
+            let sample = ui::TextBuilder::new()
+                .text("")
+                .position(55.0, 5.0)
+                .colour((170, 170, 170, 255))
+                .alignment(ui::VAttach::Bottom, ui::HAttach::Right)
+                .attach(&mut *back.borrow_mut());
+
             // Server's message of the day
             let motd = ui::FormattedBuilder::new()
                 .text(Component::Text(TextComponent::new("Connecting...")))
@@ -236,7 +277,15 @@ impl ServerList {
                 let saddr = address.clone();
                 btn.add_click_func(move |_, game| {
                     game.screen_sys.replace_screen(Box::new(super::edit_server::EditServerEntry::new(
-                        Some((index, sname.clone(), saddr.clone()))
+                        Some(super::edit_server::EntryInfo {
+                            index,
+                            name: sname.clone(),
+                            address: saddr.clone(),
+                            protocol_version,
+                            proxy: proxy_setting.clone(),
+                            account: account.clone(),
+                            resource_packs,
+                        })
                     )));
                     true
                 })
@@ -252,6 +301,7 @@ impl ServerList {
                 motd,
                 ping,
                 players,
+                sample,
                 version,
 
                 icon,
@@ -263,8 +313,16 @@ impl ServerList {
 
             // Don't block the main thread whilst pinging the server
             thread::spawn(move || {
-                match protocol::Conn::new(&address, protocol::SUPPORTED_PROTOCOLS[0]).and_then(|conn| conn.do_status()) {
+                let res = proxy.and_then(|proxy| {
+                    let mut options = protocol::ConnectOptions::default();
+                    if proxy.is_some() {
+                        options.proxy = proxy;
+                    }
+                    protocol::Conn::ping(&address, protocol::SUPPORTED_PROTOCOLS[0], &options)
+                });
+                match res {
                     Ok(res) => {
+                        server::negotiate::set_forge_info(&address, res.0.forge.as_ref());
                         let mut desc = res.0.description;
                         format::convert_legacy(&mut desc);
                         let favicon = if let Some(icon) = res.0.favicon {
@@ -284,6 +342,8 @@ impl ServerList {
                             protocol_version: res.0.version.protocol,
                             protocol_name: res.0.version.name,
                             favicon,
+                            sample: res.0.players.sample.into_iter().map(|p| p.name).collect(),
+                            forge_mods: res.0.forge.map(|forge| forge.mods.into_iter().map(|m| m.id).collect()),
                         }));
                     }
                     Err(err) => {
@@ -300,6 +360,8 @@ impl ServerList {
                             protocol_version: 0,
                             protocol_name: "".to_owned(),
                             favicon: None,
+                            sample: vec![],
+                            forge_mods: None,
                         });
                     }
                 }
@@ -355,6 +417,51 @@ impl super::Screen for ServerList {
             })
         }
 
+        // Switch between saved accounts
+        let accounts = ui::ButtonBuilder::new()
+            .position(0.0, -50.0 - 15.0)
+            .size(100.0, 30.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .draw_index(2)
+            .create(ui_container);
+        {
+            let mut accounts = accounts.borrow_mut();
+            let txt = ui::TextBuilder::new()
+                .text("Accounts")
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *accounts);
+            accounts.add_text(txt);
+            accounts.add_click_func(move |_, game| {
+                game.screen_sys.replace_screen(Box::new(super::Login::accounts(game.vars.clone())));
+                true
+            })
+        }
+
+        // Play offline on the integrated server
+        let singleplayer = ui::ButtonBuilder::new()
+            .position(100.0, -50.0 - 15.0)
+            .size(100.0, 30.0)
+            .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+            .draw_index(2)
+            .create(ui_container);
+        {
+            let mut singleplayer = singleplayer.borrow_mut();
+            let txt = ui::TextBuilder::new()
+                .text("Singleplayer")
+                .alignment(ui::VAttach::Middle, ui::HAttach::Center)
+                .attach(&mut *singleplayer);
+            singleplayer.add_text(txt);
+            singleplayer.add_click_func(move |_, game| {
+                game.screen_sys.replace_screen(Box::new(super::connecting::Connecting::new("Singleplayer")));
+                if let Err(err) = game.connect_to_integrated() {
+                    let mut msg = format::TextComponent::new(&format!("Failed to start the integrated server: {}", err));
+                    msg.modifier.color = Some(format::Color::Red);
+                    game.screen_sys.replace_screen(Box::new(ServerList::new(Some(format::Component::Text(msg)))));
+                }
+                true
+            })
+        }
+
         // Options menu
         let options = ui::ButtonBuilder::new()
             .position(5.0, 25.0)
@@ -416,6 +523,8 @@ impl super::Screen for ServerList {
             servers: Vec::new(),
 
             _add_btn: add,
+            _accounts_btn: accounts,
+            _singleplayer_btn: singleplayer,
             _refresh_btn: refresh,
             _options_btn: options,
             _disclaimer: disclaimer,
@@ -484,7 +593,7 @@ impl super::Screen for ServerList {
                         if res.exists {
                             {
                                 let mut players = s.players.borrow_mut();
-                                let txt = if protocol::SUPPORTED_PROTOCOLS.contains(&res.protocol_version) {
+                                let txt = if protocol::versions::best_protocol_version(res.protocol_version).is_some() {
                                     players.colour.1 = 255;
                                     players.colour.2 = 255;
                                     format!("{}/{}", res.online, res.max)
@@ -495,13 +604,24 @@ impl super::Screen for ServerList {
                                 };
                                 players.text = txt;
 
-                                // TODO: store in memory instead of disk? but where?
-                                self.server_protocol_versions.insert(res.address, res.protocol_version);
-                                let mut out = fs::File::create("server_versions.json").unwrap();
-                                serde_json::to_writer_pretty(&mut out, &self.server_protocol_versions).unwrap();
+                                server::negotiate::set_known_version(&res.address, res.protocol_version);
+                            }
+                            if !res.sample.is_empty() {
+                                s.sample.borrow_mut().text = res.sample.join(", ");
                             }
                             let mut txt = TextComponent::new(&res.protocol_name);
                             txt.modifier.color = Some(format::Color::Yellow);
+                            if let Some(mods) = res.forge_mods {
+                                // List the mods the server needs, as long as that fits
+                                const MAX_MODS: usize = 6;
+                                let mut list = mods.iter().take(MAX_MODS).cloned().collect::<Vec<_>>().join(", ");
+                                if mods.len() > MAX_MODS {
+                                    list.push_str(&format!(" and {} more", mods.len() - MAX_MODS));
+                                }
+                                let mut forge = TextComponent::new(&format!(" Forge ({} mods): {}", mods.len(), list));
+                                forge.modifier.color = Some(format::Color::Gray);
+                                txt.modifier.extra = Some(vec![Component::Text(forge)]);
+                            }
                             let mut msg = Component::Text(txt);
                             format::convert_legacy(&mut msg);
                             s.version.borrow_mut().set_text(msg);
@@ -0,0 +1,318 @@
+// The Forge (FML) handshake used by modded 1.7 to 1.12 servers.
+//
+// Clients announce themselves as Forge clients by adding `\0FML\0`
+// to the host in the handshake packet. After logging in the server
+// then runs a handshake over the `FML|HS` plugin channel, exchanging
+// mod lists and syncing its id registries before the game is joined.
+// We only ever advertise the core mods the server itself reports so
+// servers that accept vanilla clients will let us in.
+
+use std::io;
+use log::debug;
+use crate::protocol::{self, Serializable, VarInt, Error, ForgeMod};
+
+pub const HANDSHAKE_CHANNEL: &str = "FML|HS";
+/// Appended to the host in the handshake to mark the client as Forge
+pub const HOST_MARKER: &str = "\0FML\0";
+
+/// Channels a Forge client registers, sent on `REGISTER`.
+const CHANNELS: [&str; 4] = ["FML|HS", "FML", "FML|MP", "FORGE"];
+/// The mods every Forge install has, anything else is left out of
+/// the mod list we send.
+const CORE_MODS: [&str; 4] = ["minecraft", "mcp", "FML", "forge"];
+
+/// The stage of the handshake, as sent in a `HandshakeAck`.
+///
+/// The client and server have separate sets of stages so the same
+/// value means different things depending on who sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    // Client stages (written)
+    WaitingServerData,
+    WaitingServerComplete,
+    PendingComplete,
+    // Server stages (read)
+    WaitingCAck,
+    // Both, with different values
+    Complete,
+}
+
+impl Phase {
+    fn from_server(val: i8) -> Result<Phase, Error> {
+        match val {
+            2 => Ok(Phase::WaitingCAck),
+            3 => Ok(Phase::Complete),
+            _ => Err(Error::Err(format!("Unexpected FML server phase {}", val))),
+        }
+    }
+
+    fn client_value(self) -> Result<i8, Error> {
+        match self {
+            Phase::WaitingServerData => Ok(2),
+            Phase::WaitingServerComplete => Ok(3),
+            Phase::PendingComplete => Ok(4),
+            Phase::Complete => Ok(5),
+            Phase::WaitingCAck => Err(Error::Err("WaitingCAck is a server phase".to_owned())),
+        }
+    }
+}
+
+/// A message on the `FML|HS` channel.
+#[derive(Debug)]
+pub enum FmlHs {
+    ServerHello {
+        fml_protocol_version: i8,
+        override_dimension: Option<i32>,
+    },
+    ClientHello {
+        fml_protocol_version: i8,
+    },
+    ModList {
+        mods: Vec<ForgeMod>,
+    },
+    /// A registry's ids, sent by FML protocol 2 onwards. `has_more`
+    /// is false for the last registry.
+    RegistryData {
+        has_more: bool,
+        name: String,
+        ids: Vec<(String, i32)>,
+    },
+    /// All block and item ids, sent by FML protocol 1 (1.7).
+    ModIdData {
+        ids: Vec<(String, i32)>,
+    },
+    HandshakeAck {
+        phase: Phase,
+    },
+    HandshakeReset,
+}
+
+impl FmlHs {
+    /// Reads a message sent by the server. `fml_protocol_version`
+    /// is the version from the server's hello which decides how
+    /// id data is laid out.
+    pub fn read_from<R: io::Read>(buf: &mut R, fml_protocol_version: i8) -> Result<FmlHs, Error> {
+        let discriminator = u8::read_from(buf)?;
+        Ok(match discriminator {
+            0 => {
+                let fml_protocol_version = i8::read_from(buf)?;
+                let override_dimension = if fml_protocol_version > 1 {
+                    Some(i32::read_from(buf)?)
+                } else {
+                    None
+                };
+                FmlHs::ServerHello {
+                    fml_protocol_version,
+                    override_dimension,
+                }
+            },
+            2 => {
+                let count = VarInt::read_from(buf)?.0;
+                let mut mods = Vec::with_capacity(count.max(0) as usize);
+                for _ in 0..count {
+                    mods.push(ForgeMod {
+                        id: String::read_from(buf)?,
+                        version: String::read_from(buf)?,
+                    });
+                }
+                FmlHs::ModList { mods }
+            },
+            3 if fml_protocol_version > 1 => {
+                let has_more = bool::read_from(buf)?;
+                let name = String::read_from(buf)?;
+                let ids = read_ids(buf)?;
+                // Substitutions and dummied ids follow, neither of
+                // which we have any use for.
+                FmlHs::RegistryData { has_more, name, ids }
+            },
+            3 => FmlHs::ModIdData {
+                ids: read_ids(buf)?,
+            },
+            255 => FmlHs::HandshakeAck {
+                phase: Phase::from_server(i8::read_from(buf)?)?,
+            },
+            254 => FmlHs::HandshakeReset,
+            _ => return Err(Error::Err(format!("Unknown FML|HS message {}", discriminator))),
+        })
+    }
+
+    /// Writes a message to send to the server.
+    pub fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), Error> {
+        match *self {
+            FmlHs::ClientHello { fml_protocol_version } => {
+                1u8.write_to(buf)?;
+                fml_protocol_version.write_to(buf)?;
+            },
+            FmlHs::ModList { ref mods } => {
+                2u8.write_to(buf)?;
+                VarInt(mods.len() as i32).write_to(buf)?;
+                for m in mods {
+                    m.id.write_to(buf)?;
+                    m.version.write_to(buf)?;
+                }
+            },
+            FmlHs::HandshakeAck { phase } => {
+                255u8.write_to(buf)?;
+                phase.client_value()?.write_to(buf)?;
+            },
+            ref val => return Err(Error::Err(format!("{:?} can't be sent by the client", val))),
+        }
+        Ok(())
+    }
+}
+
+fn read_ids<R: io::Read>(buf: &mut R) -> Result<Vec<(String, i32)>, Error> {
+    let count = VarInt::read_from(buf)?.0;
+    let mut ids = Vec::with_capacity(count.max(0) as usize);
+    for _ in 0..count {
+        ids.push((String::read_from(buf)?, VarInt::read_from(buf)?.0));
+    }
+    Ok(ids)
+}
+
+/// The client's side of the handshake.
+pub struct Handshake {
+    mods: Vec<ForgeMod>,
+    fml_protocol_version: i8,
+}
+
+impl Handshake {
+    /// Creates a handshake which advertises the core mods out of
+    /// `server_mods`, the mod list from the server's status.
+    pub fn new(server_mods: &[ForgeMod]) -> Handshake {
+        Handshake {
+            mods: server_mods.iter()
+                .filter(|m| CORE_MODS.contains(&&m.id[..]))
+                .cloned()
+                .collect(),
+            fml_protocol_version: 0,
+        }
+    }
+
+    /// Handles a message from the server on the `FML|HS` channel,
+    /// returning the plugin messages (channel and data) to reply
+    /// with.
+    pub fn handle(&mut self, data: &[u8]) -> Result<Vec<(&'static str, Vec<u8>)>, Error> {
+        let msg = FmlHs::read_from(&mut io::Cursor::new(data), self.fml_protocol_version)?;
+        debug!("FML|HS: {:?}", msg);
+        let mut replies = vec![];
+        match msg {
+            FmlHs::ServerHello { fml_protocol_version, .. } => {
+                self.fml_protocol_version = fml_protocol_version;
+                replies.push(("REGISTER", CHANNELS.join("\0").into_bytes()));
+                replies.push(message(FmlHs::ClientHello { fml_protocol_version })?);
+                replies.push(message(FmlHs::ModList { mods: self.mods.clone() })?);
+            },
+            FmlHs::ModList { .. } => replies.push(ack(Phase::WaitingServerData)?),
+            FmlHs::ModIdData { .. } => replies.push(ack(Phase::WaitingServerComplete)?),
+            FmlHs::RegistryData { has_more, .. } => {
+                if !has_more {
+                    replies.push(ack(Phase::WaitingServerComplete)?);
+                }
+            },
+            FmlHs::HandshakeAck { phase } => match phase {
+                Phase::WaitingCAck => replies.push(ack(Phase::PendingComplete)?),
+                Phase::Complete => replies.push(ack(Phase::Complete)?),
+                _ => {},
+            },
+            FmlHs::HandshakeReset => self.fml_protocol_version = 0,
+            FmlHs::ClientHello { .. } => return Err(Error::Err("Server sent a ClientHello".to_owned())),
+        }
+        Ok(replies)
+    }
+}
+
+fn message(msg: FmlHs) -> Result<(&'static str, Vec<u8>), Error> {
+    let mut data = vec![];
+    msg.write_to(&mut data)?;
+    Ok((HANDSHAKE_CHANNEL, data))
+}
+
+fn ack(phase: Phase) -> Result<(&'static str, Vec<u8>), Error> {
+    message(FmlHs::HandshakeAck { phase })
+}
+
+/// Returns the handshake to run with a server that reported `forge`
+/// in its status, as long as it runs a Forge version (1.7 to 1.12)
+/// we can handshake with.
+pub fn handshake_for(protocol_version: i32, forge: Option<&protocol::ForgeInfo>) -> Option<Handshake> {
+    match forge {
+        Some(info) if info.fml_network_version == 1 && protocol_version < 393 => Some(Handshake::new(&info.mods)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn string(val: &str) -> Vec<u8> {
+        let mut data = vec![];
+        val.to_owned().write_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn full_handshake() {
+        let server_mods = vec![
+            ForgeMod { id: "minecraft".to_owned(), version: "1.12.2".to_owned() },
+            ForgeMod { id: "FML".to_owned(), version: "8.0.99.99".to_owned() },
+            ForgeMod { id: "ironchest".to_owned(), version: "7.0.59".to_owned() },
+        ];
+        let mut handshake = Handshake::new(&server_mods);
+
+        let replies = handshake.handle(&[0, 2, 0, 0, 0, 0]).unwrap();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0], ("REGISTER", b"FML|HS\0FML\0FML|MP\0FORGE".to_vec()));
+        assert_eq!(replies[1], (HANDSHAKE_CHANNEL, vec![1, 2]));
+        let mut mod_list = vec![2, 2];
+        mod_list.extend(string("minecraft"));
+        mod_list.extend(string("1.12.2"));
+        mod_list.extend(string("FML"));
+        mod_list.extend(string("8.0.99.99"));
+        assert_eq!(replies[2], (HANDSHAKE_CHANNEL, mod_list));
+
+        let mut mod_list = vec![2, 1];
+        mod_list.extend(string("ironchest"));
+        mod_list.extend(string("7.0.59"));
+        assert_eq!(handshake.handle(&mod_list).unwrap(), vec![(HANDSHAKE_CHANNEL, vec![255, 2])]);
+
+        let mut registry = vec![3, 1];
+        registry.extend(string("minecraft:blocks"));
+        registry.push(1);
+        registry.extend(string("ironchest:iron_chest"));
+        registry.push(200);
+        registry.push(1);
+        registry.extend(&[0, 0]);
+        assert!(handshake.handle(&registry).unwrap().is_empty());
+        registry[1] = 0;
+        assert_eq!(handshake.handle(&registry).unwrap(), vec![(HANDSHAKE_CHANNEL, vec![255, 3])]);
+
+        assert_eq!(handshake.handle(&[255, 2]).unwrap(), vec![(HANDSHAKE_CHANNEL, vec![255, 4])]);
+        assert_eq!(handshake.handle(&[255, 3]).unwrap(), vec![(HANDSHAKE_CHANNEL, vec![255, 5])]);
+    }
+
+    #[test]
+    fn mod_id_data_17() {
+        let mut handshake = Handshake::new(&[]);
+        let replies = handshake.handle(&[0, 1]).unwrap();
+        assert_eq!(replies[1], (HANDSHAKE_CHANNEL, vec![1, 1]));
+        assert_eq!(replies[2], (HANDSHAKE_CHANNEL, vec![2, 0]));
+        let mut ids = vec![3, 1];
+        ids.extend(string("\u{1}minecraft:stone"));
+        ids.push(1);
+        ids.extend(&[0, 0]);
+        assert_eq!(handshake.handle(&ids).unwrap(), vec![(HANDSHAKE_CHANNEL, vec![255, 3])]);
+    }
+
+    #[test]
+    fn forge_servers_only() {
+        let forge = protocol::ForgeInfo {
+            fml_network_version: 1,
+            mods: vec![],
+        };
+        assert!(handshake_for(340, Some(&forge)).is_some());
+        assert!(handshake_for(340, None).is_none());
+        assert!(handshake_for(404, Some(&forge)).is_none());
+    }
+}
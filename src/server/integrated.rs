@@ -0,0 +1,698 @@
+// An offline server running inside the client. It serves a generated
+// world over a loopback socket using the real play protocol so that
+// singleplayer goes through the same code paths as a remote server.
+//
+// The world is saved to a file of its own when the server shuts down
+// and loaded from it when started again. Worlds saved by vanilla
+// aren't supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use rand::{self, Rng};
+use log::{debug, info, warn};
+use crate::protocol::{self, packet, Serializable, VarInt, LenPrefixed, LenPrefixedBytes};
+use crate::protocol::listener::{Incoming, Listener};
+use crate::protocol::packet::Packet;
+use crate::types::bit;
+use crate::world::block;
+use crate::shared::{Axis, Position};
+use crate::format;
+
+/// The protocol version spoken by the integrated server.
+pub const PROTOCOL_VERSION: i32 = 404;
+
+/// Where the integrated server's world is saved
+pub const SAVE_FILE: &str = "./singleplayer.dat";
+/// Bumped when the layout of saved worlds changes
+const SAVE_FORMAT: i32 = 1;
+
+const COMPRESSION_THRESHOLD: i32 = 256;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Generates the terrain and trees used by both the integrated server
+/// and the world shown behind the menus.
+pub fn generate_world<F: FnMut(Position, block::Block)>(mut set_block: F) {
+    let mut rng = rand::thread_rng();
+    for x in -7*16 .. 7*16 {
+        for z in -7*16 .. 7*16 {
+            let h = 5 + (6.0 * (x as f64 / 16.0).cos() * (z as f64 / 16.0).sin()) as i32;
+            for y in 0 .. h {
+                set_block(Position::new(x, y, z), block::Dirt{ snowy: false, variant: block::DirtVariant::Normal });
+            }
+            set_block(Position::new(x, h, z), block::Grass{ snowy: false });
+
+            if x*x + z*z > 16*16 && rng.gen_bool(1.0 / 80.0) {
+                for i in 0 .. 5 {
+                    set_block(Position::new(x, h + 1 + i, z), block::Log{ axis: Axis::Y, variant: block::TreeVariant::Oak });
+                }
+                for xx in -2 .. 3 {
+                    for zz in -2 .. 3 {
+                        if xx == 0 && z == 0 {
+                            continue;
+                        }
+                        set_block(Position::new(x + xx, h + 3, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
+                        set_block(Position::new(x + xx, h + 4, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
+                        if xx.abs() <= 1 && zz.abs() <= 1 {
+                            set_block(Position::new(x + xx, h + 5, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
+                        }
+                        if xx * xx + zz * zz <= 1 {
+                            set_block(Position::new(x + xx, h + 6, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A chunk column stored as vanilla block state ids.
+struct Column {
+    sections: Vec<Option<Vec<u16>>>,
+}
+
+impl Column {
+    fn new() -> Column {
+        Column {
+            sections: vec![None; 16],
+        }
+    }
+
+    fn get(&self, x: i32, y: i32, z: i32) -> u16 {
+        match self.sections[(y >> 4) as usize] {
+            Some(ref blocks) => blocks[section_index(x, y, z)],
+            None => 0,
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, z: i32, id: u16) {
+        let section = self.sections[(y >> 4) as usize].get_or_insert_with(|| vec![0; 4096]);
+        section[section_index(x, y, z)] = id;
+    }
+
+    /// Encodes the column in the 1.13.2 chunk data format, returning
+    /// the section bitmask and the data.
+    fn write(&self) -> Result<(i32, Vec<u8>), protocol::Error> {
+        let mut mask = 0;
+        let mut data = vec![];
+        for (i, section) in self.sections.iter().enumerate() {
+            let blocks = match *section {
+                Some(ref blocks) if blocks.iter().any(|&b| b != 0) => blocks,
+                _ => continue,
+            };
+            mask |= 1 << i;
+
+            let mut palette = vec![];
+            let mut palette_ids = HashMap::new();
+            for &b in blocks.iter() {
+                palette_ids.entry(b).or_insert_with(|| {
+                    palette.push(b);
+                    palette.len() - 1
+                });
+            }
+            let mut bits = 4;
+            while (1 << bits) < palette.len() {
+                bits += 1;
+            }
+            let global = bits > 8;
+            if global {
+                bits = 14;
+            }
+
+            data.write_u8(bits as u8)?;
+            if !global {
+                VarInt(palette.len() as i32).write_to(&mut data)?;
+                for &id in &palette {
+                    VarInt(i32::from(id)).write_to(&mut data)?;
+                }
+            }
+            let mut m = bit::Map::from_raw(vec![0; 4096 * bits / 64], bits);
+            for (bi, &b) in blocks.iter().enumerate() {
+                m.set(bi, if global { b as usize } else { palette_ids[&b] });
+            }
+            LenPrefixed::<VarInt, u64>::new(m.into_raw()).write_to(&mut data)?;
+
+            // Block light, then sky light which is kept at full
+            // brightness as the server doesn't do lighting.
+            data.write_all(&[0; 2048])?;
+            data.write_all(&[0xFF; 2048])?;
+        }
+        // Biomes, all plains
+        for _ in 0 .. 256 {
+            data.write_i32::<BigEndian>(1)?;
+        }
+        Ok((mask, data))
+    }
+}
+
+fn section_index(x: i32, y: i32, z: i32) -> usize {
+    (((y & 0xF) << 8) | ((z & 0xF) << 4) | (x & 0xF)) as usize
+}
+
+struct World {
+    columns: HashMap<(i32, i32), Column>,
+}
+
+impl World {
+    fn generate() -> World {
+        let mut world = World {
+            columns: HashMap::new(),
+        };
+        let stone = block::Stone{ variant: block::StoneVariant::Normal };
+        generate_world(|pos, b| {
+            let id = b.get_vanilla_id(PROTOCOL_VERSION)
+                .or_else(|| stone.get_vanilla_id(PROTOCOL_VERSION))
+                .unwrap_or(1);
+            world.set_block(pos, id as u16);
+        });
+        world
+    }
+
+    /// Loads the world saved at `path`, or generates a new one if
+    /// nothing has been saved there yet.
+    fn open(path: &Path) -> Result<World, protocol::Error> {
+        match fs::File::open(path) {
+            Ok(file) => World::read_from(&mut GzDecoder::new(BufReader::new(file))),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(World::generate()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), protocol::Error> {
+        // Written next to the save first so a failure can't lose it
+        let tmp = path.with_extension("tmp");
+        {
+            let mut out = GzEncoder::new(BufWriter::new(fs::File::create(&tmp)?), Compression::default());
+            self.write_to(&mut out)?;
+            out.finish()?.flush()?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<World, protocol::Error> {
+        let format = r.read_i32::<BigEndian>()?;
+        let protocol_version = r.read_i32::<BigEndian>()?;
+        if format != SAVE_FORMAT || protocol_version != PROTOCOL_VERSION {
+            return Err(protocol::Error::Err(format!("Unsupported world save, format {} with block ids of protocol {}", format, protocol_version)));
+        }
+        let mut world = World {
+            columns: HashMap::new(),
+        };
+        for _ in 0 .. r.read_u32::<BigEndian>()? {
+            let x = r.read_i32::<BigEndian>()?;
+            let z = r.read_i32::<BigEndian>()?;
+            let mask = r.read_u16::<BigEndian>()?;
+            let mut column = Column::new();
+            for (i, section) in column.sections.iter_mut().enumerate() {
+                if mask & (1 << i) == 0 {
+                    continue;
+                }
+                let mut blocks = Vec::with_capacity(4096);
+                for _ in 0 .. 4096 {
+                    blocks.push(r.read_u16::<BigEndian>()?);
+                }
+                *section = Some(blocks);
+            }
+            world.columns.insert((x, z), column);
+        }
+        Ok(world)
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> Result<(), protocol::Error> {
+        w.write_i32::<BigEndian>(SAVE_FORMAT)?;
+        w.write_i32::<BigEndian>(PROTOCOL_VERSION)?;
+        w.write_u32::<BigEndian>(self.columns.len() as u32)?;
+        for (&(x, z), column) in &self.columns {
+            w.write_i32::<BigEndian>(x)?;
+            w.write_i32::<BigEndian>(z)?;
+            let mask = column.sections.iter().enumerate()
+                .filter(|&(_, section)| section.is_some())
+                .fold(0u16, |mask, (i, _)| mask | (1 << i));
+            w.write_u16::<BigEndian>(mask)?;
+            for blocks in column.sections.iter().flatten() {
+                for &id in blocks {
+                    w.write_u16::<BigEndian>(id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_block(&self, pos: Position) -> u16 {
+        if pos.y < 0 || pos.y > 255 {
+            return 0;
+        }
+        self.columns.get(&(pos.x >> 4, pos.z >> 4))
+            .map_or(0, |c| c.get(pos.x, pos.y, pos.z))
+    }
+
+    fn set_block(&mut self, pos: Position, id: u16) -> bool {
+        if pos.y < 0 || pos.y > 255 {
+            return false;
+        }
+        self.columns.entry((pos.x >> 4, pos.z >> 4))
+            .or_insert_with(Column::new)
+            .set(pos.x, pos.y, pos.z, id);
+        true
+    }
+
+    /// The y coordinate a player can stand at above the given column.
+    fn spawn_height(&self, x: i32, z: i32) -> i32 {
+        (0 .. 256).rev()
+            .find(|&y| self.get_block(Position::new(x, y, z)) != 0)
+            .map_or(64, |y| y + 1)
+    }
+}
+
+/// A player connected to the integrated server.
+struct Player {
+    name: String,
+    entity_id: i32,
+    position: (f64, f64, f64),
+    conn: Arc<Mutex<protocol::Conn>>,
+}
+
+struct Shared {
+    running: AtomicBool,
+    next_entity_id: AtomicI32,
+    world: Mutex<World>,
+    players: Mutex<Vec<Player>>,
+}
+
+impl Shared {
+    /// Sends the packet to every player, dropping any that
+    /// can no longer be written to.
+    fn broadcast<F, T>(&self, f: F)
+        where F: Fn() -> T,
+              T: protocol::PacketType + ::std::fmt::Debug {
+        self.players.lock().unwrap().retain(|p| {
+            p.conn.lock().unwrap().write_packet(f()).is_ok()
+        });
+    }
+}
+
+/// A running integrated server. It is shut down, saving its world,
+/// when dropped.
+pub struct IntegratedServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    save: PathBuf,
+}
+
+impl IntegratedServer {
+    /// Loads the world saved at `save`, generating one if there is
+    /// none, and starts listening for players on a loopback address
+    /// picked by the OS.
+    pub fn start(save: &Path) -> Result<IntegratedServer, protocol::Error> {
+        let world = World::open(save)?;
+        let listener = Listener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            next_entity_id: AtomicI32::new(1),
+            world: Mutex::new(world),
+            players: Mutex::new(vec![]),
+        });
+        info!("Integrated server listening on {}", addr);
+
+        {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                while shared.running.load(Ordering::SeqCst) {
+                    let client = match listener.accept() {
+                        Ok(val) => val,
+                        Err(err) => {
+                            debug!("Integrated server failed to accept: {}", err);
+                            continue;
+                        },
+                    };
+                    if !shared.running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // Each client gets its own thread so that a slow
+                    // handshake doesn't hold up the others
+                    let shared = shared.clone();
+                    thread::spawn(move || match client.handshake() {
+                        Ok(Incoming::Status(req)) => {
+                            let _ = req.respond(&status(&shared));
+                        },
+                        Ok(Incoming::Login(req)) => handle_login(shared, req),
+                        Err(err) => debug!("Integrated server failed to read a handshake: {}", err),
+                    });
+                }
+            });
+        }
+        {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut id = 0;
+                while shared.running.load(Ordering::SeqCst) {
+                    thread::sleep(KEEP_ALIVE_INTERVAL);
+                    id += 1;
+                    shared.broadcast(|| packet::play::clientbound::KeepAliveClientbound_i64 {
+                        id,
+                    });
+                }
+            });
+        }
+
+        Ok(IntegratedServer {
+            addr,
+            shared,
+            save: save.to_owned(),
+        })
+    }
+
+    /// The address to connect to.
+    pub fn address(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// The last position reported by the named player.
+    pub fn player_position(&self, name: &str) -> Option<(f64, f64, f64)> {
+        self.shared.players.lock().unwrap().iter()
+            .find(|p| p.name == name)
+            .map(|p| p.position)
+    }
+}
+
+impl Drop for IntegratedServer {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+        // Wake up the accept loop so it notices
+        let _ = TcpStream::connect(self.addr);
+        for player in self.shared.players.lock().unwrap().drain(..) {
+            let _ = player.conn.lock().unwrap().write_packet(packet::play::clientbound::Disconnect {
+                reason: format::Component::Text(format::TextComponent::new("Server closed")),
+            });
+        }
+        match self.shared.world.lock().unwrap().save(&self.save) {
+            Ok(()) => info!("Saved the integrated server's world to {}", self.save.display()),
+            Err(err) => warn!("Failed to save the integrated server's world to {}: {}", self.save.display(), err),
+        }
+    }
+}
+
+fn status(shared: &Shared) -> protocol::Status {
+    protocol::Status {
+        version: protocol::StatusVersion {
+            name: "Steven".to_owned(),
+            protocol: PROTOCOL_VERSION,
+        },
+        players: protocol::StatusPlayers {
+            max: 8,
+            online: shared.players.lock().unwrap().len() as i32,
+            sample: vec![],
+        },
+        description: format::Component::Text(format::TextComponent::new("Integrated server")),
+        favicon: None,
+        forge: None,
+    }
+}
+
+fn handle_login(shared: Arc<Shared>, req: protocol::listener::LoginRequest) {
+    if req.handshake.protocol_version != PROTOCOL_VERSION {
+        let _ = req.disconnect(format::Component::Text(format::TextComponent::new(
+            &format!("The integrated server requires protocol version {}", PROTOCOL_VERSION)
+        )));
+        return;
+    }
+    let name = req.username.clone();
+    let (mut read, _) = match req.accept_offline(COMPRESSION_THRESHOLD) {
+        Ok(val) => val,
+        Err(err) => {
+            warn!("Integrated server login failed for {}: {}", name, err);
+            return;
+        },
+    };
+    let conn = Arc::new(Mutex::new(read.clone()));
+    let entity_id = shared.next_entity_id.fetch_add(1, Ordering::SeqCst);
+
+    if let Err(err) = join(&shared, &name, entity_id, &conn) {
+        warn!("Integrated server failed to send the world to {}: {}", name, err);
+        return;
+    }
+    info!("{} joined the integrated server", name);
+
+    loop {
+        let pck = match read.read_packet() {
+            Ok(pck) => pck,
+            Err(_) => break,
+        };
+        if !shared.running.load(Ordering::SeqCst) {
+            break;
+        }
+        handle_packet(&shared, entity_id, &name, pck);
+    }
+
+    shared.players.lock().unwrap().retain(|p| p.entity_id != entity_id);
+    info!("{} left the integrated server", name);
+}
+
+fn join(shared: &Shared, name: &str, entity_id: i32, conn: &Arc<Mutex<protocol::Conn>>) -> Result<(), protocol::Error> {
+    use crate::protocol::packet::play::clientbound::*;
+    let mut conn_lock = conn.lock().unwrap();
+    let conn_w = &mut *conn_lock;
+
+    conn_w.write_packet(JoinGame_i32 {
+        entity_id,
+        gamemode: 1,
+        dimension: 0,
+        difficulty: 0,
+        max_players: 8,
+        level_type: "default".to_owned(),
+        reduced_debug_info: false,
+    })?;
+
+    let spawn = {
+        let world = shared.world.lock().unwrap();
+        for (&(x, z), column) in &world.columns {
+            let (bitmask, data) = column.write()?;
+            conn_w.write_packet(ChunkData {
+                chunk_x: x,
+                chunk_z: z,
+                new: true,
+                bitmask: VarInt(bitmask),
+                data: LenPrefixedBytes::new(data),
+                block_entities: LenPrefixed::new(vec![]),
+            })?;
+        }
+        (0.5, f64::from(world.spawn_height(0, 0)), 0.5)
+    };
+    conn_w.write_packet(SpawnPosition {
+        location: Position::new(spawn.0 as i32, spawn.1 as i32, spawn.2 as i32),
+    })?;
+    conn_w.write_packet(TeleportPlayer_WithConfirm {
+        x: spawn.0,
+        y: spawn.1,
+        z: spawn.2,
+        yaw: 0.0,
+        pitch: 0.0,
+        flags: 0,
+        teleport_id: VarInt(1),
+    })?;
+    drop(conn_lock);
+
+    shared.players.lock().unwrap().push(Player {
+        name: name.to_owned(),
+        entity_id,
+        position: spawn,
+        conn: conn.clone(),
+    });
+    Ok(())
+}
+
+fn handle_packet(shared: &Shared, entity_id: i32, name: &str, pck: Packet) {
+    use crate::protocol::packet::play::clientbound::{BlockChange_VarInt, ServerMessage};
+    match pck {
+        Packet::PlayerPosition(val) => set_position(shared, entity_id, (val.x, val.y, val.z)),
+        Packet::PlayerPositionLook(val) => set_position(shared, entity_id, (val.x, val.y, val.z)),
+        Packet::ChatMessage(val) => {
+            let message = format!("<{}> {}", name, val.message);
+            info!("[Integrated] {}", message);
+            shared.broadcast(|| ServerMessage {
+                message: format::Component::Text(format::TextComponent::new(&message)),
+                position: 0,
+            });
+        },
+        // Players are always in creative so blocks break as soon
+        // as digging starts.
+        Packet::PlayerDigging(val) if val.status.0 == 0 || val.status.0 == 2 => {
+            if shared.world.lock().unwrap().set_block(val.location, 0) {
+                shared.broadcast(|| BlockChange_VarInt {
+                    location: val.location,
+                    block_id: VarInt(0),
+                });
+            }
+        },
+        Packet::PlayerBlockPlacement_f32(val) => {
+            let (dx, dy, dz) = match val.face.0 {
+                0 => (0, -1, 0),
+                1 => (0, 1, 0),
+                2 => (0, 0, -1),
+                3 => (0, 0, 1),
+                4 => (-1, 0, 0),
+                _ => (1, 0, 0),
+            };
+            let location = val.location + (dx, dy, dz);
+            // The server doesn't track inventories so always places stone
+            let id = block::Stone{ variant: block::StoneVariant::Normal }
+                .get_vanilla_id(PROTOCOL_VERSION)
+                .unwrap_or(1) as i32;
+            let placed = {
+                let mut world = shared.world.lock().unwrap();
+                world.get_block(location) == 0 && world.set_block(location, id as u16)
+            };
+            if placed {
+                shared.broadcast(|| BlockChange_VarInt {
+                    location,
+                    block_id: VarInt(id),
+                });
+            }
+        },
+        _ => {},
+    }
+}
+
+fn set_position(shared: &Shared, entity_id: i32, position: (f64, f64, f64)) {
+    if let Some(player) = shared.players.lock().unwrap().iter_mut().find(|p| p.entity_id == entity_id) {
+        player.position = position;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::packet::play::serverbound::*;
+    use crate::world;
+
+    fn connect(server: &IntegratedServer, name: &str) -> protocol::Conn {
+        let mut conn = protocol::Conn::new(&server.address(), PROTOCOL_VERSION).unwrap();
+        conn.write_packet(packet::handshake::serverbound::Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            host: "localhost".to_owned(),
+            port: 25565,
+            next: VarInt(2),
+        }).unwrap();
+        conn.state = protocol::State::Login;
+        conn.write_packet(packet::login::serverbound::LoginStart {
+            username: name.to_owned(),
+        }).unwrap();
+        loop {
+            match conn.read_packet().unwrap() {
+                Packet::SetInitialCompression(val) => conn.set_compresssion(val.threshold.0),
+                Packet::LoginSuccess(_) => break,
+                val => panic!("unexpected packet {:?}", val),
+            }
+        }
+        conn.state = protocol::State::Play;
+        conn
+    }
+
+    fn save_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("steven-integrated-{}-{}.dat", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn play() {
+        let save = save_path("play");
+        let server = IntegratedServer::start(&save).unwrap();
+        let mut conn = connect(&server, "Steve");
+
+        let mut world = world::World::new(PROTOCOL_VERSION);
+        let spawn = loop {
+            match conn.read_packet().unwrap() {
+                Packet::ChunkData(val) => {
+                    world.load_chunk19(val.chunk_x, val.chunk_z, val.new, val.bitmask.0 as u16, val.data.data).unwrap();
+                },
+                Packet::TeleportPlayer_WithConfirm(val) => break (val.x, val.y, val.z),
+                _ => {},
+            }
+        };
+        let ground = Position::new(0, spawn.1 as i32 - 1, 0);
+        assert_eq!(world.get_block(ground), block::Grass{ snowy: false });
+        assert_eq!(world.get_block(ground + (0, -1, 0)), block::Dirt{ snowy: false, variant: block::DirtVariant::Normal });
+
+        conn.write_packet(PlayerPosition {
+            x: 3.5,
+            y: spawn.1,
+            z: 2.5,
+            on_ground: true,
+        }).unwrap();
+
+        conn.write_packet(ChatMessage {
+            message: "hello".to_owned(),
+        }).unwrap();
+        match conn.read_packet().unwrap() {
+            Packet::ServerMessage(val) => assert_eq!(val.message.to_string(), "<Steve> hello"),
+            val => panic!("unexpected packet {:?}", val),
+        }
+        assert_eq!(server.player_position("Steve"), Some((3.5, spawn.1, 2.5)));
+
+        conn.write_packet(PlayerDigging {
+            status: VarInt(0),
+            location: ground,
+            face: 1,
+        }).unwrap();
+        match conn.read_packet().unwrap() {
+            Packet::BlockChange_VarInt(val) => {
+                assert_eq!(val.location, ground);
+                assert_eq!(val.block_id.0, 0);
+            },
+            val => panic!("unexpected packet {:?}", val),
+        }
+
+        conn.write_packet(PlayerBlockPlacement_f32 {
+            location: ground + (0, -1, 0),
+            face: VarInt(1),
+            hand: VarInt(0),
+            cursor_x: 0.5,
+            cursor_y: 1.0,
+            cursor_z: 0.5,
+        }).unwrap();
+        match conn.read_packet().unwrap() {
+            Packet::BlockChange_VarInt(val) => {
+                assert_eq!(val.location, ground);
+                assert_eq!(block::Block::by_vanilla_id(val.block_id.0 as usize, PROTOCOL_VERSION), block::Stone{ variant: block::StoneVariant::Normal });
+            },
+            val => panic!("unexpected packet {:?}", val),
+        }
+        drop(server);
+        fs::remove_file(&save).unwrap();
+    }
+
+    #[test]
+    fn saves_world() {
+        let save = save_path("save");
+        let ground = {
+            let server = IntegratedServer::start(&save).unwrap();
+            let mut world = server.shared.world.lock().unwrap();
+            let ground = Position::new(5, world.spawn_height(5, 5) - 1, 5);
+            assert!(world.set_block(ground, 0));
+            ground
+        };
+        assert!(save.exists());
+
+        let server = IntegratedServer::start(&save).unwrap();
+        assert_eq!(server.shared.world.lock().unwrap().get_block(ground), 0);
+        assert_ne!(server.shared.world.lock().unwrap().get_block(ground + (0, -1, 0)), 0);
+        drop(server);
+
+        fs::write(&save, b"not a world").unwrap();
+        assert!(IntegratedServer::start(&save).is_err());
+        fs::remove_file(&save).unwrap();
+    }
+}
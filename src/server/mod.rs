@@ -12,15 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::protocol::{self, mojang, packet};
+use crate::protocol::{self, mojang, packet, proxy};
 use crate::world;
 use crate::world::block;
 use rand::{self, Rng};
 use std::sync::{Arc, RwLock};
 use std::sync::mpsc;
 use std::thread;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
+use std::fmt;
 use crate::types::hash::FNVHash;
 use crate::resources;
 use crate::render;
@@ -29,19 +31,44 @@ use crate::ecs;
 use crate::entity;
 use cgmath::prelude::*;
 use crate::types::Gamemode;
-use crate::shared::{Axis, Position};
+use crate::shared::Position;
 use crate::format;
 use rsa_public_encrypt_pkcs1;
 use log::{error, debug, warn};
 
 mod sun;
 pub mod plugin_messages;
+pub mod forge;
 pub mod target;
+pub mod integrated;
+pub mod negotiate;
+pub mod resource_pack;
 
 pub struct Server {
     uuid: protocol::UUID,
     conn: Option<protocol::Conn>,
     protocol_version: i32,
+    /// Handlers for plugin messages, by channel
+    pub plugin_channels: plugin_messages::Registry,
+    /// The keys player textures must be signed with to be used
+    texture_keys: Vec<protocol::signature::PublicKey>,
+    proxy: Option<proxy::Proxy>,
+    /// A resource pack the server sent which hasn't been accepted or
+    /// declined yet
+    pub resource_pack_request: Option<resource_pack::Request>,
+    /// Answers to resource pack requests given outside of the game's
+    /// tick, such as by closing the prompt asking the user
+    resource_pack_answers: mpsc::Receiver<(resource_pack::Request, bool)>,
+    resource_pack_answerer: mpsc::Sender<(resource_pack::Request, bool)>,
+    /// The hash of the resource pack being downloaded and where the
+    /// result of the download will be sent
+    resource_pack_download: Option<(String, mpsc::Receiver<Result<PathBuf, protocol::Error>>)>,
+    /// The downloaded resource pack, which the game applies on top of
+    /// the others
+    resource_pack: Option<PathBuf>,
+    /// The hash of the downloaded resource pack whilst waiting to hear
+    /// whether it could be applied
+    resource_pack_pending: Option<String>,
     read_queue: Option<mpsc::Receiver<Result<packet::Packet, protocol::Error>>>,
     pub disconnect_reason: Option<format::Component>,
     just_disconnected: bool,
@@ -83,6 +110,7 @@ pub struct PlayerInfo {
     name: String,
     uuid: protocol::UUID,
     skin_url: Option<String>,
+    textures_signature: mojang::TexturesSignature,
 
     display_name: Option<format::Component>,
     ping: i32,
@@ -97,6 +125,9 @@ macro_rules! handle_packet {
         $(
             protocol::packet::Packet::$packet(val) => $s.$func(val),
         )*
+            protocol::packet::Packet::Unknown { id, data } => {
+                debug!("Skipping unknown packet 0x{:x} ({} bytes)", id, data.len());
+            },
             _ => {},
         }
     )
@@ -104,10 +135,36 @@ macro_rules! handle_packet {
 
 impl Server {
 
-    pub fn connect(resources: Arc<RwLock<resources::Manager>>, profile: mojang::Profile, address: &str, protocol_version: i32) -> Result<Server, protocol::Error> {
-        let mut conn = protocol::Conn::new(address, protocol_version)?;
-
-        let host = conn.host.clone();
+    pub fn connect(resources: Arc<RwLock<resources::Manager>>, profile: mojang::Profile, address: &str, protocol_version: i32, options: &protocol::ConnectOptions) -> Result<Server, protocol::Error> {
+        if !protocol::SUPPORTED_PROTOCOLS.contains(&protocol_version) {
+            return Err(protocol::Error::UnsupportedVersion(protocol_version));
+        }
+        // Without the keys every skin falls back to the default one,
+        // which isn't worth failing to connect over. Keys served from
+        // a url are only fetched once, and never for offline accounts.
+        let texture_keys = match profile.endpoints.cached_texture_keys() {
+            Some(keys) => keys,
+            None if profile.offline => vec![],
+            None => profile.endpoints.texture_keys(options.proxy.as_ref()).unwrap_or_else(|err| {
+                warn!("Failed to get the texture signing keys from {}: {}", profile.endpoints.texture_keys, err);
+                vec![]
+            }),
+        };
+        let mut conn = protocol::Conn::connect(address, protocol_version, options)?;
+
+        let mut plugin_channels = plugin_messages::Registry::new();
+        let forge = forge::handshake_for(protocol_version, negotiate::forge_info(address, options).as_ref());
+        let host = if forge.is_some() {
+            format!("{}{}", conn.host, forge::HOST_MARKER)
+        } else {
+            conn.host.clone()
+        };
+        if let Some(mut forge) = forge {
+            plugin_channels.register(forge::HANDSHAKE_CHANNEL, Box::new(move |data| Ok(forge.handle(data)?
+                .into_iter()
+                .map(|(channel, data)| (channel.to_owned(), data))
+                .collect())));
+        }
         let port = conn.port;
         conn.write_packet(protocol::packet::handshake::serverbound::Handshake {
              protocol_version: protocol::VarInt(protocol_version),
@@ -123,7 +180,15 @@ impl Server {
         use std::rc::Rc;
         let (server_id, public_key, verify_token);
         loop {
-            match conn.read_packet()? {
+            let packet = conn.read_packet()?;
+            if profile.offline {
+                if let protocol::packet::Packet::EncryptionRequest(_) | protocol::packet::Packet::EncryptionRequest_i16(_) = packet {
+                    return Err(protocol::Error::Err(
+                        "This server is in online mode, log in with a Mojang account to join it".to_owned()
+                    ));
+                }
+            }
+            match packet {
                 protocol::packet::Packet::SetInitialCompression(val) => {
                     conn.set_compresssion(val.threshold.0);
                 },
@@ -147,8 +212,15 @@ impl Server {
                     read.state = protocol::State::Play;
                     write.state = protocol::State::Play;
                     let rx = Self::spawn_reader(read);
-                    return Ok(Server::new(protocol_version, protocol::UUID::from_str(&val.uuid), resources, Some(write), Some(rx)));
+                    let mut server = Server::new(protocol_version, protocol::UUID::from_str(&val.uuid), resources, Some(write), Some(rx));
+                    server.plugin_channels = plugin_channels;
+                    server.texture_keys = texture_keys;
+                    server.proxy = options.proxy.clone();
+                    return Ok(server);
                 }
+                protocol::packet::Packet::LoginPluginRequest(val) => {
+                    conn.write_packet(plugin_channels.login_request(val))?;
+                },
                 protocol::packet::Packet::LoginDisconnect(val) => return Err(protocol::Error::Disconnect(val.reason)),
                 val => return Err(protocol::Error::Err(format!("Wrong packet: {:?}", val))),
             };
@@ -161,7 +233,7 @@ impl Server {
         let shared_e = rsa_public_encrypt_pkcs1::encrypt(&public_key, &shared).unwrap();
         let token_e = rsa_public_encrypt_pkcs1::encrypt(&public_key, &verify_token).unwrap();
 
-        profile.join_server(&server_id, &shared, &public_key)?;
+        profile.join_server(&server_id, &shared, &public_key, options.proxy.as_ref())?;
 
         if protocol_version >= 47 {
             conn.write_packet(protocol::packet::login::serverbound::EncryptionResponse {
@@ -195,6 +267,9 @@ impl Server {
                    write.state = protocol::State::Play;
                    break;
                }
+               protocol::packet::Packet::LoginPluginRequest(val) => {
+                   write.write_packet(plugin_channels.login_request(val))?;
+               }
                protocol::packet::Packet::LoginDisconnect(val) => return Err(protocol::Error::Disconnect(val.reason)),
                val => return Err(protocol::Error::Err(format!("Wrong packet: {:?}", val))),
            }
@@ -202,7 +277,11 @@ impl Server {
 
         let rx = Self::spawn_reader(read);
 
-        Ok(Server::new(protocol_version, protocol::UUID::from_str(&uuid), resources, Some(write), Some(rx)))
+        let mut server = Server::new(protocol_version, protocol::UUID::from_str(&uuid), resources, Some(write), Some(rx));
+        server.plugin_channels = plugin_channels;
+        server.texture_keys = texture_keys;
+        server.proxy = options.proxy.clone();
+        Ok(server)
     }
 
     fn spawn_reader(mut read: protocol::Conn) -> mpsc::Receiver<Result<packet::Packet, protocol::Error>> {
@@ -224,36 +303,9 @@ impl Server {
 
     pub fn dummy_server(resources: Arc<RwLock<resources::Manager>>) -> Server {
         let mut server = Server::new(protocol::SUPPORTED_PROTOCOLS[0], protocol::UUID::default(), resources, None, None);
-        let mut rng = rand::thread_rng();
-        for x in -7*16 .. 7*16 {
-            for z in -7*16 .. 7*16 {
-                let h = 5 + (6.0 * (x as f64 / 16.0).cos() * (z as f64 / 16.0).sin()) as i32;
-                for y in 0 .. h {
-                    server.world.set_block(Position::new(x, y, z), block::Dirt{ snowy: false, variant: block::DirtVariant::Normal });
-                }
-                server.world.set_block(Position::new(x, h, z), block::Grass{ snowy: false });
-
-                if x*x + z*z > 16*16 && rng.gen_bool(1.0 / 80.0) {
-                    for i in 0 .. 5 {
-                        server.world.set_block(Position::new(x, h + 1 + i, z), block::Log{ axis: Axis::Y, variant: block::TreeVariant::Oak });
-                    }
-                    for xx in -2 .. 3 {
-                        for zz in -2 .. 3 {
-                            if xx == 0 && z == 0 {
-                                continue;
-                            }
-                            server.world.set_block(Position::new(x + xx, h + 3, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
-                            server.world.set_block(Position::new(x + xx, h + 4, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
-                            if xx.abs() <= 1 && zz.abs() <= 1 {
-                                server.world.set_block(Position::new(x + xx, h + 5, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
-                            }
-                            if xx * xx + zz * zz <= 1 {
-                                server.world.set_block(Position::new(x + xx, h + 6, z + zz), block::Leaves{ variant: block::TreeVariant::Oak, check_decay: false, decayable: false, distance: 1 });
-                            }
-                        }
-                    }
-                }
-            }
+        {
+            let world = &mut server.world;
+            integrated::generate_world(|pos, b| world.set_block(pos, b));
         }
         server
     }
@@ -272,10 +324,20 @@ impl Server {
         entities.add_component(world_entity, game_info, entity::GameInfo::new());
 
         let version = resources.read().unwrap().version();
+        let (resource_pack_answerer, resource_pack_answers) = mpsc::channel();
         Server {
             uuid,
             conn,
             protocol_version,
+            plugin_channels: plugin_messages::Registry::new(),
+            texture_keys: vec![],
+            proxy: None,
+            resource_pack_request: None,
+            resource_pack_answers,
+            resource_pack_answerer,
+            resource_pack_download: None,
+            resource_pack: None,
+            resource_pack_pending: None,
             read_queue,
             disconnect_reason: None,
             just_disconnected: false,
@@ -320,6 +382,10 @@ impl Server {
         if let Some(player) = self.player.take() {
             self.entities.remove_entity(player);
         }
+        self.resource_pack_request = None;
+        self.resource_pack_download = None;
+        self.resource_pack = None;
+        self.resource_pack_pending = None;
         self.just_disconnected = true;
     }
 
@@ -347,6 +413,10 @@ impl Server {
             renderer.camera.pitch = rotation.pitch;
         }
         self.entity_tick(renderer, delta);
+        while let Ok((request, accept)) = self.resource_pack_answers.try_recv() {
+            self.respond_to_resource_pack(request, accept);
+        }
+        self.tick_resource_pack();
 
         self.tick_timer += delta;
         while self.tick_timer >= 3.0 && self.is_connected() {
@@ -380,6 +450,23 @@ impl Server {
             .unwrap().delta = delta;
 
         // Packets modify entities so need to handled here
+        self.read_packets();
+
+        if self.is_connected() || self.just_disconnected { // Allow an extra tick when disconnected to clean up
+            self.just_disconnected = false;
+            self.entity_tick_timer += delta;
+            while self.entity_tick_timer >= 3.0 {
+                self.entities.tick(&mut self.world, renderer);
+                self.entity_tick_timer -= 3.0;
+            }
+
+            self.entities.render_tick(&mut self.world, renderer);
+        }
+    }
+
+    /// Handles the packets read since the last tick, disconnecting
+    /// if reading failed.
+    fn read_packets(&mut self) {
         if let Some(rx) = self.read_queue.take() {
             while let Ok(pck) = rx.try_recv() {
                 match pck {
@@ -388,7 +475,9 @@ impl Server {
                             JoinGame_i32 => on_game_join_i32,
                             JoinGame_i8 => on_game_join_i8,
                             JoinGame_i8_NoDebug => on_game_join_i8_nodebug,
+                            JoinGame_i32_ViewDistance => on_game_join_i32_viewdistance,
                             Respawn => on_respawn,
+                            Respawn_NoDifficulty => on_respawn_nodifficulty,
                             KeepAliveClientbound_i64 => on_keep_alive_i64,
                             KeepAliveClientbound_VarInt => on_keep_alive_varint,
                             KeepAliveClientbound_i32 => on_keep_alive_i32,
@@ -400,6 +489,7 @@ impl Server {
                             ChunkDataBulk => on_chunk_data_bulk,
                             ChunkDataBulk_17 => on_chunk_data_bulk_17,
                             ChunkUnload => on_chunk_unload,
+                            UpdateLight => on_update_light,
                             BlockChange_VarInt => on_block_change_varint,
                             BlockChange_u8 => on_block_change_u8,
                             MultiBlockChange_VarInt => on_multi_block_change_varint,
@@ -415,6 +505,9 @@ impl Server {
                             PlayerInfo => on_player_info,
                             PlayerInfo_String => on_player_info_string,
                             Disconnect => on_disconnect,
+                            ResourcePackSend => on_resource_pack_send,
+                            PluginMessageClientbound => on_plugin_message_clientbound,
+                            PluginMessageClientbound_i16 => on_plugin_message_clientbound_i16,
                             // Entities
                             EntityDestroy => on_entity_destroy,
                             EntityDestroy_u8 => on_entity_destroy_u8,
@@ -422,6 +515,7 @@ impl Server {
                             SpawnPlayer_i32 => on_player_spawn_i32,
                             SpawnPlayer_i32_HeldItem => on_player_spawn_i32_helditem,
                             SpawnPlayer_i32_HeldItem_String => on_player_spawn_i32_helditem_string,
+                            SpawnPlayer_i32_HeldItem_String_NoProperties => on_player_spawn_i32_helditem_string_noproperties,
                             EntityTeleport_f64 => on_entity_teleport_f64,
                             EntityTeleport_i32 => on_entity_teleport_i32,
                             EntityTeleport_i32_i32_NoGround => on_entity_teleport_i32_i32_noground,
@@ -435,7 +529,11 @@ impl Server {
                             EntityLookAndMove_i8_i32_NoGround => on_entity_look_and_move_i8_i32_noground,
                         }
                     },
-                    Err(err) => panic!("Err: {:?}", err),
+                    Err(err) => {
+                        let reason = format!("Lost the connection to the server: {}", err);
+                        error!("{}", reason);
+                        self.disconnect(Some(format::Component::Text(format::TextComponent::new(&reason))));
+                    },
                 }
                 // Disconnected
                 if self.conn.is_none() {
@@ -447,17 +545,6 @@ impl Server {
                 self.read_queue = Some(rx);
             }
         }
-
-        if self.is_connected() || self.just_disconnected { // Allow an extra tick when disconnected to clean up
-            self.just_disconnected = false;
-            self.entity_tick_timer += delta;
-            while self.entity_tick_timer >= 3.0 {
-                self.entities.tick(&mut self.world, renderer);
-                self.entity_tick_timer -= 3.0;
-            }
-
-            self.entities.render_tick(&mut self.world, renderer);
-        }
     }
 
     pub fn remove(&mut self, renderer: &mut render::Renderer) {
@@ -468,6 +555,94 @@ impl Server {
         self.target_info.clear(renderer);
     }
 
+    /// Returns a sender for answering resource pack requests on the
+    /// next tick, when the server itself can't be reached.
+    pub fn resource_pack_answerer(&self) -> mpsc::Sender<(resource_pack::Request, bool)> {
+        self.resource_pack_answerer.clone()
+    }
+
+    /// Answers the server's request to use a resource pack, starting
+    /// the download if it was accepted.
+    pub fn respond_to_resource_pack(&mut self, request: resource_pack::Request, accept: bool) {
+        if !self.is_connected() {
+            return;
+        }
+        if !accept {
+            self.write_resource_pack_status(&request.hash, resource_pack::Status::Declined);
+            return;
+        }
+        self.write_resource_pack_status(&request.hash, resource_pack::Status::Accepted);
+        let (tx, rx) = mpsc::channel();
+        let proxy = self.proxy.clone();
+        let hash = request.hash.clone();
+        thread::spawn(move || {
+            let _ = tx.send(resource_pack::obtain(&request, Path::new(resource_pack::CACHE_DIR), proxy.as_ref()));
+        });
+        self.resource_pack_download = Some((hash, rx));
+    }
+
+    fn tick_resource_pack(&mut self) {
+        let (hash, res) = match self.resource_pack_download {
+            Some((ref hash, ref rx)) => match rx.try_recv() {
+                Ok(res) => (hash.clone(), res),
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => (hash.clone(), Err(protocol::Error::Err("Resource pack download failed".to_owned()))),
+            },
+            None => return,
+        };
+        self.resource_pack_download = None;
+        match res {
+            Ok(location) => {
+                self.resource_pack = Some(location);
+                self.resource_pack_pending = Some(hash);
+            },
+            Err(err) => {
+                warn!("Failed to download the server's resource pack: {}", err);
+                self.write_resource_pack_status(&hash, resource_pack::Status::Failed);
+            },
+        }
+    }
+
+    /// Returns the location of the resource pack this server wants
+    /// used, if it has been downloaded.
+    pub fn resource_pack(&self) -> Option<&Path> {
+        self.resource_pack.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Tells the server whether the pack from `resource_pack` was
+    /// applied, once it has been.
+    pub fn resource_pack_applied(&mut self, res: Result<(), protocol::Error>) {
+        let hash = match self.resource_pack_pending.take() {
+            Some(hash) => hash,
+            None => return,
+        };
+        match res {
+            Ok(()) => self.write_resource_pack_status(&hash, resource_pack::Status::Loaded),
+            Err(err) => {
+                warn!("Failed to load the server's resource pack: {}", err);
+                self.resource_pack = None;
+                self.write_resource_pack_status(&hash, resource_pack::Status::Failed);
+            },
+        }
+    }
+
+    fn write_resource_pack_status(&mut self, hash: &str, status: resource_pack::Status) {
+        if !self.is_connected() {
+            return;
+        }
+        // The hash was dropped from the packet in 1.10
+        if self.protocol_version < 210 {
+            self.write_packet(packet::play::serverbound::ResourcePackStatus_hash {
+                hash: hash.to_owned(),
+                result: protocol::VarInt(status as i32),
+            });
+        } else {
+            self.write_packet(packet::play::serverbound::ResourcePackStatus {
+                result: protocol::VarInt(status as i32),
+            });
+        }
+    }
+
     fn update_time(&mut self, renderer: &mut render::Renderer, delta: f64) {
         if self.tick_time {
             self.world_time_target += delta / 3.0;
@@ -562,11 +737,38 @@ impl Server {
         }
     }
 
+    /// Sends a chat message, or runs a server command when it starts
+    /// with `/`. Messages longer than the server accepts are cut
+    /// short.
+    pub fn send_chat(&mut self, message: &str) {
+        let max_len = if self.protocol_version >= 315 { 256 } else { 100 };
+        let message = message.chars().take(max_len).collect();
+        self.write_packet(packet::play::serverbound::ChatMessage { message });
+    }
+
     pub fn on_right_click(&mut self, renderer: &mut render::Renderer) {
         use crate::shared::Direction;
         if self.player.is_some() {
             if let Some((pos, _, face, at)) = target::trace_ray(&self.world, 4.0, renderer.camera.pos.to_vec(), renderer.view_vector.cast().unwrap(), target::test_block) {
-                if self.protocol_version >= 315 {
+                if self.protocol_version >= 477 {
+                    self.write_packet(packet::play::serverbound::PlayerBlockPlacement_insideblock {
+                        location: pos,
+                        face: protocol::VarInt(match face {
+                            Direction::Down => 0,
+                            Direction::Up => 1,
+                            Direction::North => 2,
+                            Direction::South => 3,
+                            Direction::West => 4,
+                            Direction::East => 5,
+                            _ => unreachable!(),
+                        }),
+                        hand: protocol::VarInt(0),
+                        cursor_x: at.x as f32,
+                        cursor_y: at.y as f32,
+                        cursor_z: at.z as f32,
+                        inside_block: false,
+                    });
+                } else if self.protocol_version >= 315 {
                     self.write_packet(packet::play::serverbound::PlayerBlockPlacement_f32 {
                         location: pos,
                         face: protocol::VarInt(match face {
@@ -641,10 +843,41 @@ impl Server {
         }
     }
 
-    pub fn write_packet<T: protocol::PacketType>(&mut self, p: T) {
+    pub fn write_packet<T: protocol::PacketType + fmt::Debug>(&mut self, p: T) {
         let _ = self.conn.as_mut().unwrap().write_packet(p); // TODO handle errors
     }
 
+    fn write_plugin_message(&mut self, channel: &str, data: Vec<u8>) {
+        if self.protocol_version >= 47 {
+            self.write_packet(packet::play::serverbound::PluginMessageServerbound {
+                channel: channel.into(),
+                data,
+            });
+        } else {
+            self.write_packet(packet::play::serverbound::PluginMessageServerbound_i16 {
+                channel: channel.into(),
+                data: protocol::LenPrefixedBytes::new(data),
+            });
+        }
+    }
+
+    fn on_plugin_message_clientbound(&mut self, msg: packet::play::clientbound::PluginMessageClientbound) {
+        self.on_plugin_message(&msg.channel, &msg.data)
+    }
+
+    fn on_plugin_message_clientbound_i16(&mut self, msg: packet::play::clientbound::PluginMessageClientbound_i16) {
+        self.on_plugin_message(&msg.channel, &msg.data.data)
+    }
+
+    fn on_plugin_message(&mut self, channel: &str, data: &[u8]) {
+        match self.plugin_channels.message(channel, data) {
+            Ok(replies) => for (channel, data) in replies {
+                self.write_plugin_message(&channel, data);
+            },
+            Err(err) => error!("Failed to handle plugin message on {}: {}", channel, err),
+        }
+    }
+
     fn on_keep_alive_i64(&mut self, keep_alive: packet::play::clientbound::KeepAliveClientbound_i64) {
         self.write_packet(packet::play::serverbound::KeepAliveServerbound_i64 {
             id: keep_alive.id,
@@ -675,6 +908,10 @@ impl Server {
         self.on_game_join(join.gamemode, join.entity_id)
     }
 
+    fn on_game_join_i32_viewdistance(&mut self, join: packet::play::clientbound::JoinGame_i32_ViewDistance) {
+        self.on_game_join(join.gamemode, join.entity_id)
+    }
+
 
     fn on_game_join(&mut self, gamemode: u8, entity_id: i32) {
         let gamemode = Gamemode::from_int((gamemode & 0x7) as i32);
@@ -695,15 +932,23 @@ impl Server {
                 brand: "Steven".into(),
             };
         if self.protocol_version >= 47 {
-            self.write_packet(brand.as_message());
+            self.write_packet(brand.as_message(self.protocol_version));
         } else {
             self.write_packet(brand.as_message17());
         }
     }
 
     fn on_respawn(&mut self, respawn: packet::play::clientbound::Respawn) {
+        self.respawn(respawn.gamemode)
+    }
+
+    fn on_respawn_nodifficulty(&mut self, respawn: packet::play::clientbound::Respawn_NoDifficulty) {
+        self.respawn(respawn.gamemode)
+    }
+
+    fn respawn(&mut self, gamemode: u8) {
         self.world = world::World::new(self.protocol_version);
-        let gamemode = Gamemode::from_int((respawn.gamemode & 0x7) as i32);
+        let gamemode = Gamemode::from_int((gamemode & 0x7) as i32);
 
         if let Some(player) = self.player {
             *self.entities.get_component_mut(player, self.gamemode).unwrap() = gamemode;
@@ -716,6 +961,13 @@ impl Server {
         self.disconnect(Some(disconnect.reason));
     }
 
+    fn on_resource_pack_send(&mut self, pack: packet::play::clientbound::ResourcePackSend) {
+        self.resource_pack_request = Some(resource_pack::Request {
+            url: pack.url,
+            hash: pack.hash,
+        });
+    }
+
     fn on_time_update(&mut self, time_update: packet::play::clientbound::TimeUpdate) {
         self.world_age = time_update.time_of_day;
         self.world_time_target = (time_update.time_of_day % 24000) as f64;
@@ -867,6 +1119,10 @@ impl Server {
         self.on_player_spawn(spawn.entity_id.0, protocol::UUID::from_str(&spawn.uuid), spawn.x as f64, spawn.y as f64, spawn.z as f64, spawn.yaw as f64, spawn.pitch as f64)
     }
 
+    fn on_player_spawn_i32_helditem_string_noproperties(&mut self, spawn: packet::play::clientbound::SpawnPlayer_i32_HeldItem_String_NoProperties) {
+        self.on_player_spawn(spawn.entity_id.0, protocol::UUID::from_str(&spawn.uuid), spawn.x as f64, spawn.y as f64, spawn.z as f64, spawn.yaw as f64, spawn.pitch as f64)
+    }
+
     fn on_player_spawn(&mut self, entity_id: i32, uuid: protocol::UUID, x: f64, y: f64, z: f64, pitch: f64, yaw: f64) {
         use std::f64::consts::PI;
         if let Some(entity) = self.entity_map.remove(&entity_id) {
@@ -1007,8 +1263,6 @@ impl Server {
 
     fn on_player_info(&mut self, player_info: packet::play::clientbound::PlayerInfo) {
         use crate::protocol::packet::PlayerDetail::*;
-        use base64;
-        use serde_json;
         for detail in player_info.inner.players {
             match detail {
                 Add { name, uuid, properties, display, gamemode, ping} => {
@@ -1016,6 +1270,7 @@ impl Server {
                         name: name.clone(),
                         uuid,
                         skin_url: None,
+                        textures_signature: mojang::TexturesSignature::Missing,
 
                         display_name: display.clone(),
                         ping: ping.0,
@@ -1030,30 +1285,16 @@ impl Server {
                         if prop.name != "textures" {
                             continue;
                         }
-                        // Ideally we would check the signature of the blob to
-                        // verify it was from Mojang and not faked by the server
-                        // but this requires the public key which is distributed
-                        // authlib. We could download authlib on startup and extract
-                        // the key but this seems like overkill compared to just
-                        // whitelisting Mojang's texture servers instead.
-                        let skin_blob_result = &base64::decode(&prop.value);
-                        let skin_blob = match skin_blob_result {
-                            Ok(val) => val,
-                            Err(err) => {
-                                error!("Failed to decode skin blob, {:?}", err);
-                                continue;
-                            },
-                        };
-                        let skin_blob: serde_json::Value = match serde_json::from_slice(&skin_blob) {
-                            Ok(val) => val,
-                            Err(err) => {
-                                error!("Failed to parse skin blob, {:?}", err);
-                                continue;
-                            },
-                        };
-                        if let Some(skin_url) = skin_blob.pointer("/textures/SKIN/url").and_then(|v| v.as_str()) {
-                            info.skin_url = Some(skin_url.to_owned());
+                        // Only trust textures signed by the session server,
+                        // anything else could point us at any url.
+                        let (signature, skin_url) = mojang::verify_textures(&prop.value, prop.signature.as_ref().map(|v| &v[..]), &self.texture_keys);
+                        match signature {
+                            mojang::TexturesSignature::Invalid => warn!("Ignoring textures with an invalid signature for {}", info.name),
+                            mojang::TexturesSignature::Unsigned => debug!("Ignoring unsigned textures for {}", info.name),
+                            _ => {},
                         }
+                        info.textures_signature = signature;
+                        info.skin_url = skin_url;
                     }
 
                     // Refresh our own skin when the server sends it to us.
@@ -1133,6 +1374,20 @@ impl Server {
         self.load_block_entities(chunk_data.block_entities.data);
     }
 
+    fn on_update_light(&mut self, update_light: packet::play::clientbound::UpdateLight) {
+        let (x, z) = (update_light.chunk_x.0, update_light.chunk_z.0);
+        let res = self.world.load_light(
+            x,
+            z,
+            update_light.sky_light_mask.0,
+            update_light.block_light_mask.0,
+            update_light.light_arrays,
+        );
+        if let Err(err) = res {
+            error!("Ignoring light update for chunk {},{}: {}", x, z, err);
+        }
+    }
+
     fn on_chunk_data_no_entities(&mut self, chunk_data: packet::play::clientbound::ChunkData_NoEntities) {
         self.world.load_chunk19(
             chunk_data.chunk_x,
@@ -1241,3 +1496,42 @@ fn calculate_relative_teleport(flag: TeleportFlag, flags: u8, base: f64, val: f6
         base + val
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn silent_servers_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // Accepts the connection but never sends anything
+        let stub = thread::spawn(move || listener.accept().unwrap());
+        let options = protocol::ConnectOptions {
+            read_timeout: Some(Duration::from_millis(50)),
+            proxy: None,
+            ..Default::default()
+        };
+        let protocol_version = protocol::SUPPORTED_PROTOCOLS[0];
+        let conn = protocol::Conn::connect(&address, protocol_version, &options).unwrap();
+        let _stream = stub.join().unwrap();
+
+        let (resources, _) = resources::Manager::new(&resources::LocalAssets {
+            client_jar: Some(PathBuf::from("missing-client.jar")),
+            assets_dir: Some(PathBuf::from("missing-assets")),
+        });
+        let read_queue = Server::spawn_reader(conn.clone());
+        let mut server = Server::new(protocol_version, protocol::UUID::default(), Arc::new(RwLock::new(resources)), Some(conn), Some(read_queue));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.is_connected() && Instant::now() < deadline {
+            server.read_packets();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!server.is_connected());
+        let reason = server.disconnect_reason.as_ref().map(|v| v.to_string()).unwrap();
+        assert!(reason.starts_with("Lost the connection to the server"), "{}", reason);
+    }
+}
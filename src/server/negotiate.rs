@@ -0,0 +1,174 @@
+// Works out which protocol version to speak to a server before
+// connecting to it. The server is pinged first and the version it
+// reports is matched against the versions we support. Reported
+// versions are remembered (in memory and in server_versions.json)
+// so that a server which doesn't answer pings can still be joined
+// with the version it used last time. The Forge mod list from the
+// ping is kept as well (in memory only) for the FML handshake, servers
+// that haven't been pinged yet are pinged for it when connecting.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use crate::protocol;
+
+const CACHE_FILE: &str = "server_versions.json";
+
+lazy_static! {
+    static ref KNOWN_VERSIONS: Mutex<HashMap<String, i32>> = Mutex::new(load_cache());
+    /// The mods of every server pinged, `None` for vanilla servers
+    static ref FORGE_SERVERS: Mutex<HashMap<String, Option<protocol::ForgeInfo>>> = Mutex::new(HashMap::new());
+}
+
+fn load_cache() -> HashMap<String, i32> {
+    let file = match fs::File::open(CACHE_FILE) {
+        Ok(val) => val,
+        Err(_) => return HashMap::new(),
+    };
+    let info: serde_json::Value = match serde_json::from_reader(file) {
+        Ok(val) => val,
+        Err(err) => {
+            warn!("Ignoring invalid {}: {}", CACHE_FILE, err);
+            return HashMap::new();
+        },
+    };
+    info.as_object()
+        .map(|entries| entries.iter()
+            .filter_map(|(address, version)| Some((address.clone(), version.as_i64()? as i32)))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Returns the protocol version the server at `address` last
+/// reported, if it has been pinged before.
+pub fn known_version(address: &str) -> Option<i32> {
+    KNOWN_VERSIONS.lock().unwrap().get(address).cloned()
+}
+
+/// Remembers the protocol version reported by the server at `address`.
+pub fn set_known_version(address: &str, protocol_version: i32) {
+    let mut versions = KNOWN_VERSIONS.lock().unwrap();
+    if versions.get(address) == Some(&protocol_version) {
+        return;
+    }
+    versions.insert(address.to_owned(), protocol_version);
+    let res = fs::File::create(CACHE_FILE)
+        .map_err(protocol::Error::from)
+        .and_then(|mut out| Ok(serde_json::to_writer_pretty(&mut out, &*versions)?));
+    if let Err(err) = res {
+        warn!("Failed to save {}: {}", CACHE_FILE, err);
+    }
+}
+
+/// Returns the Forge mods of the server at `address`, if it is a
+/// Forge server. Servers that haven't been pinged yet, such as ones
+/// with a pinned protocol version, are pinged first. If that fails
+/// the server is assumed to be a vanilla one.
+pub fn forge_info(address: &str, options: &protocol::ConnectOptions) -> Option<protocol::ForgeInfo> {
+    if let Some(forge) = FORGE_SERVERS.lock().unwrap().get(address) {
+        return forge.clone();
+    }
+    match ping(address, options) {
+        Ok(status) => status.forge,
+        Err(err) => {
+            warn!("Failed to ping {} for its mods ({}), connecting without Forge", address, err);
+            None
+        },
+    }
+}
+
+/// Remembers whether the server at `address` runs Forge.
+pub fn set_forge_info(address: &str, forge: Option<&protocol::ForgeInfo>) {
+    FORGE_SERVERS.lock().unwrap().insert(address.to_owned(), forge.cloned());
+}
+
+/// Pings the server at `address`, remembering its mods.
+fn ping(address: &str, options: &protocol::ConnectOptions) -> Result<protocol::Status, protocol::Error> {
+    // The ping shouldn't end up in the capture of the real connection
+    let ping_options = protocol::ConnectOptions {
+        capture: None,
+        ..options.clone()
+    };
+    let (status, _) = protocol::Conn::ping(address, protocol::SUPPORTED_PROTOCOLS[0], &ping_options)?;
+    set_forge_info(address, status.forge.as_ref());
+    Ok(status)
+}
+
+/// Pings the server at `address` and picks the protocol version to
+/// connect with. If the ping fails the last known version is used
+/// instead, failing that the ping's error is returned.
+pub fn negotiate(address: &str, options: &protocol::ConnectOptions) -> Result<i32, protocol::Error> {
+    let server_version = match ping(address, options) {
+        Ok(status) => {
+            set_known_version(address, status.version.protocol);
+            status.version.protocol
+        },
+        Err(err) => match known_version(address) {
+            Some(version) => {
+                warn!("Failed to ping {} ({}), using last known protocol version {}", address, err, version);
+                version
+            },
+            None => return Err(err),
+        },
+    };
+    let version = protocol::versions::best_protocol_version(server_version)
+        .ok_or(protocol::Error::UnsupportedVersion(server_version))?;
+    if version != server_version {
+        info!("Server {} uses protocol version {}, connecting with {}", address, server_version, version);
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+    use crate::format;
+    use crate::protocol::listener::{Incoming, Listener};
+
+    #[test]
+    fn pings_for_forge_info() {
+        let listener = Listener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // Only answers a single ping, later lookups must be cached
+        let server = thread::spawn(move || {
+            match listener.accept().unwrap().handshake().unwrap() {
+                Incoming::Status(req) => req.respond(&protocol::Status {
+                    version: protocol::StatusVersion {
+                        name: "1.12.2".to_owned(),
+                        protocol: 340,
+                    },
+                    players: protocol::StatusPlayers {
+                        max: 20,
+                        online: 0,
+                        sample: vec![],
+                    },
+                    description: format::Component::Text(format::TextComponent::new("")),
+                    favicon: None,
+                    forge: Some(protocol::ForgeInfo {
+                        fml_network_version: 1,
+                        mods: vec![protocol::ForgeMod {
+                            id: "forge".to_owned(),
+                            version: "14.23.5.2768".to_owned(),
+                        }],
+                    }),
+                }).unwrap(),
+                Incoming::Login(_) => panic!("expected a ping"),
+            }
+        });
+        let options = protocol::ConnectOptions {
+            proxy: None,
+            ..Default::default()
+        };
+        for _ in 0 .. 2 {
+            let forge = forge_info(&address, &options).unwrap();
+            assert_eq!(forge.mods[0].id, "forge");
+        }
+        server.join().unwrap();
+
+        set_forge_info(&address, None);
+        assert!(forge_info(&address, &options).is_none());
+    }
+}
@@ -1,5 +1,11 @@
 
-use crate::protocol::Serializable;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use byteorder::{BigEndian, ReadBytesExt};
+use log::debug;
+use crate::protocol::{Error, Serializable};
+use crate::protocol::packet::login::clientbound::LoginPluginRequest;
+use crate::protocol::packet::login::serverbound::LoginPluginResponse;
 use crate::protocol::packet::play::serverbound::PluginMessageServerbound;
 use crate::protocol::packet::play::serverbound::PluginMessageServerbound_i16;
 
@@ -8,10 +14,8 @@ pub struct Brand {
 }
 
 impl Brand {
-    pub fn as_message(self) -> PluginMessageServerbound {
-        let protocol_version = unsafe { crate::protocol::CURRENT_PROTOCOL_VERSION };
-
-        let channel_name = if protocol_version >= 404 {
+    pub fn as_message(self, protocol_version: i32) -> PluginMessageServerbound {
+        let channel_name = if protocol_version >= 393 {
             "minecraft:brand"
         } else {
             "MC|Brand"
@@ -36,3 +40,165 @@ impl Brand {
     }
 
 }
+
+/// Handles the messages the server sends on a plugin channel,
+/// returning the messages (channel and data) to reply with.
+pub type Handler = Box<dyn FnMut(&[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> + Send>;
+/// Handles a login plugin request, returning the response data or
+/// `None` if the request isn't understood.
+pub type LoginHandler = Box<dyn FnMut(&[u8]) -> Option<Vec<u8>> + Send>;
+
+/// Dispatches plugin messages, both the login plugin requests sent
+/// by 1.13+ servers and the plugin messages sent during play, to the
+/// handler registered for their channel.
+///
+/// `REGISTER` and `UNREGISTER` are handled by the registry itself to
+/// keep track of the channels the server listens on.
+pub struct Registry {
+    handlers: HashMap<String, Handler>,
+    login_handlers: HashMap<String, LoginHandler>,
+    server_channels: HashSet<String>,
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+    }
+}
+
+impl Registry {
+    /// Creates a registry with the default handlers registered.
+    pub fn new() -> Registry {
+        let mut registry = Registry {
+            handlers: HashMap::new(),
+            login_handlers: HashMap::new(),
+            server_channels: HashSet::new(),
+        };
+        registry.register("BungeeCord", Box::new(on_bungeecord));
+        registry.register("bungeecord:main", Box::new(on_bungeecord));
+        registry
+    }
+
+    /// Registers `handler` for play messages on `channel`, replacing
+    /// any handler already registered for it.
+    pub fn register(&mut self, channel: &str, handler: Handler) {
+        self.handlers.insert(channel.to_owned(), handler);
+    }
+
+    /// Registers `handler` for login plugin requests on `channel`,
+    /// replacing any handler already registered for it.
+    pub fn register_login(&mut self, channel: &str, handler: LoginHandler) {
+        self.login_handlers.insert(channel.to_owned(), handler);
+    }
+
+    /// Returns whether the server has registered `channel`.
+    pub fn server_has_channel(&self, channel: &str) -> bool {
+        self.server_channels.contains(channel)
+    }
+
+    /// Handles a login plugin request, returning the response to
+    /// send. Requests without a handler aren't understood.
+    pub fn login_request(&mut self, request: LoginPluginRequest) -> LoginPluginResponse {
+        let data = match self.login_handlers.get_mut(&request.channel) {
+            Some(handler) => handler(&request.data),
+            None => {
+                debug!("Not understood login plugin request on {} ({} bytes)", request.channel, request.data.len());
+                None
+            },
+        };
+        LoginPluginResponse {
+            message_id: request.message_id,
+            successful: data.is_some(),
+            data: data.unwrap_or_default(),
+        }
+    }
+
+    /// Handles a plugin message sent during play, returning the
+    /// messages to reply with.
+    pub fn message(&mut self, channel: &str, data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        match channel {
+            "REGISTER" | "minecraft:register" => {
+                self.server_channels.extend(channel_list(data));
+                Ok(vec![])
+            },
+            "UNREGISTER" | "minecraft:unregister" => {
+                for channel in channel_list(data) {
+                    self.server_channels.remove(&channel);
+                }
+                Ok(vec![])
+            },
+            _ => match self.handlers.get_mut(channel) {
+                Some(handler) => handler(data),
+                None => {
+                    debug!("Ignoring plugin message on {} ({} bytes)", channel, data.len());
+                    Ok(vec![])
+                },
+            },
+        }
+    }
+}
+
+/// Splits the null separated list of channels sent with `REGISTER`
+/// and `UNREGISTER`.
+fn channel_list(data: &[u8]) -> Vec<String> {
+    data.split(|b| *b == 0)
+        .filter(|v| !v.is_empty())
+        .map(|v| String::from_utf8_lossy(v).into_owned())
+        .collect()
+}
+
+/// BungeeCord messages are meant for the proxy so only the
+/// subchannel is logged.
+fn on_bungeecord(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let mut data = io::Cursor::new(data);
+    // Written with Java's writeUTF, a u16 length followed by the string
+    let len = data.read_u16::<BigEndian>()?;
+    let mut subchannel = vec![0; len as usize];
+    data.read_exact(&mut subchannel)?;
+    debug!("BungeeCord message: {}", String::from_utf8_lossy(&subchannel));
+    Ok(vec![])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::VarInt;
+
+    #[test]
+    fn login_requests() {
+        let mut registry = Registry::new();
+        registry.register_login("example:echo", Box::new(|data| Some(data.to_vec())));
+        let response = registry.login_request(LoginPluginRequest {
+            message_id: VarInt(3),
+            channel: "example:echo".to_owned(),
+            data: vec![1, 2, 3],
+        });
+        assert_eq!(response.message_id.0, 3);
+        assert!(response.successful);
+        assert_eq!(response.data, vec![1, 2, 3]);
+
+        let response = registry.login_request(LoginPluginRequest {
+            message_id: VarInt(4),
+            channel: "velocity:player_info".to_owned(),
+            data: vec![1],
+        });
+        assert_eq!(response.message_id.0, 4);
+        assert!(!response.successful);
+        assert!(response.data.is_empty());
+    }
+
+    #[test]
+    fn play_messages() {
+        let mut registry = Registry::new();
+        registry.message("REGISTER", b"example:a\0example:b").unwrap();
+        assert!(registry.server_has_channel("example:a"));
+        registry.message("minecraft:unregister", b"example:a").unwrap();
+        assert!(!registry.server_has_channel("example:a"));
+        assert!(registry.server_has_channel("example:b"));
+
+        registry.register("example:ping", Box::new(|data| Ok(vec![("example:pong".to_owned(), data.to_vec())])));
+        assert_eq!(registry.message("example:ping", &[7]).unwrap(), vec![("example:pong".to_owned(), vec![7])]);
+        assert!(registry.message("example:unknown", &[7]).unwrap().is_empty());
+        assert!(registry.message("BungeeCord", b"\0\x07Connect").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+// Resource packs sent by servers with `ResourcePackSend`.
+//
+// Packs are downloaded, checked against the SHA-1 hash the server
+// sent and cached in `server-resource-packs/` by that hash, so that
+// rejoining a server doesn't download its pack again.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use sha1::{self, Digest};
+use log::warn;
+use crate::protocol::{proxy, Error};
+
+/// Where downloaded packs are kept
+pub const CACHE_DIR: &str = "./server-resource-packs";
+/// Packs larger than this are refused, as the vanilla client does
+const MAX_SIZE: u64 = 50 * 1024 * 1024;
+
+/// What to do when a server sends a resource pack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    Prompt,
+    Enabled,
+    Disabled,
+}
+
+impl Policy {
+    pub fn parse(val: &str) -> Policy {
+        match val {
+            "enabled" => Policy::Enabled,
+            "disabled" => Policy::Disabled,
+            _ => Policy::Prompt,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Policy::Prompt => "prompt",
+            Policy::Enabled => "enabled",
+            Policy::Disabled => "disabled",
+        }
+    }
+
+    /// Returns the policy after this one, for cycling through them.
+    pub fn next(self) -> Policy {
+        match self {
+            Policy::Prompt => Policy::Enabled,
+            Policy::Enabled => Policy::Disabled,
+            Policy::Disabled => Policy::Prompt,
+        }
+    }
+}
+
+/// The result sent back in `ResourcePackStatus`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Loaded = 0,
+    Declined = 1,
+    Failed = 2,
+    Accepted = 3,
+}
+
+/// A resource pack the server asked us to use.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub url: String,
+    pub hash: String,
+}
+
+/// Returns whether `hash` is a SHA-1 hash we can check a pack
+/// against. Some servers send an empty or malformed one.
+fn valid_hash(hash: &str) -> bool {
+    hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Returns the location of the pack for `request`, downloading it
+/// into `cache_dir` unless it was already.
+pub fn obtain(request: &Request, cache_dir: &Path, proxy: Option<&proxy::Proxy>) -> Result<PathBuf, Error> {
+    let hash = request.hash.to_lowercase();
+    let checked = valid_hash(&hash);
+    if checked {
+        let location = cache_dir.join(format!("{}.zip", hash));
+        if location.is_file() {
+            return Ok(location);
+        }
+    } else {
+        warn!("Server sent an invalid resource pack hash {:?}, not checking it", request.hash);
+    }
+    if !request.url.starts_with("http://") && !request.url.starts_with("https://") {
+        return Err(Error::Err(format!("Unsupported resource pack url {}", request.url)));
+    }
+
+    let client = proxy::http_client(proxy)?;
+    let res = client.get(&request.url).send()?;
+    if !res.status().is_success() {
+        return Err(Error::Err(format!("Resource pack download failed with {}", res.status())));
+    }
+    let mut data = vec![];
+    res.take(MAX_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_SIZE {
+        return Err(Error::Err("Resource pack is too large".to_owned()));
+    }
+
+    let mut hasher = sha1::Sha1::new();
+    hasher.input(&data);
+    let actual = hex::encode(hasher.result());
+    if checked && actual != hash {
+        return Err(Error::Err(format!("Resource pack hash mismatch, expected {} but got {}", hash, actual)));
+    }
+
+    fs::create_dir_all(cache_dir)?;
+    let location = cache_dir.join(format!("{}.zip", actual));
+    let tmp = cache_dir.join(format!("{}.tmp", actual));
+    fs::File::create(&tmp)?.write_all(&data)?;
+    fs::rename(&tmp, &location)?;
+    Ok(location)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_http;
+    use std::thread;
+
+    /// Serves `body` to each of `count` requests.
+    fn serve(body: &'static [u8], count: usize) -> (String, thread::JoinHandle<Vec<mock_http::Request>>) {
+        let (url, handle) = mock_http::serve(count, move |_| ("200 OK", body.to_vec()));
+        (url + "/pack.zip", handle)
+    }
+
+    #[test]
+    fn download_and_cache() {
+        const PACK: &[u8] = b"not really a zip";
+        const HASH: &str = "240db3986ff0c20e0de52ae69943b98496cb779b";
+        let cache = std::env::temp_dir().join(format!("steven-server-packs-{}", std::process::id()));
+        let (url, handle) = serve(PACK, 2);
+
+        let bad = Request { url: url.clone(), hash: "0".repeat(40) };
+        assert!(obtain(&bad, &cache, None).is_err());
+
+        let request = Request { url, hash: HASH.to_uppercase() };
+        let location = obtain(&request, &cache, None).unwrap();
+        assert_eq!(location, cache.join(format!("{}.zip", HASH)));
+        assert_eq!(fs::read(&location).unwrap(), PACK);
+        handle.join().unwrap();
+
+        // Cached, so the server isn't asked again
+        assert_eq!(obtain(&request, &cache, None).unwrap(), location);
+        fs::remove_dir_all(&cache).unwrap();
+
+        let file = Request { url: "file:///etc/passwd".to_owned(), hash: String::new() };
+        assert!(obtain(&file, &cache, None).is_err());
+    }
+
+    #[test]
+    fn policies() {
+        for &policy in &[Policy::Prompt, Policy::Enabled, Policy::Disabled] {
+            assert_eq!(Policy::parse(policy.as_str()), policy);
+        }
+        assert_eq!(Policy::parse(""), Policy::Prompt);
+    }
+}
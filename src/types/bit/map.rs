@@ -67,6 +67,10 @@ impl Map {
         }
     }
 
+    pub fn into_raw(self) -> Vec<u64> {
+        self.bits
+    }
+
     pub fn resize(&self, size: usize) -> Map {
         let mut n = Map::new(self.length, size);
         for i in 0..self.length {
@@ -322,6 +322,11 @@ impl Metadata {
                 }
                 15 => panic!("TODO: particle"),
                 16 => m.put_raw(index, VillagerData::read_from(buf)?),
+                17 => {
+                    let val = protocol::VarInt::read_from(buf)?.0;
+                    m.put_raw::<Option<i32>>(index, if val == 0 { None } else { Some(val - 1) });
+                }
+                18 => m.put_raw(index, PoseData::read_from(buf)?),
                 _ => return Err(protocol::Error::Err("unknown metadata type".to_owned())),
             }
         }
@@ -405,6 +410,14 @@ impl Metadata {
                     u8::write_to(&16, buf)?;
                     val.write_to(buf)?;
                 }
+                Value::OptionalVarInt(ref val) => {
+                    u8::write_to(&17, buf)?;
+                    protocol::VarInt(val.map_or(0, |v| v + 1)).write_to(buf)?;
+                }
+                Value::Pose(ref val) => {
+                    u8::write_to(&18, buf)?;
+                    val.write_to(buf)?;
+                }
                 _ => panic!("unexpected metadata"),
             }
         }
@@ -417,9 +430,9 @@ impl Metadata {
 
 impl Serializable for Metadata {
     fn read_from<R: io::Read>(buf: &mut R) -> Result<Self, protocol::Error> {
-        let protocol_version = unsafe { protocol::CURRENT_PROTOCOL_VERSION };
+        let protocol_version = protocol::current_protocol_version();
 
-        if protocol_version >= 404 {
+        if protocol_version >= 393 {
             Metadata::read_from113(buf)
         } else if protocol_version >= 74 {
             Metadata::read_from19(buf)
@@ -429,9 +442,9 @@ impl Serializable for Metadata {
     }
 
     fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), protocol::Error> {
-        let protocol_version = unsafe { protocol::CURRENT_PROTOCOL_VERSION };
+        let protocol_version = protocol::current_protocol_version();
 
-        if protocol_version >= 404 {
+        if protocol_version >= 393 {
             self.write_to113(buf)
         } else if protocol_version >= 74 {
             self.write_to19(buf)
@@ -478,6 +491,8 @@ pub enum Value {
     NBTTag(nbt::NamedTag),
     Particle(ParticleData),
     Villager(VillagerData),
+    OptionalVarInt(Option<i32>),
+    Pose(PoseData),
 }
 
 #[derive(Debug)]
@@ -639,6 +654,35 @@ impl Serializable for VillagerData {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoseData {
+    Standing,
+    FallFlying,
+    Sleeping,
+    Swimming,
+    SpinAttack,
+    Sneaking,
+    Dying,
+}
+
+impl Serializable for PoseData {
+    fn read_from<R: io::Read>(buf: &mut R) -> Result<Self, protocol::Error> {
+        Ok(match protocol::VarInt::read_from(buf)?.0 {
+            0 => PoseData::Standing,
+            1 => PoseData::FallFlying,
+            2 => PoseData::Sleeping,
+            3 => PoseData::Swimming,
+            4 => PoseData::SpinAttack,
+            5 => PoseData::Sneaking,
+            6 => PoseData::Dying,
+            _ => return Err(protocol::Error::Err("unknown pose".to_owned())),
+        })
+    }
+
+    fn write_to<W: io::Write>(&self, buf: &mut W) -> Result<(), protocol::Error> {
+        protocol::VarInt(*self as i32).write_to(buf)
+    }
+}
 
 pub trait MetaValue {
     fn unwrap(_: &Value) -> &Self;
@@ -850,6 +894,30 @@ impl MetaValue for nbt::NamedTag {
     }
 }
 
+impl MetaValue for Option<i32> {
+    fn unwrap(value: &Value) -> &Self {
+        match *value {
+            Value::OptionalVarInt(ref val) => val,
+            _ => panic!("incorrect key"),
+        }
+    }
+    fn wrap(self) -> Value {
+        Value::OptionalVarInt(self)
+    }
+}
+
+impl MetaValue for PoseData {
+    fn unwrap(value: &Value) -> &Self {
+        match *value {
+            Value::Pose(ref val) => val,
+            _ => panic!("incorrect key"),
+        }
+    }
+    fn wrap(self) -> Value {
+        Value::Pose(self)
+    }
+}
+
 impl MetaValue for VillagerData {
     fn unwrap(value: &Value) -> &Self {
         match *value {
@@ -886,4 +954,16 @@ mod test {
             None => panic!("failed"),
         }
     }
+
+    #[test]
+    fn optional_varint_and_pose() {
+        // index, type, value ... 0xFF
+        let data = vec![6, 18, 2, 7, 17, 0, 8, 17, 5, 0xFF];
+        let m = protocol::with_protocol_version(498, || {
+            Metadata::read_from(&mut io::Cursor::new(data))
+        }).unwrap();
+        assert_eq!(m.get(&MetadataKey::<PoseData>::new(6)), Some(&PoseData::Sleeping));
+        assert_eq!(m.get(&MetadataKey::<Option<i32>>::new(7)), Some(&None));
+        assert_eq!(m.get(&MetadataKey::<Option<i32>>::new(8)), Some(&Some(4)));
+    }
 }
@@ -0,0 +1,147 @@
+// Copyright 2016 Matthew Collins
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Watches directories for changes, for reloading resource packs and
+// shaders whilst working on them.
+//
+// The directories are polled on another thread rather than using
+// the platform's notification api as only a handful of small
+// directories are ever watched and only when asked for.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+pub struct Watcher {
+    roots: Arc<Mutex<Vec<PathBuf>>>,
+    changed: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    pub fn new(roots: Vec<PathBuf>) -> Watcher {
+        Watcher::start(roots, POLL_INTERVAL)
+    }
+
+    fn start(roots: Vec<PathBuf>, interval: Duration) -> Watcher {
+        let watcher = Watcher {
+            roots: Arc::new(Mutex::new(roots)),
+            changed: Arc::new(AtomicBool::new(false)),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let roots = watcher.roots.clone();
+        let changed = watcher.changed.clone();
+        let running = watcher.running.clone();
+        thread::spawn(move || {
+            let mut watched = roots.lock().unwrap().clone();
+            let mut last = snapshot(&watched);
+            while running.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                let current_roots = roots.lock().unwrap().clone();
+                let current = snapshot(&current_roots);
+                // A change of roots is the caller's doing, so it
+                // isn't reported
+                if current_roots == watched && current != last {
+                    changed.store(true, Ordering::Relaxed);
+                }
+                watched = current_roots;
+                last = current;
+            }
+        });
+        watcher
+    }
+
+    /// Replaces the directories being watched.
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        *self.roots.lock().unwrap() = roots;
+    }
+
+    /// Returns whether anything changed since this was last called.
+    pub fn changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn snapshot(roots: &[PathBuf]) -> Snapshot {
+    let mut files = HashMap::new();
+    for root in roots {
+        visit(root, &mut files);
+    }
+    files
+}
+
+fn visit(dir: &Path, files: &mut Snapshot) {
+    let entries = match fs::read_dir(dir) {
+        Ok(val) => val,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let meta = match entry.metadata() {
+            Ok(val) => val,
+            Err(_) => continue,
+        };
+        if meta.is_dir() {
+            visit(&path, files);
+        } else {
+            files.insert(path, (meta.modified().ok(), meta.len()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn notices_changes() {
+        let interval = Duration::from_millis(20);
+        let wait = || thread::sleep(interval * 5);
+        let dir = std::env::temp_dir().join(format!("steven-watcher-{}", std::process::id()));
+        fs::create_dir_all(dir.join("textures")).unwrap();
+        let watcher = Watcher::start(vec![dir.clone()], interval);
+        wait();
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("textures").join("stone.png"), b"stone").unwrap();
+        wait();
+        assert!(watcher.changed());
+        // Only reported once
+        assert!(!watcher.changed());
+
+        fs::write(dir.join("textures").join("stone.png"), b"granite").unwrap();
+        wait();
+        assert!(watcher.changed());
+
+        watcher.set_roots(vec![]);
+        wait();
+        assert!(!watcher.changed());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -44,9 +44,18 @@ pub struct World {
 
     block_entity_actions: VecDeque<BlockEntityAction>,
 
+    /// Light sent (1.14+) before the chunk it belongs to was loaded
+    pending_light: HashMap<CPos, PendingLight, BuildHasherDefault<FNVHash>>,
+
     protocol_version: i32,
 }
 
+struct PendingLight {
+    sky_mask: i32,
+    block_mask: i32,
+    data: Vec<u8>,
+}
+
 #[derive(Clone, Debug)]
 pub enum BlockEntityAction {
     Create(Position),
@@ -547,6 +556,7 @@ impl World {
     }
 
     pub fn unload_chunk(&mut self, x: i32, z: i32, m: &mut ecs::Manager) {
+        self.pending_light.remove(&CPos(x, z));
         if let Some(chunk) = self.chunks.remove(&CPos(x, z)) {
             for entity in chunk.block_entities.values() {
                 m.remove_entity(*entity);
@@ -870,7 +880,7 @@ impl World {
                 section.dirty = true;
  
                 if self.protocol_version >= 451 {
-                    let _block_count = data.read_u16::<byteorder::LittleEndian>()?;
+                    let _block_count = data.read_i16::<byteorder::BigEndian>()?;
                     // TODO: use block_count
                 }
 
@@ -878,6 +888,8 @@ impl World {
                 let mut mappings: HashMap<usize, block::Block, BuildHasherDefault<FNVHash>> = HashMap::with_hasher(BuildHasherDefault::default());
                 if bit_size == 0 {
                     bit_size = 13;
+                } else if self.protocol_version >= 477 && bit_size > 8 {
+                    // Direct palette, 1.14 doesn't send an (empty) palette
                 } else {
                     let count = VarInt::read_from(&mut data)?.0;
                     for i in 0 .. count {
@@ -923,6 +935,54 @@ impl World {
             chunk.calculate_heightmap();
         }
 
+        if let Some(light) = self.pending_light.remove(&cpos) {
+            self.load_light(x, z, light.sky_mask, light.block_mask, light.data)?;
+        }
+
+        self.dirty_chunks_by_bitmask(x, z, mask);
+        Ok(())
+    }
+
+    /// Loads the light arrays of an UpdateLight packet (1.14+). Bit 0
+    /// of each mask is the section below the world and bit 17 the one
+    /// above it, neither of which are stored.
+    pub fn load_light(&mut self, x: i32, z: i32, sky_mask: i32, block_mask: i32, data: Vec<u8>) -> Result<(), protocol::Error> {
+        use std::io::Cursor;
+        use crate::protocol::{VarInt, Serializable, LenPrefixedBytes};
+
+        let cpos = CPos(x, z);
+        let chunk = match self.chunks.get_mut(&cpos) {
+            Some(chunk) => chunk,
+            None => {
+                self.pending_light.insert(cpos, PendingLight { sky_mask, block_mask, data });
+                return Ok(());
+            },
+        };
+
+        let mut data = Cursor::new(data);
+        let mut mask = 0u16;
+        for &(light_mask, sky) in &[(sky_mask, true), (block_mask, false)] {
+            for bit in 0 .. 18usize {
+                if light_mask & (1 << bit) == 0 {
+                    continue;
+                }
+                let light = LenPrefixedBytes::<VarInt>::read_from(&mut data)?.data;
+                if bit == 0 || bit == 17 {
+                    continue;
+                }
+                let i = bit - 1;
+                if let Some(section) = chunk.sections[i].as_mut() {
+                    let array = if sky { &mut section.sky_light } else { &mut section.block_light };
+                    if light.len() != array.data.len() {
+                        return Err(protocol::Error::Err(format!("light array has length {}", light.len())));
+                    }
+                    array.data.copy_from_slice(&light);
+                    section.dirty = true;
+                    mask |= 1 << i;
+                }
+            }
+        }
+
         self.dirty_chunks_by_bitmask(x, z, mask);
         Ok(())
     }